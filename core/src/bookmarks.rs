@@ -0,0 +1,153 @@
+use crate::geometry::Rect;
+use crate::util::*;
+use crate::zooming::*;
+use serde::{Deserialize, Serialize};
+
+/// A named camera view that can be flown back to later, e.g. one step of an explanation
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    camera: ZoomTransform,
+    /// Size of the screen the camera was looking through, so the same part of the board can be
+    /// shown on a differently sized screen
+    screen_size: V2f64,
+}
+
+impl Bookmark {
+    pub fn new(name: String, camera: ZoomTransform, screen_size: V2f64) -> Self {
+        Self {
+            name,
+            camera,
+            screen_size,
+        }
+    }
+
+    /// The camera that shows the bookmarked part of the board on a screen of size `screen_size`
+    pub fn camera_for(&self, screen_size: V2f64) -> ZoomTransform {
+        let seen = Rect {
+            min: self.camera.transform_point_exact(P2f64::origin()),
+            max: self
+                .camera
+                .transform_point_exact(P2f64::from(self.screen_size)),
+        };
+        ZoomTransform::fitting(&seen, screen_size, 1.0).unwrap_or_else(|| self.camera.clone())
+    }
+}
+
+/// The ordered list of bookmarks on a board, and which one was last visited
+pub struct Bookmarks {
+    pub views: Vec<Bookmark>,
+    current: Option<usize>,
+}
+
+impl Bookmarks {
+    pub fn new(views: Vec<Bookmark>) -> Self {
+        Self {
+            views,
+            current: None,
+        }
+    }
+
+    pub fn current(&self) -> Option<&Bookmark> {
+        self.current.and_then(|i| self.views.get(i))
+    }
+
+    pub fn current_mut(&mut self) -> Option<&mut Bookmark> {
+        self.current.and_then(move |i| self.views.get_mut(i))
+    }
+
+    /// Adds a bookmark right after the current one, making it current
+    pub fn add(&mut self, camera: ZoomTransform, screen_size: V2f64) {
+        let name = format!("View {}", self.views.len() + 1);
+        let index = self.current.map(|i| i + 1).unwrap_or(self.views.len());
+        self.views
+            .insert(index, Bookmark::new(name, camera, screen_size));
+        self.current = Some(index);
+    }
+
+    pub fn remove_current(&mut self) {
+        if let Some(i) = self.current {
+            self.views.remove(i);
+            self.current = if self.views.is_empty() {
+                None
+            } else {
+                Some(i.min(self.views.len() - 1))
+            };
+        }
+    }
+
+    /// Moves the current bookmark `by` places later in the order, or earlier if negative
+    pub fn move_current(&mut self, by: isize) {
+        if let Some(i) = self.current {
+            let to = (i as isize + by).max(0).min(self.views.len() as isize - 1) as usize;
+            let moving = self.views.remove(i);
+            self.views.insert(to, moving);
+            self.current = Some(to);
+        }
+    }
+
+    /// Makes the bookmark `by` places after the current one current, stopping at either end.
+    /// With no current bookmark, stepping forwards starts at the first one and backwards at the
+    /// last one
+    pub fn step(&mut self, by: isize) -> Option<&Bookmark> {
+        if self.views.is_empty() {
+            return None;
+        }
+        let last = self.views.len() as isize - 1;
+        let to = match self.current {
+            Some(i) => i as isize + by,
+            None if by > 0 => 0,
+            None => last,
+        };
+        self.current = Some(to.max(0).min(last) as usize);
+        self.current()
+    }
+
+    pub fn go_to_start(&mut self) {
+        self.current = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(bookmarks: &Bookmarks) -> Vec<&str> {
+        bookmarks.views.iter().map(|b| b.name.as_str()).collect()
+    }
+
+    fn screen() -> V2f64 {
+        V2f64::new(800.0, 600.0)
+    }
+
+    #[test]
+    fn reordering_and_stepping() {
+        let mut bookmarks = Bookmarks::new(vec![]);
+        for _ in 0..3 {
+            bookmarks.add(ZoomTransform::does_nothing(), screen());
+        }
+        assert_eq!(names(&bookmarks), vec!["View 1", "View 2", "View 3"]);
+
+        bookmarks.move_current(-1);
+        assert_eq!(names(&bookmarks), vec!["View 1", "View 3", "View 2"]);
+        bookmarks.move_current(-5);
+        assert_eq!(names(&bookmarks), vec!["View 3", "View 1", "View 2"]);
+
+        bookmarks.go_to_start();
+        assert_eq!(bookmarks.step(1).unwrap().name, "View 3");
+        assert_eq!(bookmarks.step(1).unwrap().name, "View 1");
+        assert_eq!(bookmarks.step(5).unwrap().name, "View 2");
+        bookmarks.remove_current();
+        assert_eq!(bookmarks.current().unwrap().name, "View 1");
+    }
+
+    #[test]
+    fn bookmarks_show_the_same_part_of_the_board_on_any_screen() {
+        let camera = ZoomTransform::new(1e-12, V2f64::new(3.0, 4.0));
+        let bookmark = Bookmark::new(String::from("deep"), camera.clone(), screen());
+        // twice as big a screen needs half the world units per pixel
+        let bigger = bookmark.camera_for(screen() * 2.0);
+        assert!((bigger.scale() - camera.scale() / 2.0).abs() < 1e-24);
+        assert!(bookmark.camera_for(screen()).is_close_to(&camera, 1e-6));
+    }
+}
@@ -0,0 +1,312 @@
+use crate::object_kinds::Attachments;
+use crate::world::{self, LayerId, ObjectId, SavedObjects, World};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// A change to a board. Objects and layers are named by their ids, which stay the same wherever
+/// the change is made, like on everyone's copy of a shared board
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Operation {
+    /// A new layer, or a layer being renamed. Whether it's hidden or locked isn't a change to the
+    /// board
+    PutLayer {
+        id: LayerId,
+        name: String,
+    },
+    Add {
+        id: ObjectId,
+        layer: LayerId,
+        object: SavedObjects,
+    },
+    /// Replaces an object that's still there, like after it was moved
+    Update {
+        id: ObjectId,
+        layer: LayerId,
+        object: SavedObjects,
+    },
+    Remove {
+        id: ObjectId,
+    },
+    /// Removes a layer along with anything still on it
+    RemoveLayer {
+        id: LayerId,
+    },
+    /// Layers bottom to top. Any not listed, like ones someone else just added, go on top
+    OrderLayers {
+        ids: Vec<LayerId>,
+    },
+    /// What's on a layer bottom to top, after it was brought to the front or sent to the back.
+    /// Any not listed go on top
+    OrderObjects {
+        layer: LayerId,
+        ids: Vec<ObjectId>,
+    },
+}
+
+impl Operation {
+    /// The ids of the layers and of the objects the operation names, to give them new ones
+    pub fn ids_mut(&mut self) -> (Vec<&mut LayerId>, Vec<&mut ObjectId>) {
+        match self {
+            Operation::PutLayer { id, .. } | Operation::RemoveLayer { id } => (vec![id], vec![]),
+            Operation::Add { id, layer, .. } | Operation::Update { id, layer, .. } => {
+                (vec![layer], vec![id])
+            }
+            Operation::Remove { id } => (vec![], vec![id]),
+            Operation::OrderLayers { ids } => (ids.iter_mut().collect(), vec![]),
+            Operation::OrderObjects { layer, ids } => (vec![layer], ids.iter_mut().collect()),
+        }
+    }
+}
+
+/// What an object looked like the last time it was seen, to tell if it changed since
+#[derive(PartialEq)]
+struct Seen {
+    layer: LayerId,
+    kind: &'static str,
+    data: Value,
+}
+
+/// Finds what changed on a board since it was last looked at
+pub struct Tracker {
+    objects: HashMap<ObjectId, Seen>,
+    layers: HashMap<LayerId, String>,
+    /// Layers bottom to top
+    layer_order: Vec<LayerId>,
+    /// What's on each layer bottom to top
+    object_order: HashMap<LayerId, Vec<ObjectId>>,
+}
+
+impl Default for Tracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tracker {
+    /// A tracker that hasn't seen anything, so everything on a board is a change
+    pub fn new() -> Self {
+        Self {
+            objects: HashMap::new(),
+            layers: HashMap::new(),
+            layer_order: vec![],
+            object_order: HashMap::new(),
+        }
+    }
+
+    /// What changed on `world` since it was last seen, as operations, remembering it as seen
+    pub fn changes(&mut self, world: &World) -> Vec<Operation> {
+        let mut to_return = vec![];
+        for layer in world.layers() {
+            if self.layers.get(&layer.id) != Some(&layer.name) {
+                to_return.push(Operation::PutLayer {
+                    id: layer.id,
+                    name: layer.name.clone(),
+                });
+            }
+        }
+        let layer_order: Vec<LayerId> = world.layers().map(|l| l.id).collect();
+        if !in_order(&self.layer_order, &layer_order) {
+            to_return.push(Operation::OrderLayers {
+                ids: layer_order.clone(),
+            });
+        }
+        self.layer_order = layer_order;
+        for layer in world.layers() {
+            for (id, object) in layer.objects() {
+                let seen = Seen {
+                    layer: layer.id,
+                    kind: object.kind(),
+                    data: object.save(),
+                };
+                let (layer, before) = (layer.id, self.objects.get(&id));
+                if before == Some(&seen) {
+                    continue;
+                }
+                let object = world::save_objects(std::iter::once(object));
+                to_return.push(match before {
+                    Some(_) => Operation::Update { id, layer, object },
+                    None => Operation::Add { id, layer, object },
+                });
+                self.objects.insert(id, seen);
+            }
+        }
+        let removed: Vec<ObjectId> = self
+            .objects
+            .keys()
+            .filter(|&&id| world.object(id).is_none())
+            .cloned()
+            .collect();
+        for id in removed {
+            self.objects.remove(&id);
+            to_return.push(Operation::Remove { id });
+        }
+        for layer in world.layers() {
+            let order: Vec<ObjectId> = layer.objects().map(|(id, _)| id).collect();
+            let seen = self.object_order.remove(&layer.id).unwrap_or_default();
+            if !in_order(&seen, &order) {
+                to_return.push(Operation::OrderObjects {
+                    layer: layer.id,
+                    ids: order.clone(),
+                });
+            }
+            self.object_order.insert(layer.id, order);
+        }
+        let removed: Vec<LayerId> = self
+            .layers
+            .keys()
+            .filter(|&&id| !world.layers().any(|l| l.id == id))
+            .cloned()
+            .collect();
+        for id in removed {
+            self.object_order.remove(&id);
+            to_return.push(Operation::RemoveLayer { id });
+        }
+        self.layers = world.layers().map(|l| (l.id, l.name.clone())).collect();
+        to_return
+    }
+
+    /// Notes what `operation` did to `world` as already seen, like a change someone else made that
+    /// shouldn't be sent back to them. Only what it touched is noted, since anything else might
+    /// be a change that hasn't been seen yet
+    pub fn remember(&mut self, operation: &Operation, world: &World) {
+        match *operation {
+            Operation::PutLayer { id, .. } => {
+                if let Some(layer) = world.layers().find(|l| l.id == id) {
+                    self.layers.insert(id, layer.name.clone());
+                }
+                self.remember_layer_order(world);
+            }
+            Operation::Add { id, .. } | Operation::Update { id, .. } | Operation::Remove { id } => {
+                let before = self.objects.get(&id).map(|s| s.layer);
+                let found = world.layers().find_map(|l| {
+                    let (_, object) = l.objects().find(|(i, _)| *i == id)?;
+                    Some(Seen {
+                        layer: l.id,
+                        kind: object.kind(),
+                        data: object.save(),
+                    })
+                });
+                let after = found.as_ref().map(|s| s.layer);
+                match found {
+                    Some(seen) => self.objects.insert(id, seen),
+                    None => self.objects.remove(&id),
+                };
+                for layer in before.into_iter().chain(after) {
+                    self.remember_object_order(layer, world);
+                }
+            }
+            Operation::RemoveLayer { id } => {
+                // anything on it went with it
+                let objects = self.object_order.remove(&id).unwrap_or_default();
+                for object in objects {
+                    if world.object(object).is_none() {
+                        self.objects.remove(&object);
+                    }
+                }
+                if !world.layers().any(|l| l.id == id) {
+                    self.layers.remove(&id);
+                }
+                // an empty layer might have taken the place of the last one
+                for layer in world.layers() {
+                    self.layers
+                        .entry(layer.id)
+                        .or_insert_with(|| layer.name.clone());
+                }
+                self.remember_layer_order(world);
+            }
+            Operation::OrderLayers { .. } => self.remember_layer_order(world),
+            Operation::OrderObjects { layer, .. } => self.remember_object_order(layer, world),
+        }
+    }
+
+    fn remember_layer_order(&mut self, world: &World) {
+        self.layer_order = world.layers().map(|l| l.id).collect();
+    }
+
+    fn remember_object_order(&mut self, layer: LayerId, world: &World) {
+        match world.layers().find(|l| l.id == layer) {
+            Some(l) => {
+                let order = l.objects().map(|(id, _)| id).collect();
+                self.object_order.insert(layer, order);
+            }
+            None => {
+                self.object_order.remove(&layer);
+            }
+        }
+    }
+}
+
+/// If `now` is what applying changes to something that was `seen` gives without reordering
+/// anything: what's still there is in the same order, with anything new on top
+fn in_order<T: PartialEq + Copy>(seen: &[T], now: &[T]) -> bool {
+    let kept = seen.iter().filter(|id| now.contains(id));
+    let added = now.iter().filter(|id| !seen.contains(id));
+    kept.chain(added).eq(now.iter())
+}
+
+/// Operations that make an empty board into `world`
+pub fn everything_on(world: &World) -> Vec<Operation> {
+    let layers = world.layers().map(|l| Operation::PutLayer {
+        id: l.id,
+        name: l.name.clone(),
+    });
+    let objects = world.layers().flat_map(|l| {
+        l.objects().map(move |(id, o)| Operation::Add {
+            id,
+            layer: l.id,
+            object: world::save_objects(std::iter::once(o)),
+        })
+    });
+    layers.chain(objects).collect()
+}
+
+pub fn apply(operation: &Operation, world: &mut World) -> Result<(), String> {
+    apply_using(operation, world, &Attachments::new())
+}
+
+/// Like `apply`, finding attachments the operation only names in `attachments`
+pub fn apply_using(
+    operation: &Operation,
+    world: &mut World,
+    attachments: &Attachments,
+) -> Result<(), String> {
+    match operation {
+        Operation::PutLayer { id, name } => world.put_layer(*id, name.clone()),
+        Operation::Add { id, layer, object } => {
+            put(world, *id, *layer, object, attachments)?;
+        }
+        Operation::Update { id, layer, object } => {
+            // changes to something already removed don't bring it back
+            if world.object(*id).is_some() {
+                put(world, *id, *layer, object, attachments)?;
+            }
+        }
+        Operation::Remove { id } => {
+            world.remove(*id);
+        }
+        Operation::RemoveLayer { id } => world.remove_layer(*id),
+        Operation::OrderLayers { ids } => world.order_layers(ids),
+        Operation::OrderObjects { layer, ids } => world.order_objects(*layer, ids),
+    }
+    Ok(())
+}
+
+fn put(
+    world: &mut World,
+    id: ObjectId,
+    layer: LayerId,
+    object: &SavedObjects,
+    attachments: &Attachments,
+) -> Result<(), String> {
+    let object = world::load_objects_using(object.clone(), attachments)?
+        .pop()
+        .ok_or_else(|| String::from("no object was sent"))?;
+    // the layer was removed by someone else in the meantime, taking the object with it
+    if !world.layers().any(|l| l.id == layer) {
+        world.remove(id);
+        return Ok(());
+    }
+    world.put(id, layer, object);
+    Ok(())
+}
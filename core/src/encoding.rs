@@ -0,0 +1,146 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard base64 with padding, for putting binary data in text like json or data urls
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut to_return = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                to_return.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                to_return.push('=');
+            }
+        }
+    }
+    to_return
+}
+
+/// Reads standard base64, ignoring whitespace so wrapped lines are fine
+pub fn base64_decode(text: &str) -> Result<Vec<u8>, String> {
+    let mut to_return = Vec::with_capacity(text.len() / 4 * 3);
+    let mut n: u32 = 0;
+    let mut bits = 0;
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        if c == b'=' {
+            break;
+        }
+        let value = BASE64_ALPHABET
+            .iter()
+            .position(|&a| a == c)
+            .ok_or_else(|| format!("'{}' isn't base64", c as char))?;
+        n = n << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            to_return.push((n >> bits) as u8);
+        }
+    }
+    Ok(to_return)
+}
+
+/// `#[serde(with = ...)]` for bytes, written as base64 in text formats
+pub mod bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            base64_encode(bytes).serialize(s)
+        } else {
+            bytes.serialize(s)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+        if d.is_human_readable() {
+            let text = String::deserialize(d)?;
+            base64_decode(&text).map_err(serde::de::Error::custom)
+        } else {
+            Vec::deserialize(d)
+        }
+    }
+}
+
+/// `#[serde(with = ...)]` for audio samples, written as base64 of their little endian bytes in text
+/// formats so they don't take a line each
+pub mod samples {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(samples: &[i16], s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            let bytes: Vec<u8> = samples.iter().flat_map(|x| x.to_le_bytes()).collect();
+            base64_encode(&bytes).serialize(s)
+        } else {
+            samples.serialize(s)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<i16>, D::Error> {
+        if d.is_human_readable() {
+            let text = String::deserialize(d)?;
+            let bytes = base64_decode(&text).map_err(serde::de::Error::custom)?;
+            if bytes.len() % 2 != 0 {
+                return Err(serde::de::Error::custom("samples cut off half way"));
+            }
+            let samples = bytes.chunks(2).map(|b| i16::from_le_bytes([b[0], b[1]]));
+            Ok(samples.collect())
+        } else {
+            Vec::deserialize(d)
+        }
+    }
+}
+
+/// `#[serde(with = ...)]` for json values, kept as json in text formats and as a json string in
+/// binary ones, which can't store data without knowing its shape up front
+pub mod json {
+    use super::*;
+    use serde_json::Value;
+
+    pub fn serialize<S: Serializer>(value: &Value, s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            value.serialize(s)
+        } else {
+            value.to_string().serialize(s)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Value, D::Error> {
+        if d.is_human_readable() {
+            Value::deserialize(d)
+        } else {
+            let text = String::deserialize(d)?;
+            serde_json::from_str(&text).map_err(serde::de::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn base64_matches_the_standard() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64_decode("Zm9v\nYmE=").unwrap(), b"fooba");
+        assert!(base64_decode("Zm9v!").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn base64_round_trips(bytes in proptest::collection::vec(any::<u8>(), 0..100)) {
+            prop_assert_eq!(base64_decode(&base64_encode(&bytes)).unwrap(), bytes);
+        }
+    }
+}
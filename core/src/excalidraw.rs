@@ -0,0 +1,455 @@
+use crate::line::{SavedLine, Stroke};
+use crate::svg;
+use crate::text::SavedText;
+use crate::util::*;
+use crate::world::SavedWorld;
+use crate::zooming::ZoomTransform;
+use serde_json::{json, Map, Value};
+use std::path::Path;
+
+/// Height of a line of text before its transform, the size of the font text is drawn with
+const TEXT_SIZE: f64 = 64.0;
+/// How far apart lines of excalidraw text are, as a multiple of the font size
+const LINE_HEIGHT: f64 = 1.25;
+/// Roughly how wide a character is, as a multiple of the font size. Excalidraw works out the real
+/// width when it opens the file
+const CHARACTER_WIDTH: f64 = 0.55;
+/// How far each side of an arrowhead sticks out from the line
+const ARROWHEAD_ANGLE: f64 = 25.0;
+
+/// Whether the file at `path` is named like an excalidraw drawing
+pub fn is_excalidraw(path: &Path) -> bool {
+    let extension = path.extension().and_then(|e| e.to_str());
+    matches!(extension, Some(e) if e.eq_ignore_ascii_case("excalidraw"))
+}
+
+/// What every kind of excalidraw element has
+fn element(index: usize, kind: &str, at: P2f64, size: V2f64, stroke: Stroke) -> Map<String, Value> {
+    let seed = index as u64 + 1;
+    let value = json!({
+        "id": format!("explain-{}", index),
+        "type": kind,
+        "x": at.x,
+        "y": at.y,
+        "width": size.x,
+        "height": size.y,
+        "angle": 0,
+        "strokeColor": svg::color(stroke.color),
+        "backgroundColor": "transparent",
+        "fillStyle": "solid",
+        "strokeWidth": stroke.width,
+        "strokeStyle": "solid",
+        // drawn the way explain draws it, not sketchy
+        "roughness": 0,
+        "opacity": 100,
+        "groupIds": [],
+        "frameId": null,
+        "roundness": null,
+        "seed": seed,
+        "version": 1,
+        "versionNonce": seed,
+        "isDeleted": false,
+        "boundElements": null,
+        "updated": 1,
+        "link": null,
+        "locked": false,
+    });
+    match value {
+        Value::Object(map) => map,
+        _ => unreachable!(),
+    }
+}
+
+fn export_line(index: usize, line: SavedLine, on_screen: &ZoomTransform) -> Option<Value> {
+    let points: Vec<P2f64> = line
+        .points
+        .iter()
+        .map(|&p| on_screen.transform_point(na::convert(p)))
+        .collect();
+    let &start = points.first()?;
+    let (mut low, mut high) = (V2f64::zeros(), V2f64::zeros());
+    let relative: Vec<Value> = points
+        .iter()
+        .map(|p| {
+            let offset = p - start;
+            low = low.inf(&offset);
+            high = high.sup(&offset);
+            json!([offset.x, offset.y])
+        })
+        .collect();
+    // lines are as wide on the screen whatever the zoom, so the width stays as it is
+    let mut to_return = element(index, "freedraw", start, high - low, line.stroke);
+    to_return.insert(String::from("points"), Value::Array(relative));
+    to_return.insert(String::from("pressures"), json!([]));
+    to_return.insert(String::from("simulatePressure"), json!(true));
+    to_return.insert(String::from("lastCommittedPoint"), Value::Null);
+    Some(Value::Object(to_return))
+}
+
+fn export_text(index: usize, text: SavedText, on_screen: &ZoomTransform) -> Value {
+    let at = on_screen.transform_point(na::convert(text.origin));
+    let size = TEXT_SIZE * on_screen.scale();
+    let extent = V2f64::new(
+        text.text.chars().count() as f64 * size * CHARACTER_WIDTH,
+        size * LINE_HEIGHT,
+    );
+    let black = Stroke {
+        color: [0.0, 0.0, 0.0],
+        width: 1.0,
+    };
+    let mut to_return = element(index, "text", at, extent, black);
+    let text_fields = json!({
+        "text": text.text,
+        "originalText": text.text,
+        "fontSize": size,
+        // helvetica, the closest to arial
+        "fontFamily": 2,
+        "textAlign": "left",
+        "verticalAlign": "top",
+        "baseline": size,
+        "containerId": null,
+        "lineHeight": LINE_HEIGHT,
+        "autoResize": true,
+    });
+    if let Value::Object(fields) = text_fields {
+        to_return.extend(fields);
+    }
+    Value::Object(to_return)
+}
+
+/// An excalidraw drawing of what's visible on `saved_world`, as it looked from where the board
+/// was left. Lines become freehand drawings and text becomes text. Images are left out, and how
+/// many were is returned alongside
+pub fn export(saved_world: &SavedWorld) -> (Value, usize) {
+    let camera = &saved_world.camera;
+    let mut elements = vec![];
+    let mut left_out = 0;
+    for (kind, data) in saved_world.visible_objects() {
+        let index = elements.len();
+        let exported = match kind {
+            SavedLine::KIND => serde_json::from_value::<SavedLine>(data.clone())
+                .ok()
+                .and_then(|line| {
+                    let on_screen = line.transform.relative_to(camera);
+                    export_line(index, line, &on_screen)
+                }),
+            SavedText::KIND => serde_json::from_value::<SavedText>(data.clone())
+                .ok()
+                .map(|text| {
+                    let on_screen = text.transform.relative_to(camera);
+                    export_text(index, text, &on_screen)
+                }),
+            _ => None,
+        };
+        match exported {
+            Some(element) => elements.push(element),
+            None => left_out += 1,
+        }
+    }
+    let drawing = json!({
+        "type": "excalidraw",
+        "version": 2,
+        "source": "explain",
+        "elements": elements,
+        // the elements are placed as they were on the screen, so the view starts there too
+        "appState": {
+            "viewBackgroundColor": "#ffffff",
+            "scrollX": 0,
+            "scrollY": 0,
+            "zoom": { "value": 1 },
+        },
+        "files": {},
+    });
+    (drawing, left_out)
+}
+
+fn number(element: &Value, name: &str) -> f64 {
+    element.get(name).and_then(Value::as_f64).unwrap_or(0.0)
+}
+
+/// The two sides of an arrowhead pointing from `from` to `tip`, as one stroke
+fn arrowhead(from: P2f64, tip: P2f64, width: f64) -> Option<Vec<P2f64>> {
+    let back = (from - tip).try_normalize(1e-9)?;
+    let length = (15.0 + 2.0 * width).min((from - tip).norm());
+    let side = |degrees: f64| {
+        let rotation = na::Rotation2::new(degrees.to_radians());
+        tip + rotation * back * length
+    };
+    Some(vec![side(ARROWHEAD_ANGLE), tip, side(-ARROWHEAD_ANGLE)])
+}
+
+/// The strokes drawing an excalidraw element that's made of lines, relative to its position
+fn element_strokes(element: &Value, kind: &str) -> Vec<Vec<P2f64>> {
+    let (width, height) = (number(element, "width"), number(element, "height"));
+    let corner = |x: f64, y: f64| P2f64::new(x * width, y * height);
+    match kind {
+        "rectangle" => vec![vec![
+            corner(0.0, 0.0),
+            corner(1.0, 0.0),
+            corner(1.0, 1.0),
+            corner(0.0, 1.0),
+            corner(0.0, 0.0),
+        ]],
+        "diamond" => vec![vec![
+            corner(0.5, 0.0),
+            corner(1.0, 0.5),
+            corner(0.5, 1.0),
+            corner(0.0, 0.5),
+            corner(0.5, 0.0),
+        ]],
+        "ellipse" => vec![svg::ellipse(
+            corner(0.5, 0.5),
+            V2f64::new(width / 2.0, height / 2.0),
+        )],
+        "freedraw" | "line" | "arrow" => {
+            let points: Vec<P2f64> = element
+                .get("points")
+                .and_then(Value::as_array)
+                .map(|points| {
+                    points
+                        .iter()
+                        .filter_map(|p| Some(P2f64::new(p.get(0)?.as_f64()?, p.get(1)?.as_f64()?)))
+                        .collect()
+                })
+                .unwrap_or_default();
+            let stroke_width = number(element, "strokeWidth");
+            let has_head = |name: &str| matches!(element.get(name), Some(h) if !h.is_null());
+            let mut heads = vec![];
+            if points.len() > 1 && has_head("endArrowhead") {
+                let n = points.len();
+                heads.extend(arrowhead(points[n - 2], points[n - 1], stroke_width));
+            }
+            if points.len() > 1 && has_head("startArrowhead") {
+                heads.extend(arrowhead(points[1], points[0], stroke_width));
+            }
+            let mut to_return = vec![points];
+            to_return.extend(heads);
+            to_return
+        }
+        _ => vec![],
+    }
+}
+
+/// The objects drawing one excalidraw element, as the name of their kind and what they saved.
+/// Elements are placed by their transforms, so their points stay small and precise
+fn import_element(element: &Value) -> Vec<(String, Value)> {
+    let kind = element.get("type").and_then(Value::as_str).unwrap_or("");
+    let at = V2f64::new(number(element, "x"), number(element, "y"));
+    if kind == "text" {
+        let size = element
+            .get("fontSize")
+            .and_then(Value::as_f64)
+            .unwrap_or(20.0);
+        let spacing = element
+            .get("lineHeight")
+            .and_then(Value::as_f64)
+            .unwrap_or(LINE_HEIGHT);
+        let text = element.get("text").and_then(Value::as_str).unwrap_or("");
+        // text objects are one line each
+        return text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                let top = at + V2f64::new(0.0, i as f64 * size * spacing);
+                let saved = SavedText {
+                    text: String::from(line),
+                    transform: ZoomTransform::new(size / TEXT_SIZE, top),
+                    origin: P2::origin(),
+                };
+                (
+                    String::from(SavedText::KIND),
+                    serde_json::to_value(saved).unwrap(),
+                )
+            })
+            .collect();
+    }
+    let paint = |name: &str| {
+        element
+            .get(name)
+            .and_then(Value::as_str)
+            .and_then(svg::parse_paint)
+            .flatten()
+    };
+    // shapes with only a fill are outlined in it
+    let color = match paint("strokeColor").or_else(|| paint("backgroundColor")) {
+        Some(color) => color,
+        None => return vec![],
+    };
+    let stroke = Stroke {
+        color,
+        width: number(element, "strokeWidth").max(1.0) as f32,
+    };
+    let strokes = element_strokes(element, kind);
+    // turned about the middle of what it draws
+    let (low, high) = strokes.iter().flatten().fold(
+        (V2f64::repeat(f64::MAX), V2f64::repeat(f64::MIN)),
+        |(low, high), p| (low.inf(&p.coords), high.sup(&p.coords)),
+    );
+    let middle = P2f64::from((low + high) / 2.0);
+    let rotation = na::Rotation2::new(number(element, "angle"));
+    strokes
+        .into_iter()
+        .filter(|points| points.len() > 1)
+        .map(|points| {
+            let saved = SavedLine {
+                points: points
+                    .iter()
+                    .map(|&p| na::convert(middle + rotation * (p - middle)))
+                    .collect(),
+                transform: ZoomTransform::new(1.0, at),
+                stroke,
+            };
+            (
+                String::from(SavedLine::KIND),
+                serde_json::to_value(saved).unwrap(),
+            )
+        })
+        .collect()
+}
+
+/// A board of everything in an excalidraw drawing that can be drawn with lines and text, looked
+/// at from where the drawing was. Freehand drawings, lines, arrows and the outlines of shapes
+/// become lines. Text keeps its size but not any rotation
+pub fn import(bytes: &[u8]) -> Result<SavedWorld<'static>, String> {
+    let drawing: Value = serde_json::from_slice(bytes).map_err(|e| e.to_string())?;
+    if drawing.get("type").and_then(Value::as_str) != Some("excalidraw") {
+        return Err(String::from("not an excalidraw drawing"));
+    }
+    let elements = drawing
+        .get("elements")
+        .and_then(Value::as_array)
+        .ok_or("the drawing has no elements")?;
+    let objects = elements
+        .iter()
+        .filter(|e| e.get("isDeleted").and_then(Value::as_bool) != Some(true))
+        .flat_map(import_element)
+        .collect();
+    // excalidraw shows (scene + scroll) * zoom on the screen
+    let view = drawing.get("appState");
+    let view_number = |name: &str| view.and_then(|v| v.get(name));
+    let zoom = view_number("zoom")
+        .and_then(|z| z.get("value").or(Some(z)))
+        .and_then(Value::as_f64)
+        .filter(|&z| z > 0.0)
+        .unwrap_or(1.0);
+    let scroll = V2f64::new(
+        view_number("scrollX")
+            .and_then(Value::as_f64)
+            .unwrap_or(0.0),
+        view_number("scrollY")
+            .and_then(Value::as_f64)
+            .unwrap_or(0.0),
+    );
+    let camera = ZoomTransform::new(1.0 / zoom, -scroll);
+    Ok(SavedWorld::from_saved_objects(camera, objects))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved<T: serde::de::DeserializeOwned>(objects: &[(String, Value)], i: usize) -> T {
+        serde_json::from_value(objects[i].1.clone()).unwrap()
+    }
+
+    #[test]
+    fn boards_go_to_excalidraw_and_back() {
+        let line = SavedLine {
+            points: vec![P2::new(0.0, 0.0), P2::new(10.0, -5.0)],
+            transform: ZoomTransform::new(2.0, V2f64::new(100.0, 100.0)),
+            stroke: Stroke {
+                color: [1.0, 0.0, 0.0],
+                width: 3.0,
+            },
+        };
+        let text = SavedText {
+            text: String::from("Hi"),
+            transform: ZoomTransform::new(0.5, V2f64::new(20.0, 40.0)),
+            origin: P2::origin(),
+        };
+        let objects = vec![
+            (
+                String::from(SavedLine::KIND),
+                serde_json::to_value(line).unwrap(),
+            ),
+            (
+                String::from(SavedText::KIND),
+                serde_json::to_value(text).unwrap(),
+            ),
+            (String::from("image"), json!({})),
+        ];
+        // looking at the board zoomed in twice as far, from 10 right
+        let camera = ZoomTransform::new(0.5, V2f64::new(10.0, 0.0));
+        let (drawing, left_out) = export(&SavedWorld::from_saved_objects(camera, objects));
+        assert_eq!(left_out, 1);
+        let freedraw = &drawing["elements"][0];
+        assert_eq!(freedraw["type"], "freedraw");
+        assert_eq!(
+            (freedraw["x"].as_f64(), freedraw["y"].as_f64()),
+            (Some(180.0), Some(200.0))
+        );
+        assert_eq!(freedraw["points"], json!([[0.0, 0.0], [40.0, -20.0]]));
+        assert_eq!(freedraw["strokeWidth"], 3.0);
+        assert_eq!(freedraw["strokeColor"], "#ff0000");
+        assert_eq!(drawing["elements"][1]["fontSize"], 64.0);
+
+        let bytes = serde_json::to_vec(&drawing).unwrap();
+        let board = import(&bytes).unwrap();
+        let objects: Vec<(String, Value)> = board
+            .visible_objects()
+            .map(|(kind, data)| (String::from(kind), data.clone()))
+            .collect();
+        assert_eq!(objects.len(), 2);
+        let line: SavedLine = saved(&objects, 0);
+        let on_screen = line.transform.relative_to(&board.camera);
+        let end = on_screen.transform_point(na::convert(line.points[1]));
+        assert_eq!(end, P2f64::new(220.0, 180.0));
+        assert_eq!(line.stroke.width, 3.0);
+        let text: SavedText = saved(&objects, 1);
+        assert_eq!(text.text, "Hi");
+        assert_eq!(text.transform.relative_to(&board.camera).scale(), 1.0);
+    }
+
+    #[test]
+    fn excalidraw_shapes_become_lines() {
+        let drawing = json!({
+            "type": "excalidraw",
+            "elements": [
+                { "type": "rectangle", "x": 10, "y": 20, "width": 4, "height": 2,
+                  "angle": std::f64::consts::PI, "strokeColor": "#1e1e1e", "strokeWidth": 2 },
+                { "type": "arrow", "x": 0, "y": 0, "points": [[0, 0], [100, 0]],
+                  "strokeColor": "transparent", "backgroundColor": "#00ff00",
+                  "endArrowhead": "arrow", "startArrowhead": null },
+                { "type": "ellipse", "x": 0, "y": 0, "width": 2, "height": 2, "isDeleted": true },
+                { "type": "text", "x": 5, "y": 5, "fontSize": 32, "text": "one\ntwo" },
+            ],
+            "appState": { "scrollX": -50, "scrollY": 0, "zoom": { "value": 2 } },
+        });
+        let board = import(&serde_json::to_vec(&drawing).unwrap()).unwrap();
+        let objects: Vec<(String, Value)> = board
+            .visible_objects()
+            .map(|(kind, data)| (String::from(kind), data.clone()))
+            .collect();
+        let kinds: Vec<&str> = objects.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(kinds, ["line", "line", "line", "text", "text"]);
+        // turned half way round its middle, so the first corner is where the third was
+        let rectangle: SavedLine = saved(&objects, 0);
+        let first = rectangle.points[0];
+        assert!((first - P2::new(4.0, 2.0)).norm() < 1e-5, "{}", first);
+        assert_eq!(rectangle.stroke.width, 2.0);
+        let head: SavedLine = saved(&objects, 2);
+        assert_eq!(head.points[1], P2::new(100.0, 0.0));
+        assert!(head.points[0].x < 100.0);
+        assert_eq!(head.stroke.color, [0.0, 1.0, 0.0]);
+        let second: SavedText = saved(&objects, 4);
+        assert_eq!(second.text, "two");
+        assert_eq!(second.transform.scale(), 0.5);
+        let camera = &board.camera;
+        assert_eq!(camera.scale(), 0.5);
+        assert_eq!(
+            camera.transform_point(P2f64::origin()),
+            P2f64::new(50.0, 0.0)
+        );
+    }
+}
@@ -0,0 +1,451 @@
+use crate::util::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+
+/// Number of bits after the binary point
+const FRACTION_BITS: i32 = 128;
+
+/// A signed fixed point number with 64 integer bits and 128 fractional bits, stored as a
+/// little endian two's complement 192 bit integer.
+///
+/// f64 only has 53 bits of mantissa, so a position stored in f64 stops being exact once you zoom
+/// in more than a handful of orders of magnitude away from the origin. Fixed point keeps the same
+/// absolute precision (about 3e-39 world units) everywhere, so positions only ever get rounded
+/// when something is converted back into floating point to be drawn.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub struct Fixed([u64; 3]);
+
+impl Fixed {
+    pub const ZERO: Fixed = Fixed([0, 0, 0]);
+    /// Just under 2 to the power of 63
+    pub const MAX: Fixed = Fixed([u64::MAX, u64::MAX, i64::MAX as u64]);
+    /// -2 to the power of 63
+    pub const MIN: Fixed = Fixed([0, 0, 1 << 63]);
+
+    fn is_negative(self) -> bool {
+        (self.0[2] as i64) < 0
+    }
+
+    fn abs_bits(self) -> [u64; 3] {
+        if self.is_negative() {
+            (-self).0
+        } else {
+            self.0
+        }
+    }
+
+    /// Rounds to the nearest representable value. Anything too big for the 64 integer bits,
+    /// infinity included, is clamped to `MAX` or `MIN`, and NaN becomes zero
+    pub fn from_f64(f: f64) -> Fixed {
+        if f == 0.0 || f.is_nan() {
+            return Fixed::ZERO;
+        }
+        let clamped = if f < 0.0 { Fixed::MIN } else { Fixed::MAX };
+        // f64 layout: 1 sign bit, 11 exponent bits, 52 mantissa bits
+        let bits = f.abs().to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as i32;
+        let mut mantissa = bits & ((1 << 52) - 1);
+        let shift = if exponent == 0 {
+            // subnormal, way below anything we can represent anyways
+            -1074
+        } else {
+            mantissa |= 1 << 52;
+            exponent - 1075
+        } + FRACTION_BITS;
+        // the top bit is the sign
+        if shift + 53 >= 192 {
+            return clamped;
+        }
+
+        let magnitude = if shift >= 0 {
+            shl([mantissa, 0, 0], shift as u32)
+        } else {
+            // round to nearest by adding half of the last bit that is kept before truncating
+            let shift = (-shift) as u32;
+            if shift > 64 {
+                return Fixed::ZERO;
+            }
+            let rounded = (mantissa as u128) + (1u128 << (shift - 1));
+            shr([rounded as u64, (rounded >> 64) as u64, 0], shift)
+        };
+
+        if f < 0.0 {
+            -Fixed(magnitude)
+        } else {
+            Fixed(magnitude)
+        }
+    }
+
+    pub fn to_f64(self) -> f64 {
+        let magnitude = self.abs_bits();
+        let highest_bit = match highest_set_bit(magnitude) {
+            Some(b) => b as i32,
+            None => return 0.0,
+        };
+        // keep the top 64 bits, which is more than f64 can hold anyways
+        let dropped = (highest_bit - 63).max(0);
+        let top = shr(magnitude, dropped as u32)[0];
+        let to_return = top as f64 * 2f64.powi(dropped - FRACTION_BITS);
+        if self.is_negative() {
+            -to_return
+        } else {
+            to_return
+        }
+    }
+}
+
+impl Fixed {
+    pub fn abs(self) -> Fixed {
+        Fixed(self.abs_bits())
+    }
+
+    /// Rounds down to a multiple of 2 to the power of `exponent`, which is exact since it only
+    /// clears bits. `exponent` has to be from -128 to 63
+    pub fn floor_to_power_of_two(self, exponent: i32) -> Fixed {
+        let cleared = (exponent + FRACTION_BITS) as u32;
+        let mut to_return = self.0;
+        for (i, limb) in to_return.iter_mut().enumerate() {
+            let start = i as u32 * 64;
+            if cleared >= start + 64 {
+                *limb = 0;
+            } else if cleared > start {
+                // in two's complement this rounds negative numbers away from zero, like floor
+                *limb &= !((1u64 << (cleared - start)) - 1);
+            }
+        }
+        Fixed(to_return)
+    }
+
+    /// Rounds to the nearest multiple of 2 to the power of `exponent`, which has to be from -127
+    /// to 63
+    pub fn round_to_power_of_two(self, exponent: i32) -> Fixed {
+        let half = Fixed::from_f64(2f64.powi(exponent - 1));
+        (self + half).floor_to_power_of_two(exponent)
+    }
+}
+
+impl Fixed {
+    /// Writes the number out in decimal with `digits` digits after the point. Unlike going
+    /// through f64 this stays exact however many digits there are
+    pub fn to_decimal(self, digits: usize) -> String {
+        // rounds to nearest by adding half of the last digit. That half is only roughly right,
+        // which doesn't matter for rounding
+        let half_digit = Fixed::from_f64(0.5 * 10f64.powi(-(digits as i32)));
+        let magnitude = Fixed(self.abs_bits()) + half_digit;
+        let mut to_return = magnitude.0[2].to_string();
+        if digits > 0 {
+            to_return.push('.');
+        }
+        let mut fraction = ((magnitude.0[1] as u128) << 64) | magnitude.0[0] as u128;
+        for _ in 0..digits {
+            // multiply by ten, and whatever overflows the fraction is the next digit
+            let low = (fraction & u64::MAX as u128) * 10;
+            let high = (fraction >> 64) * 10 + (low >> 64);
+            to_return.push(std::char::from_digit((high >> 64) as u32, 10).unwrap());
+            fraction = (high << 64) | (low & u64::MAX as u128);
+        }
+        let is_zero = to_return.chars().all(|c| c == '0' || c == '.');
+        if self.is_negative() && !is_zero {
+            to_return.insert(0, '-');
+        }
+        to_return
+    }
+}
+
+fn shl(x: [u64; 3], amount: u32) -> [u64; 3] {
+    let mut to_return = [0u64; 3];
+    let limbs = (amount / 64) as usize;
+    let bits = amount % 64;
+    for i in (limbs..3).rev() {
+        to_return[i] = x[i - limbs] << bits;
+        if bits > 0 && i > limbs {
+            to_return[i] |= x[i - limbs - 1] >> (64 - bits);
+        }
+    }
+    to_return
+}
+
+fn shr(x: [u64; 3], amount: u32) -> [u64; 3] {
+    let mut to_return = [0u64; 3];
+    let limbs = (amount / 64) as usize;
+    let bits = amount % 64;
+    for i in 0..3usize.saturating_sub(limbs) {
+        to_return[i] = x[i + limbs] >> bits;
+        if bits > 0 && i + limbs + 1 < 3 {
+            to_return[i] |= x[i + limbs + 1] << (64 - bits);
+        }
+    }
+    to_return
+}
+
+fn highest_set_bit(x: [u64; 3]) -> Option<u32> {
+    for i in (0..3).rev() {
+        if x[i] != 0 {
+            return Some(i as u32 * 64 + 63 - x[i].leading_zeros());
+        }
+    }
+    None
+}
+
+/// How a fixed point number looks in text formats like json. A plain number when that's exact,
+/// so positions can be read and written by hand, and the raw bits when it isn't
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ReadableFixed {
+    Number(f64),
+    Exact([u64; 3]),
+}
+
+impl Serialize for Fixed {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        if !s.is_human_readable() {
+            return self.0.serialize(s);
+        }
+        let f = self.to_f64();
+        if Fixed::from_f64(f) == *self {
+            ReadableFixed::Number(f).serialize(s)
+        } else {
+            ReadableFixed::Exact(self.0).serialize(s)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Fixed {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        if !d.is_human_readable() {
+            return Ok(Fixed(<[u64; 3]>::deserialize(d)?));
+        }
+        match ReadableFixed::deserialize(d)? {
+            ReadableFixed::Number(f) if f.is_finite() && f.abs() < 2f64.powi(63) => {
+                Ok(Fixed::from_f64(f))
+            }
+            ReadableFixed::Number(f) => Err(serde::de::Error::custom(format!(
+                "{} is too big for a position",
+                f
+            ))),
+            ReadableFixed::Exact(bits) => Ok(Fixed(bits)),
+        }
+    }
+}
+
+impl Add for Fixed {
+    type Output = Fixed;
+    fn add(self, other: Fixed) -> Fixed {
+        let mut to_return = [0u64; 3];
+        let mut carry = false;
+        for (limb, (a, b)) in to_return.iter_mut().zip(self.0.iter().zip(other.0.iter())) {
+            let (sum, c1) = a.overflowing_add(*b);
+            let (sum, c2) = sum.overflowing_add(carry as u64);
+            *limb = sum;
+            carry = c1 || c2;
+        }
+        Fixed(to_return)
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Fixed;
+    fn neg(self) -> Fixed {
+        let inverted = Fixed([!self.0[0], !self.0[1], !self.0[2]]);
+        inverted + Fixed([1, 0, 0])
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Fixed;
+    fn sub(self, other: Fixed) -> Fixed {
+        self + -other
+    }
+}
+
+impl AddAssign for Fixed {
+    fn add_assign(&mut self, other: Fixed) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Fixed {
+    fn sub_assign(&mut self, other: Fixed) {
+        *self = *self - other;
+    }
+}
+
+impl Ord for Fixed {
+    fn cmp(&self, other: &Fixed) -> Ordering {
+        (self.0[2] as i64)
+            .cmp(&(other.0[2] as i64))
+            .then(self.0[1].cmp(&other.0[1]))
+            .then(self.0[0].cmp(&other.0[0]))
+    }
+}
+
+impl PartialOrd for Fixed {
+    fn partial_cmp(&self, other: &Fixed) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A 2d vector of fixed point numbers, used for positions in the world that have to survive
+/// zooming in and out arbitrarily far
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug, Serialize, Deserialize)]
+pub struct FixedV2 {
+    pub x: Fixed,
+    pub y: Fixed,
+}
+
+impl FixedV2 {
+    pub fn zero() -> Self {
+        Self {
+            x: Fixed::ZERO,
+            y: Fixed::ZERO,
+        }
+    }
+    pub fn from_f64(v: V2f64) -> Self {
+        Self {
+            x: Fixed::from_f64(v.x),
+            y: Fixed::from_f64(v.y),
+        }
+    }
+    pub fn to_f64(self) -> V2f64 {
+        V2f64::new(self.x.to_f64(), self.y.to_f64())
+    }
+}
+
+impl Add for FixedV2 {
+    type Output = FixedV2;
+    fn add(self, other: FixedV2) -> FixedV2 {
+        FixedV2 {
+            x: self.x + other.x,
+            y: self.y + other.y,
+        }
+    }
+}
+
+impl Sub for FixedV2 {
+    type Output = FixedV2;
+    fn sub(self, other: FixedV2) -> FixedV2 {
+        FixedV2 {
+            x: self.x - other.x,
+            y: self.y - other.y,
+        }
+    }
+}
+
+impl Neg for FixedV2 {
+    type Output = FixedV2;
+    fn neg(self) -> FixedV2 {
+        FixedV2 {
+            x: -self.x,
+            y: -self.y,
+        }
+    }
+}
+
+impl AddAssign for FixedV2 {
+    fn add_assign(&mut self, other: FixedV2) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for FixedV2 {
+    fn sub_assign(&mut self, other: FixedV2) {
+        *self = *self - other;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn json_has_plain_numbers_when_they_are_exact() {
+        let half = Fixed::from_f64(-0.5);
+        assert_eq!(serde_json::to_string(&half).unwrap(), "-0.5");
+        assert_eq!(serde_json::from_str::<Fixed>("-0.5").unwrap(), half);
+        let one_and_a_bit = Fixed([1, 0, 1]);
+        let json = serde_json::to_string(&one_and_a_bit).unwrap();
+        assert_eq!(json, "[1,0,1]");
+        assert_eq!(serde_json::from_str::<Fixed>(&json).unwrap(), one_and_a_bit);
+        assert!(serde_json::from_str::<Fixed>("1e30").is_err());
+    }
+
+    #[test]
+    fn small_values_are_exact() {
+        for f in [0.0, 1.0, -1.0, 0.5, -0.25, 105.0, -73.125, -3e15] {
+            assert_eq!(Fixed::from_f64(f).to_f64(), f);
+        }
+    }
+
+    #[test]
+    fn numbers_too_big_are_clamped() {
+        assert_eq!(Fixed::from_f64(f64::INFINITY), Fixed::MAX);
+        assert_eq!(Fixed::from_f64(1e300), Fixed::MAX);
+        assert_eq!(Fixed::from_f64(f64::NEG_INFINITY), Fixed::MIN);
+        assert_eq!(Fixed::from_f64(-1e300), Fixed::MIN);
+        assert_eq!(Fixed::from_f64(f64::NAN), Fixed::ZERO);
+        // the biggest that fit are still exact
+        assert_eq!(Fixed::from_f64(-2f64.powi(63)), Fixed::MIN);
+        let biggest = 2f64.powi(63) - 2f64.powi(10);
+        assert_eq!(Fixed::from_f64(biggest).to_f64(), biggest);
+        assert!(Fixed::MIN < Fixed::ZERO && Fixed::ZERO < Fixed::MAX);
+    }
+
+    #[test]
+    fn tiny_powers_of_two_are_exact() {
+        for exponent in -128..0 {
+            let f = 2f64.powi(exponent);
+            assert_eq!(Fixed::from_f64(f).to_f64(), f);
+        }
+    }
+
+    #[test]
+    fn ordering_handles_sign() {
+        let values = [-1e9, -1.0, -1e-20, 0.0, 1e-20, 1.0, 1e9];
+        for w in values.windows(2) {
+            assert!(Fixed::from_f64(w[0]) < Fixed::from_f64(w[1]));
+        }
+    }
+
+    #[test]
+    fn decimals_are_rounded_and_exact() {
+        assert_eq!(Fixed::from_f64(-73.125).to_decimal(2), "-73.13");
+        assert_eq!(Fixed::from_f64(105.0).to_decimal(0), "105");
+        assert_eq!(Fixed::from_f64(-0.0001).to_decimal(2), "0.00");
+        let tiny = Fixed::from_f64(1.0) + Fixed::from_f64(1e-30);
+        assert_eq!(tiny.to_decimal(31), "1.0000000000000000000000000000010");
+    }
+
+    #[test]
+    fn rounding_to_powers_of_two_is_exact() {
+        let f = |f: f64| Fixed::from_f64(f);
+        assert_eq!(f(5.75).floor_to_power_of_two(1), f(4.0));
+        assert_eq!(f(-5.75).floor_to_power_of_two(1), f(-6.0));
+        assert_eq!(f(-5.75).round_to_power_of_two(-1), f(-5.5));
+        assert_eq!(f(3e15 + 0.75).round_to_power_of_two(-2), f(3e15 + 0.75));
+        // way past what f64 can tell apart from 1
+        let tiny = f(1.0) + f(3.0 * 2f64.powi(-122));
+        assert_eq!(
+            tiny.round_to_power_of_two(-120),
+            f(1.0) + f(2f64.powi(-120))
+        );
+        assert_eq!(f(-2.5).abs(), f(2.5));
+    }
+
+    proptest! {
+        #[test]
+        fn f64_round_trips(f in -1e18f64..1e18) {
+            let back = Fixed::from_f64(f).to_f64();
+            prop_assert!((back - f).abs() <= f.abs() * 1e-15);
+        }
+
+        #[test]
+        fn addition_is_exact(a in -1e12f64..1e12, b in -1e12f64..1e12, tiny in -1e-25f64..1e-25) {
+            // a number way below f64 precision next to a and b still survives the round trip
+            let (a, b, tiny) = (Fixed::from_f64(a), Fixed::from_f64(b), Fixed::from_f64(tiny));
+            prop_assert_eq!(a + tiny + b - a - b, tiny);
+            prop_assert_eq!(-(-a), a);
+        }
+    }
+}
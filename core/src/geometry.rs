@@ -0,0 +1,67 @@
+use crate::fixed::*;
+use crate::util::*;
+use crate::zooming::*;
+
+/// An axis aligned rectangle in world space
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Rect {
+    pub min: FixedV2,
+    pub max: FixedV2,
+}
+
+impl Rect {
+    /// The smallest rectangle around `points`, which are in the space that `transform` maps into
+    /// the world. `None` if there are no points
+    pub fn around_points<I: IntoIterator<Item = P2f64>>(
+        points: I,
+        transform: &ZoomTransform,
+    ) -> Option<Rect> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        let (mut min, mut max) = (first, first);
+        for p in points {
+            min = min.inf(&p);
+            max = max.sup(&p);
+        }
+        // the corners are found before transforming, because the transform doesn't rotate
+        Some(Rect {
+            min: transform.transform_point_exact(min),
+            max: transform.transform_point_exact(max),
+        })
+    }
+
+    pub fn union(&self, other: &Rect) -> Rect {
+        Rect {
+            min: FixedV2 {
+                x: self.min.x.min(other.min.x),
+                y: self.min.y.min(other.min.y),
+            },
+            max: FixedV2 {
+                x: self.max.x.max(other.max.x),
+                y: self.max.y.max(other.max.y),
+            },
+        }
+    }
+
+    /// If `point` is inside the rectangle, or less than `margin` away from it
+    pub fn contains(&self, point: FixedV2, margin: f64) -> bool {
+        let margin = FixedV2::from_f64(V2f64::new(margin, margin));
+        let (min, max) = (self.min - margin, self.max + margin);
+        min.x <= point.x && point.x <= max.x && min.y <= point.y && point.y <= max.y
+    }
+
+    pub fn size(&self) -> V2f64 {
+        (self.max - self.min).to_f64()
+    }
+
+    pub fn translated(&self, by: FixedV2) -> Rect {
+        Rect {
+            min: self.min + by,
+            max: self.max + by,
+        }
+    }
+
+    pub fn center(&self) -> FixedV2 {
+        self.min + FixedV2::from_f64(self.size() / 2.0)
+    }
+}
@@ -0,0 +1,206 @@
+use crate::encoding::base64_encode;
+use crate::geometry::Rect;
+use crate::object_kinds::Attachments;
+use crate::shape::{Canvas, Shape};
+use crate::util::*;
+use crate::zooming::ZoomTransform;
+use image::{ImageFormat, RgbaImage};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cell::OnceCell;
+use std::io::Cursor;
+use std::path::Path;
+use std::rc::Rc;
+
+/// How much of the screen a newly added image can cover at most, so big screenshots don't land
+/// bigger than the window
+const MAX_SCREEN_FRACTION: f64 = 0.8;
+
+/// Names file contents by what's in them, so the same file added twice is only saved once. This
+/// is 64 bit FNV-1a, which unlike the standard library's hasher is the same on every build
+pub fn content_hash(bytes: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in bytes {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedImage {
+    /// Name of the attachment holding the encoded file
+    pub file: String,
+    pub transform: ZoomTransform,
+}
+
+impl SavedImage {
+    pub const KIND: &'static str = "image";
+
+    /// The encoded file, from the attachments of the board it was saved with
+    pub fn file(&self, attachments: &Attachments) -> Result<Rc<[u8]>, String> {
+        attachments
+            .get(&self.file)
+            .cloned()
+            .ok_or_else(|| format!("the save is missing image file {}", self.file))
+    }
+
+    /// The image read without gl, for using it without a window
+    pub fn into_picture(self, attachments: &Attachments) -> Result<Picture, String> {
+        let mut to_return = Picture::from_bytes(self.file(attachments)?)?;
+        to_return.transform = self.transform;
+        Ok(to_return)
+    }
+}
+
+/// A picture on the board, like a screenshot to draw over. One image pixel is one unit before
+/// the transform
+pub struct Picture {
+    /// The encoded file it was made from, which is what gets saved
+    file: Rc<[u8]>,
+    file_name: String,
+    size: V2f64,
+    /// Only decoded once it's drawn without gl, since the app draws it with a texture instead
+    pixels: OnceCell<RgbaImage>,
+    transform: ZoomTransform,
+}
+
+impl Picture {
+    /// A PNG or JPEG file that was already read into memory, checking it can be decoded
+    pub fn from_bytes(file: Rc<[u8]>) -> Result<Self, String> {
+        let (width, height) = image::io::Reader::new(Cursor::new(&file[..]))
+            .with_guessed_format()
+            .map_err(|e| e.to_string())?
+            .into_dimensions()
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            file_name: content_hash(&file),
+            file,
+            size: V2f64::new(width as f64, height as f64),
+            pixels: OnceCell::new(),
+            transform: ZoomTransform::does_nothing(),
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file = std::fs::read(path).map_err(|e| e.to_string())?;
+        Self::from_bytes(Rc::from(file))
+    }
+
+    /// The encoded file, like it's saved
+    pub fn file(&self) -> &Rc<[u8]> {
+        &self.file
+    }
+
+    /// Width and height in pixels
+    pub fn size(&self) -> V2f64 {
+        self.size
+    }
+
+    /// Puts the image in the middle of `screen_point` on the screen `camera` is looking through,
+    /// shrunk to fit if it's bigger than most of the screen
+    pub fn place(&mut self, camera: &ZoomTransform, screen_point: P2f64, screen_size: V2f64) {
+        let fit = (screen_size * MAX_SCREEN_FRACTION).component_div(&self.size);
+        let shrink = fit.x.min(fit.y).min(1.0);
+        let corner = screen_point.coords - self.size * (shrink / 2.0);
+        self.transform = ZoomTransform::new(camera.scale() * shrink, V2f64::zeros())
+            .translated(camera.transform_point_exact(P2f64::from(corner)));
+    }
+}
+
+impl Shape for Picture {
+    fn bounding_box(&self) -> Option<Rect> {
+        let corners = vec![P2f64::origin(), P2f64::from(self.size)];
+        Rect::around_points(corners, &self.transform)
+    }
+
+    fn transform(&self) -> &ZoomTransform {
+        &self.transform
+    }
+
+    fn set_transform(&mut self, z: ZoomTransform) {
+        self.transform = z;
+    }
+
+    fn kind(&self) -> &'static str {
+        SavedImage::KIND
+    }
+
+    fn save(&self) -> Value {
+        let saved = SavedImage {
+            file: self.file_name.clone(),
+            transform: self.transform.clone(),
+        };
+        serde_json::to_value(saved).unwrap()
+    }
+
+    fn attachments(&self) -> Vec<(String, Rc<[u8]>)> {
+        vec![(self.file_name.clone(), self.file.clone())]
+    }
+
+    fn to_svg(&self, view: &ZoomTransform) -> String {
+        svg_image(&self.file, self.size, &self.transform.relative_to(view))
+    }
+
+    fn paint(&self, canvas: &mut Canvas, view: &ZoomTransform) {
+        let pixels = self
+            .pixels
+            .get_or_init(|| match image::load_from_memory(&self.file) {
+                Ok(decoded) => decoded.to_rgba8(),
+                // it was checked when it was made, so this is only a file that's wrong past its header
+                Err(_) => RgbaImage::new(1, 1),
+            });
+        let on_page = self.transform.relative_to(view);
+        let upper = on_page.transform_point(P2f64::origin());
+        let lower = on_page.transform_point(P2f64::from(self.size));
+        let (columns, rows) = canvas.pixels_between(upper, lower);
+        let (width, height) = pixels.dimensions();
+        for y in rows {
+            for x in columns.clone() {
+                // the nearest pixel of the image
+                let at =
+                    on_page.inverse_transform_point(P2f64::new(x as f64 + 0.5, y as f64 + 0.5));
+                let pixel =
+                    pixels.get_pixel((at.x as u32).min(width - 1), (at.y as u32).min(height - 1));
+                let [r, g, b, a] = pixel.0;
+                let color = [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0];
+                canvas.blend(x, y, color, a as f32 / 255.0);
+            }
+        }
+    }
+}
+
+/// An svg element showing the image `file`, which is `size` pixels before `on_page`
+pub fn svg_image(file: &[u8], size: V2f64, on_page: &ZoomTransform) -> String {
+    let corner = on_page.transform_point(P2f64::origin());
+    let size = size * on_page.scale();
+    let mime = match image::guess_format(file) {
+        Ok(ImageFormat::Png) => "image/png",
+        Ok(ImageFormat::Jpeg) => "image/jpeg",
+        Ok(ImageFormat::Gif) => "image/gif",
+        Ok(ImageFormat::WebP) => "image/webp",
+        Ok(ImageFormat::Bmp) => "image/bmp",
+        _ => "application/octet-stream",
+    };
+    format!(
+        "<image x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" preserveAspectRatio=\"none\" href=\"data:{};base64,{}\"/>",
+        corner.x,
+        corner.y,
+        size.x,
+        size.y,
+        mime,
+        base64_encode(file)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_are_fnv_1a() {
+        assert_eq!(content_hash(b""), "cbf29ce484222325");
+        assert_eq!(content_hash(b"a"), "af63dc4c8601ec8c");
+        assert_ne!(content_hash(b"png"), content_hash(b"gnp"));
+    }
+}
@@ -0,0 +1,25 @@
+//! Boards and everything on them as plain data: where things are, what they look like, and how
+//! they're saved. None of it needs a window or a graphics card, so tools and tests can use it
+//! as well as the app
+extern crate nalgebra as na;
+extern crate serde;
+
+pub mod bookmarks;
+pub mod changes;
+pub mod encoding;
+pub mod excalidraw;
+pub mod fixed;
+pub mod geometry;
+pub mod images;
+pub mod line;
+pub mod narration;
+pub mod navigation;
+pub mod object_kinds;
+pub mod recording;
+pub mod saving;
+pub mod shape;
+pub mod svg;
+pub mod text;
+pub mod util;
+pub mod world;
+pub mod zooming;
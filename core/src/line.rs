@@ -0,0 +1,92 @@
+use crate::fixed::FixedV2;
+use crate::geometry::Rect;
+use crate::shape::{Canvas, Shape};
+use crate::svg;
+use crate::util::*;
+use crate::zooming::ZoomTransform;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// How a line looks
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Stroke {
+    /// Red, green and blue from 0 to 1
+    pub color: [f32; 3],
+    /// How thick the line is, in pixels at the zoom it's drawn at
+    pub width: f32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedLine {
+    pub points: Vec<P2>,
+    pub transform: ZoomTransform,
+    pub stroke: Stroke,
+}
+
+impl SavedLine {
+    pub const KIND: &'static str = "line";
+
+    /// The points as they are on a page looked at through `view`
+    fn on_page(&self, view: &ZoomTransform) -> Vec<P2f64> {
+        let on_page = self.transform.relative_to(view);
+        self.points
+            .iter()
+            .map(|&p| on_page.transform_point(na::convert(p)))
+            .collect()
+    }
+}
+
+impl Shape for SavedLine {
+    fn bounding_box(&self) -> Option<Rect> {
+        let points = self.points.iter().map(|&p| na::convert(p));
+        Rect::around_points(points, &self.transform)
+    }
+
+    fn hit(&self, point: FixedV2, tolerance: f64) -> bool {
+        let p = self.transform.inverse_transform_exact(point);
+        let reach = tolerance / self.transform.scale() + self.stroke.width as f64 / 2.0;
+        let points: Vec<P2f64> = self.points.iter().map(|&p| na::convert(p)).collect();
+        points.windows(2).any(|segment| {
+            let (a, b) = (segment[0], segment[1]);
+            let along = b - a;
+            let t = ((p - a).dot(&along) / along.norm_squared()).clamp(0.0, 1.0);
+            let closest = if t.is_nan() { a } else { a + along * t };
+            (p - closest).norm() <= reach
+        })
+    }
+
+    fn transform(&self) -> &ZoomTransform {
+        &self.transform
+    }
+
+    fn set_transform(&mut self, z: ZoomTransform) {
+        self.transform = z;
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn save(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+
+    fn to_svg(&self, view: &ZoomTransform) -> String {
+        let points: Vec<String> = self
+            .on_page(view)
+            .iter()
+            .map(|p| format!("{:.2},{:.2}", p.x, p.y))
+            .collect();
+        format!(
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" stroke-linecap=\"round\" stroke-linejoin=\"round\"/>",
+            points.join(" "),
+            svg::color(self.stroke.color),
+            self.stroke.width
+        )
+    }
+
+    fn paint(&self, canvas: &mut Canvas, view: &ZoomTransform) {
+        let points = self.on_page(view);
+        canvas.stroke(&points, self.stroke.width as f64, self.stroke.color);
+    }
+}
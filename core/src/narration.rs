@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Instant;
+
+/// Samples per second narration is recorded, stored and played back at. Enough for a voice
+pub const SAMPLE_RATE: i32 = 22050;
+
+/// Narration recorded in one go, from starting to talk until stopping
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Clip {
+    /// Seconds from the start of the recording of the board
+    pub at: f64,
+    /// Mono, at `SAMPLE_RATE`
+    #[serde(with = "crate::encoding::samples")]
+    pub samples: Vec<i16>,
+}
+
+impl Clip {
+    /// Seconds from the start of the recording to when this stops
+    pub fn end(&self) -> f64 {
+        self.at + self.samples.len() as f64 / SAMPLE_RATE as f64
+    }
+}
+
+/// The narration heard from `from` to `to` seconds into the recording, played `speed` times
+/// faster than it was spoken
+pub fn samples_between(clips: &[Clip], from: f64, to: f64, speed: f64) -> Vec<i16> {
+    let count = ((to - from) / speed * SAMPLE_RATE as f64).round().max(0.0) as usize;
+    let mut to_return = vec![0i16; count];
+    for clip in clips.iter().filter(|c| c.at < to && c.end() > from) {
+        for (i, sample) in to_return.iter_mut().enumerate() {
+            let time = from + i as f64 * speed / SAMPLE_RATE as f64;
+            let index = ((time - clip.at) * SAMPLE_RATE as f64).floor();
+            if index >= 0.0 {
+                if let Some(&s) = clip.samples.get(index as usize) {
+                    *sample = sample.saturating_add(s);
+                }
+            }
+        }
+    }
+    to_return
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes([*bytes.get(at)?, *bytes.get(at + 1)?]))
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes([
+        *bytes.get(at)?,
+        *bytes.get(at + 1)?,
+        *bytes.get(at + 2)?,
+        *bytes.get(at + 3)?,
+    ]))
+}
+
+/// The samples in a 16 bit wav file, mixed down to mono at `SAMPLE_RATE`
+pub fn read_wav(bytes: &[u8]) -> Result<Vec<i16>, String> {
+    if bytes.get(0..4) != Some(b"RIFF") || bytes.get(8..12) != Some(b"WAVE") {
+        return Err(String::from("not a wav file"));
+    }
+    let mut format = None;
+    let mut data = None;
+    let mut at = 12;
+    while let (Some(id), Some(size)) = (bytes.get(at..at + 4), u32_at(bytes, at + 4)) {
+        let start = at + 8;
+        let end = (start + size as usize).min(bytes.len());
+        match id {
+            b"fmt " => format = Some(&bytes[start..end]),
+            b"data" => data = Some(&bytes[start..end]),
+            _ => {}
+        }
+        // chunks are padded to an even length
+        at = start + size as usize + size as usize % 2;
+    }
+    let (format, data) = format
+        .zip(data)
+        .ok_or("the wav file is missing its sound")?;
+    let (encoding, channels, rate, bits) = (
+        u16_at(format, 0),
+        u16_at(format, 2).unwrap_or(0) as usize,
+        u32_at(format, 4).unwrap_or(0),
+        u16_at(format, 14),
+    );
+    if encoding != Some(1) || bits != Some(16) || channels == 0 || rate == 0 {
+        return Err(String::from(
+            "only 16 bit uncompressed wav files can be read",
+        ));
+    }
+    let mono: Vec<i16> = data
+        .chunks_exact(2 * channels)
+        .map(|frame| {
+            let sum: i32 = frame
+                .chunks_exact(2)
+                .map(|s| i16::from_le_bytes([s[0], s[1]]) as i32)
+                .sum();
+            (sum / channels as i32) as i16
+        })
+        .collect();
+    let count = mono.len() as u64 * SAMPLE_RATE as u64 / rate as u64;
+    Ok((0..count)
+        .map(|i| mono[(i * rate as u64 / SAMPLE_RATE as u64) as usize])
+        .collect())
+}
+
+/// Somewhere narration comes from, a microphone or something standing in for one
+pub trait AudioSource {
+    /// Everything heard since this was last asked, at `SAMPLE_RATE`
+    fn take(&mut self, now: Instant) -> Vec<i16>;
+}
+
+/// Plays a wav file as if it was being spoken into a microphone, for trying out narration
+/// without one
+pub struct WavFile {
+    samples: Vec<i16>,
+    start: Instant,
+    taken: usize,
+}
+
+impl WavFile {
+    pub fn open(path: &Path, now: Instant) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+        Ok(Self::new(read_wav(&bytes)?, now))
+    }
+
+    /// Starts "speaking" `samples` at `now`
+    pub fn new(samples: Vec<i16>, now: Instant) -> Self {
+        Self {
+            samples,
+            start: now,
+            taken: 0,
+        }
+    }
+}
+
+impl AudioSource for WavFile {
+    fn take(&mut self, now: Instant) -> Vec<i16> {
+        let heard = ((now - self.start).as_secs_f64() * SAMPLE_RATE as f64) as usize;
+        let heard = heard.max(self.taken);
+        // silence once the file runs out, like a microphone nobody's talking into
+        let to_return = (self.taken..heard)
+            .map(|i| self.samples.get(i).copied().unwrap_or(0))
+            .collect();
+        self.taken = heard;
+        to_return
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn wav(channels: u16, rate: u32, samples: &[i16]) -> Vec<u8> {
+        let data: Vec<u8> = samples
+            .iter()
+            .flat_map(|s| s.to_le_bytes().to_vec())
+            .collect();
+        let mut to_return = b"RIFF".to_vec();
+        to_return.extend(&(36 + data.len() as u32).to_le_bytes());
+        to_return.extend(b"WAVEfmt ");
+        to_return.extend(&16u32.to_le_bytes());
+        to_return.extend(&1u16.to_le_bytes());
+        to_return.extend(&channels.to_le_bytes());
+        to_return.extend(&rate.to_le_bytes());
+        to_return.extend(&(rate * channels as u32 * 2).to_le_bytes());
+        to_return.extend(&(channels * 2).to_le_bytes());
+        to_return.extend(&16u16.to_le_bytes());
+        to_return.extend(b"data");
+        to_return.extend(&(data.len() as u32).to_le_bytes());
+        to_return.extend(data);
+        to_return
+    }
+
+    #[test]
+    fn wav_files_are_read_as_mono_at_the_sample_rate() {
+        let stereo = wav(2, SAMPLE_RATE as u32, &[100, 300, -10, -20]);
+        assert_eq!(read_wav(&stereo).unwrap(), [200, -15]);
+        let slow = wav(1, SAMPLE_RATE as u32 / 2, &[1, 2]);
+        assert_eq!(read_wav(&slow).unwrap(), [1, 1, 2, 2]);
+        assert!(read_wav(b"RIFF....WAVE").is_err());
+    }
+
+    #[test]
+    fn narration_plays_at_the_time_it_was_spoken() {
+        let second = SAMPLE_RATE as usize;
+        let clips = [Clip {
+            at: 1.0,
+            samples: vec![5; second],
+        }];
+        assert_eq!(clips[0].end(), 2.0);
+        let heard = samples_between(&clips, 0.5, 1.5, 1.0);
+        assert_eq!(heard.len(), second);
+        assert_eq!(heard[second / 2 - 1], 0);
+        assert_eq!(heard[second / 2], 5);
+        // twice as fast takes half as long
+        assert_eq!(samples_between(&clips, 0.0, 2.0, 2.0).len(), second);
+    }
+
+    #[test]
+    fn narration_is_saved_as_base64_in_json() {
+        let clip = Clip {
+            at: 0.5,
+            samples: vec![1, -2, i16::MAX, i16::MIN],
+        };
+        let json = serde_json::to_string(&clip).unwrap();
+        assert!(json.contains("\"samples\":\"AQD+//9/AIA=\""), "{}", json);
+        let read: Clip = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.samples, clip.samples);
+        let read: Clip = bincode::deserialize(&bincode::serialize(&clip).unwrap()).unwrap();
+        assert_eq!(read.samples, clip.samples);
+    }
+
+    #[test]
+    fn wav_files_are_heard_as_time_passes() {
+        let start = Instant::now();
+        let mut file = WavFile::new(vec![7; SAMPLE_RATE as usize], start);
+        let heard = file.take(start + Duration::from_millis(500));
+        assert_eq!(heard.len(), SAMPLE_RATE as usize / 2);
+        // then silence after the end
+        let heard = file.take(start + Duration::from_secs(2));
+        assert_eq!(heard.len(), SAMPLE_RATE as usize * 3 / 2);
+        assert_eq!(heard[SAMPLE_RATE as usize / 2 - 1], 7);
+        assert_eq!(heard[SAMPLE_RATE as usize / 2], 0);
+    }
+}
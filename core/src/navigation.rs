@@ -0,0 +1,90 @@
+use crate::geometry::Rect;
+use crate::shape::bounds;
+use crate::util::*;
+use crate::world::{ObjectId, World};
+use crate::zooming::*;
+
+/// How much room is left around the whole board when fitting it on screen
+pub const FIT_MARGIN: f64 = 1.1;
+/// Going to a single object leaves more room so you can see what's around it
+const GO_TO_MARGIN: f64 = 1.5;
+
+/// Ways of moving the camera that are computed from what's on the board
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum NavigationCommand {
+    FitBoard,
+    FitSelection,
+    /// Back to one world unit per pixel, keeping the middle of the screen where it is
+    ResetZoom,
+    NextObject,
+    PreviousObject,
+}
+
+/// The camera showing everything visible on the board, with a little room around it
+pub fn fit_board(world: &World, screen_size: V2f64) -> Option<ZoomTransform> {
+    let board = bounds(
+        world
+            .visible_objects()
+            .filter_map(|(_, o)| o.bounding_box()),
+    )?;
+    ZoomTransform::fitting(&board, screen_size, FIT_MARGIN)
+}
+
+/// Where the camera should end up after `command`, or `None` if there's nowhere to go. Going to
+/// the next or previous object also selects it.
+pub fn camera_target(
+    command: NavigationCommand,
+    world: &mut World,
+    screen_size: V2f64,
+) -> Option<ZoomTransform> {
+    match command {
+        NavigationCommand::FitBoard => fit_board(world, screen_size),
+        NavigationCommand::FitSelection => {
+            let selection = bounds(world.selected().filter_map(|o| o.bounding_box()))?;
+            ZoomTransform::fitting(&selection, screen_size, FIT_MARGIN)
+        }
+        NavigationCommand::ResetZoom => {
+            let screen_center = P2f64::from(screen_size / 2.0);
+            let mut to_return = world.camera.clone();
+            let zoom = Movement {
+                wrt_point: screen_center,
+                zoom: to_return.scale(),
+                pan: V2f64::zeros(),
+            };
+            zoom.apply_to_transform(&mut to_return);
+            Some(to_return)
+        }
+        NavigationCommand::NextObject | NavigationCommand::PreviousObject => {
+            let forwards = command == NavigationCommand::NextObject;
+            let boxes: Vec<(ObjectId, Option<Rect>)> = world
+                .pickable_objects()
+                .map(|(id, o)| (id, o.bounding_box()))
+                .collect();
+            let count = boxes.len();
+            let selected = world
+                .selection
+                .first()
+                .and_then(|&id| boxes.iter().position(|&(i, _)| i == id));
+            let mut index = match selected {
+                Some(i) if forwards => i + 1,
+                Some(i) => i + count - 1,
+                None if forwards => 0,
+                None => count.checked_sub(1)?,
+            };
+            // skip over anything that's empty, like a line that was only clicked
+            for _ in 0..count {
+                index %= count;
+                if let (id, Some(rect)) = &boxes[index] {
+                    world.selection = vec![*id];
+                    return ZoomTransform::fitting(rect, screen_size, GO_TO_MARGIN);
+                }
+                index = if forwards {
+                    index + 1
+                } else {
+                    index + count - 1
+                };
+            }
+            None
+        }
+    }
+}
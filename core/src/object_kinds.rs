@@ -0,0 +1,69 @@
+use crate::images::SavedImage;
+use crate::line::SavedLine;
+use crate::shape::Shape;
+use crate::text::SavedText;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Big pieces of data objects refer to by name, like image files. They're saved once per board
+/// however many objects use them
+pub type Attachments = HashMap<String, Rc<[u8]>>;
+
+/// Makes what an object looks like from what it saved and the attachments of the board it was
+/// saved with
+pub type ShapeLoader = fn(Value, &Attachments) -> Result<Box<dyn Shape>, String>;
+
+/// How to read one kind of object back from what it saved
+pub struct ObjectKind {
+    /// Stored with every saved object of the kind, so it can't change once boards have been saved
+    pub name: &'static str,
+    pub shape: ShapeLoader,
+}
+
+/// Every kind of object that can be on a board. A new kind needs a `Shape` listed here as well
+/// as whatever draws it in the app
+pub const KINDS: &[ObjectKind] = &[
+    ObjectKind {
+        name: SavedLine::KIND,
+        shape: |data, _| {
+            let saved: SavedLine = serde_json::from_value(data).map_err(|e| e.to_string())?;
+            Ok(Box::new(saved))
+        },
+    },
+    ObjectKind {
+        name: SavedText::KIND,
+        shape: |data, _| {
+            let saved: SavedText = serde_json::from_value(data).map_err(|e| e.to_string())?;
+            Ok(Box::new(saved))
+        },
+    },
+    ObjectKind {
+        name: SavedImage::KIND,
+        shape: |data, attachments| {
+            let saved: SavedImage = serde_json::from_value(data).map_err(|e| e.to_string())?;
+            Ok(Box::new(saved.into_picture(attachments)?))
+        },
+    },
+];
+
+/// What the object of kind `kind` that was saved as `data` looks like
+pub fn shape(kind: &str, data: Value, attachments: &Attachments) -> Result<Box<dyn Shape>, String> {
+    let found = KINDS
+        .iter()
+        .find(|k| k.name == kind)
+        .ok_or_else(|| format!("unknown kind of object \"{}\"", kind))?;
+    (found.shape)(data, attachments).map_err(|e| format!("couldn't read a {} - {}", kind, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kind_names_are_unique() {
+        for (i, kind) in KINDS.iter().enumerate() {
+            assert!(KINDS[i + 1..].iter().all(|k| k.name != kind.name));
+        }
+    }
+}
@@ -0,0 +1,677 @@
+use crate::bookmarks::Bookmark;
+use crate::changes::{apply_using, Operation, Tracker};
+use crate::narration::{AudioSource, Clip};
+use crate::object_kinds::Attachments;
+use crate::shape::Shape;
+use crate::util::*;
+use crate::world::{self, ObjectId, SavedObjects, World};
+use crate::zooming::ZoomTransform;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Instant;
+
+/// Something that happened while the board was being drawn
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Change {
+    Board(Operation),
+    /// What's being drawn but isn't on the board yet, like a line while the pen is still down or
+    /// text still being typed. `None` once it's done
+    Drawing(Option<SavedObjects>),
+    /// More of what's being drawn, as `added` on the end of the list or text saved as `field`,
+    /// like the points of a line since it was last looked at
+    DrawingGrew {
+        field: String,
+        #[serde(with = "crate::encoding::json")]
+        added: Value,
+    },
+    /// What the person drawing could see
+    View(Bookmark),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Moment {
+    /// Seconds from the start of the recording
+    pub at: f64,
+    pub change: Change,
+}
+
+/// How a board was drawn, in order, so it can be replayed
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Recording {
+    pub moments: Vec<Moment>,
+    /// What was said while drawing, in order
+    pub narration: Vec<Clip>,
+    /// Files like images that the moments only name, even ones no longer on the board. Saved
+    /// with the board's own attachments
+    #[serde(skip)]
+    pub attachments: Attachments,
+}
+
+impl Recording {
+    /// Moves what `objects` has attached into the recording's own table, leaving just the names
+    fn keep_attachments(&mut self, objects: &mut SavedObjects) {
+        let attached = world::load_attachments(std::mem::take(&mut objects.attachments));
+        for (name, file) in attached {
+            self.attachments.entry(name).or_insert(file);
+        }
+    }
+
+    /// Seconds from the start to the last moment or the end of the narration, whichever's later
+    pub fn duration(&self) -> f64 {
+        let moments = self.moments.last().map_or(0.0, |m| m.at);
+        let narration = self.narration.last().map_or(0.0, |c| c.end());
+        moments.max(narration)
+    }
+}
+
+/// Longest pause kept in a recording, in seconds. Time spent away from the board is cut down to
+/// this, so replays don't sit still for hours
+const MAX_PAUSE: f64 = 2.0;
+/// Shortest time between two looks at what's being drawn and what can be seen, in seconds, so
+/// smooth movements don't record every frame
+const SAMPLE_INTERVAL: f64 = 1.0 / 30.0;
+/// How many times faster or slower each change of the replay speed is
+const SPEED_STEP: f64 = 2.0;
+/// How far skipping ahead or back in a replay goes, in seconds of the recording
+const SKIP: f64 = 5.0;
+const MIN_SPEED: f64 = 0.125;
+const MAX_SPEED: f64 = 16.0;
+
+/// Adds everything that happens on a board to its recording
+pub struct Recorder {
+    board: Tracker,
+    /// When the last moment was recorded, and where it is in the recording
+    last: Option<(Instant, f64)>,
+    last_sample: Option<Instant>,
+    /// The kind of what's being drawn and what it saved, when it was last looked at
+    drawing: Option<(&'static str, Value)>,
+    view: Option<Bookmark>,
+    /// Where narration is coming from while it's being recorded
+    narrator: Option<Box<dyn AudioSource>>,
+}
+
+impl Recorder {
+    /// Carries on the recording of `world`. A board that was never recorded starts out recorded
+    /// as it is now
+    pub fn new(world: &mut World, now: Instant) -> Self {
+        let mut to_return = Self {
+            board: Tracker::new(),
+            last: None,
+            last_sample: None,
+            drawing: None,
+            view: None,
+            narrator: None,
+        };
+        if world.recording.moments.is_empty() {
+            to_return.board_changed(world, now);
+        } else {
+            // already in the recording
+            to_return.board.changes(world);
+        }
+        to_return
+    }
+
+    /// Records whatever changed on the board since it was last recorded
+    pub fn board_changed(&mut self, world: &mut World, now: Instant) {
+        let operations = self.board.changes(world);
+        if operations.is_empty() {
+            return;
+        }
+        // whatever was being drawn is finished, so it's about to be on the board
+        if self.drawing.take().is_some() {
+            self.push(world, now, Change::Drawing(None));
+        }
+        for operation in operations {
+            self.push(world, now, Change::Board(operation));
+        }
+    }
+
+    /// Records what's being drawn and what can be seen on a screen of `screen_size`, if they
+    /// changed and it's been long enough since they were last looked at
+    pub fn sample(
+        &mut self,
+        world: &mut World,
+        drawing: Option<&dyn Shape>,
+        screen_size: V2f64,
+        now: Instant,
+    ) {
+        if let Some(last) = self.last_sample {
+            if (now - last).as_secs_f64() < SAMPLE_INTERVAL {
+                return;
+            }
+        }
+        self.last_sample = Some(now);
+        let data = drawing.map(|o| (o.kind(), o.save()));
+        if data != self.drawing {
+            // lines being drawn and text being typed only get longer, so only that is recorded
+            let grown = match (&self.drawing, &data) {
+                (Some((was, before)), Some((kind, after))) if was == kind => growth(before, after),
+                _ => None,
+            };
+            let change = match grown {
+                Some((field, added)) => Change::DrawingGrew { field, added },
+                None => Change::Drawing(drawing.map(|o| world::save_objects(std::iter::once(o)))),
+            };
+            self.push(world, now, change);
+            self.drawing = data;
+        }
+        let view = Bookmark::new(String::new(), world.camera.clone(), screen_size);
+        if self.view.as_ref() != Some(&view) {
+            self.push(world, now, Change::View(view.clone()));
+            self.view = Some(view);
+        }
+    }
+
+    pub fn is_narrating(&self) -> bool {
+        self.narrator.is_some()
+    }
+
+    /// Starts recording what's heard from `source` along with the drawing
+    pub fn start_narrating(
+        &mut self,
+        world: &mut World,
+        source: Box<dyn AudioSource>,
+        now: Instant,
+    ) {
+        self.stop_narrating(world, now);
+        let at = self.time(&world.recording, now);
+        world.recording.narration.push(Clip {
+            at,
+            samples: vec![],
+        });
+        self.last = Some((now, at));
+        self.narrator = Some(source);
+    }
+
+    /// Adds what's been heard since this was last called to the narration. Called often, so it
+    /// keeps up with the drawing
+    pub fn listen(&mut self, world: &mut World, now: Instant) {
+        if let (Some(narrator), Some(clip)) =
+            (&mut self.narrator, world.recording.narration.last_mut())
+        {
+            clip.samples.extend(narrator.take(now));
+        }
+    }
+
+    pub fn stop_narrating(&mut self, world: &mut World, now: Instant) {
+        if self.narrator.is_none() {
+            return;
+        }
+        self.listen(world, now);
+        self.last = Some((now, self.time(&world.recording, now)));
+        self.narrator = None;
+    }
+
+    /// Where `now` is in the recording
+    fn time(&self, recording: &Recording, now: Instant) -> f64 {
+        match self.last {
+            Some((last, at)) => {
+                let pause = (now - last).as_secs_f64();
+                // the narration carries on while nothing's being drawn, so pauses in it can't be
+                // cut short
+                at + if self.is_narrating() {
+                    pause
+                } else {
+                    pause.min(MAX_PAUSE)
+                }
+            }
+            None if recording.moments.is_empty() && recording.narration.is_empty() => 0.0,
+            // picking up where an earlier recording left off
+            None => recording.duration() + MAX_PAUSE,
+        }
+    }
+
+    fn push(&mut self, world: &mut World, now: Instant, mut change: Change) {
+        match &mut change {
+            Change::Board(Operation::Add { object, .. })
+            | Change::Board(Operation::Update { object, .. })
+            | Change::Drawing(Some(object)) => world.recording.keep_attachments(object),
+            _ => {}
+        }
+        let at = self.time(&world.recording, now);
+        world.recording.moments.push(Moment { at, change });
+        self.last = Some((now, at));
+    }
+}
+
+/// How `after` is `before` with more on the end of one list or text in it, as the name it's saved
+/// under and what was added. `None` if anything else changed
+fn growth(before: &Value, after: &Value) -> Option<(String, Value)> {
+    let (before, after) = (before.as_object()?, after.as_object()?);
+    if before.len() != after.len() {
+        return None;
+    }
+    let mut grown = None;
+    for (field, now) in after {
+        let was = before.get(field)?;
+        if was == now {
+            continue;
+        }
+        let added = match (was, now) {
+            (Value::Array(was), Value::Array(now)) if grown.is_none() && now.starts_with(was) => {
+                Value::Array(now[was.len()..].to_vec())
+            }
+            (Value::String(was), Value::String(now)) if grown.is_none() && now.starts_with(was) => {
+                Value::String(now[was.len()..].to_string())
+            }
+            _ => return None,
+        };
+        grown = Some((field.clone(), added));
+    }
+    grown
+}
+
+/// Puts `added` on the end of what `data` saved as `field`, undoing `growth`
+fn grow(data: &mut Value, field: &str, added: &Value) {
+    match (data.get_mut(field), added) {
+        (Some(Value::Array(items)), Value::Array(more)) => items.extend(more.iter().cloned()),
+        (Some(Value::String(text)), Value::String(more)) => text.push_str(more),
+        _ => {}
+    }
+}
+
+/// Plays a recording back, showing the board as it was at any point while it was drawn
+pub struct Replay {
+    recording: Recording,
+    /// The board after the first `applied` moments
+    world: World,
+    applied: usize,
+    /// What was being drawn as it was saved, which only gets made into objects when it's shown
+    drawn: Option<SavedObjects>,
+    /// Each with its revision, see `World::visible_revisions`
+    drawing: Vec<(u64, Box<dyn Shape>)>,
+    view: Option<Bookmark>,
+    /// What couldn't be replayed so far
+    problems: Vec<String>,
+    /// Seconds from the start
+    time: f64,
+    speed: f64,
+    playing: bool,
+}
+
+impl Replay {
+    /// Starts playing `recording` from the beginning
+    pub fn new(recording: Recording) -> Self {
+        let mut to_return = Self {
+            recording,
+            world: World::new(),
+            applied: 0,
+            drawn: None,
+            drawing: vec![],
+            view: None,
+            problems: vec![],
+            time: 0.0,
+            speed: 1.0,
+            playing: true,
+        };
+        to_return.seek(0.0);
+        to_return
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.recording.duration()
+    }
+
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// How many times faster than it was drawn it's playing
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Pauses, or plays again. Playing at the end starts over
+    pub fn toggle_playing(&mut self) {
+        self.playing = !self.playing;
+        if self.playing && self.time >= self.duration() {
+            self.seek(0.0);
+        }
+    }
+
+    pub fn change_speed(&mut self, faster: bool) {
+        let step = if faster { SPEED_STEP } else { 1.0 / SPEED_STEP };
+        self.speed = (self.speed * step).clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    pub fn skip(&mut self, forward: bool) {
+        self.seek(self.time + if forward { SKIP } else { -SKIP });
+    }
+
+    /// Moves on by `dt` seconds of real time if playing, stopping at the end
+    pub fn advance(&mut self, dt: f64) {
+        if !self.playing {
+            return;
+        }
+        self.seek(self.time + dt * self.speed);
+        if self.time >= self.duration() {
+            self.playing = false;
+        }
+    }
+
+    /// Jumps to `time` seconds from the start
+    pub fn seek(&mut self, time: f64) {
+        let time = time.clamp(0.0, self.duration());
+        if time < self.time {
+            // moments can't be undone, so going back starts over
+            self.world = World::new();
+            self.applied = 0;
+            self.drawn = None;
+            self.drawing = vec![];
+            self.view = None;
+            self.problems = vec![];
+        }
+        self.time = time;
+        let attachments = &self.recording.attachments;
+        let mut drawing_changed = false;
+        while let Some(moment) = self.recording.moments.get(self.applied) {
+            if moment.at > time {
+                break;
+            }
+            match &moment.change {
+                Change::Board(operation) => {
+                    if let Err(e) = apply_using(operation, &mut self.world, attachments) {
+                        self.problems
+                            .push(format!("couldn't make a change - {}", e));
+                    }
+                }
+                // only what's being drawn at the end is made, since making objects is slow
+                Change::Drawing(saved) => {
+                    self.drawn = saved.clone();
+                    drawing_changed = true;
+                }
+                Change::DrawingGrew { field, added } => {
+                    let drawn = self.drawn.iter_mut().flat_map(|d| d.objects.iter_mut());
+                    for object in drawn {
+                        grow(&mut object.data, field, added);
+                    }
+                    drawing_changed = true;
+                }
+                Change::View(view) => self.view = Some(view.clone()),
+            }
+            self.applied += 1;
+        }
+        if !drawing_changed {
+            return;
+        }
+        let drawn = self
+            .drawn
+            .clone()
+            .map(|d| world::load_objects_using(d, attachments));
+        match drawn {
+            Some(Ok(objects)) => {
+                self.drawing = objects
+                    .into_iter()
+                    .map(|o| (world::next_revision(), o))
+                    .collect();
+            }
+            Some(Err(e)) => {
+                let problem = format!("couldn't show what was being drawn - {}", e);
+                self.problems.push(problem);
+            }
+            None => self.drawing = vec![],
+        }
+    }
+
+    /// What couldn't be replayed up to the current time, oldest first
+    pub fn problems(&self) -> &[String] {
+        &self.problems
+    }
+
+    /// The board as it was at the current time
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// What was being drawn but wasn't on the board yet at the current time, numbered in the
+    /// order it's drawn and with revisions like `World::visible_revisions`
+    pub fn drawing(&self) -> impl Iterator<Item = (ObjectId, u64, &dyn Shape)> {
+        self.drawing
+            .iter()
+            .enumerate()
+            .map(|(i, (revision, o))| (ObjectId(i as u64), *revision, o.as_ref()))
+    }
+
+    pub fn narration(&self) -> &[Clip] {
+        &self.recording.narration
+    }
+
+    /// The camera showing what the person drawing could see, on a screen of `screen_size`
+    pub fn camera_for(&self, screen_size: V2f64) -> Option<ZoomTransform> {
+        self.view.as_ref().map(|v| v.camera_for(screen_size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::images::Picture;
+    use crate::line::{SavedLine, Stroke};
+    use crate::narration::{samples_between, WavFile, SAMPLE_RATE};
+    use crate::saving::{decode, encode, SaveFormat};
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    fn layer_names(world: &World) -> Vec<String> {
+        world.layers().map(|l| l.name.clone()).collect()
+    }
+
+    #[test]
+    fn replays_go_back_and_forth_through_the_recording() {
+        let start = Instant::now();
+        let at = |seconds: f64| start + Duration::from_secs_f64(seconds);
+        let screen = V2f64::new(800.0, 600.0);
+        let mut world = World::new();
+        let mut recorder = Recorder::new(&mut world, at(0.0));
+        recorder.sample(&mut world, None, screen, at(0.0));
+
+        world.add_layer();
+        world.current_layer_mut().name = String::from("Notes");
+        recorder.board_changed(&mut world, at(1.0));
+        world.camera = ZoomTransform::new(2.0, V2f64::new(5.0, 0.0));
+        recorder.sample(&mut world, None, screen, at(1.5));
+        // time away from the board is cut short
+        world.current_layer_mut().name = String::from("Later notes");
+        recorder.board_changed(&mut world, at(3600.0));
+        assert_eq!(world.recording.duration(), 1.5 + MAX_PAUSE);
+
+        let mut replay = Replay::new(world.recording.clone());
+        assert_eq!(layer_names(replay.world()), ["Layer 1"]);
+        assert!(replay
+            .camera_for(screen)
+            .unwrap()
+            .is_close_to(&ZoomTransform::does_nothing(), 1e-9));
+        replay.advance(1.6);
+        assert_eq!(layer_names(replay.world()), ["Layer 1", "Notes"]);
+        assert!(replay
+            .camera_for(screen)
+            .unwrap()
+            .is_close_to(&world.camera, 1e-9));
+        replay.advance(10.0);
+        assert_eq!(layer_names(replay.world()), ["Layer 1", "Later notes"]);
+        assert!(!replay.is_playing());
+
+        replay.seek(1.2);
+        assert_eq!(layer_names(replay.world()), ["Layer 1", "Notes"]);
+        assert!(replay
+            .camera_for(screen)
+            .unwrap()
+            .is_close_to(&ZoomTransform::does_nothing(), 1e-9));
+
+        // carrying on a recording after it was loaded again
+        let mut recorder = Recorder::new(&mut world, at(4000.0));
+        world.add_layer();
+        recorder.board_changed(&mut world, at(4001.0));
+        assert_eq!(world.recording.duration(), 1.5 + 2.0 * MAX_PAUSE);
+        assert_eq!(world.recording.moments.len(), 6);
+    }
+
+    #[test]
+    fn changes_that_cant_be_replayed_are_kept_as_problems() {
+        let mut world = World::new();
+        let layer = world.current_layer().id;
+        let nothing = SavedObjects {
+            objects: vec![],
+            attachments: vec![],
+        };
+        let change = Change::Board(Operation::Add {
+            id: ObjectId(7),
+            layer,
+            object: nothing,
+        });
+        world.recording.moments.push(Moment { at: 1.0, change });
+
+        let mut replay = Replay::new(world.recording.clone());
+        assert!(replay.problems().is_empty());
+        replay.seek(2.0);
+        assert_eq!(
+            replay.problems(),
+            ["couldn't make a change - no object was sent"]
+        );
+        // starting over forgets them until they come up again
+        replay.seek(0.5);
+        assert!(replay.problems().is_empty());
+    }
+
+    #[test]
+    fn narration_stays_in_time_with_the_drawing() {
+        let start = Instant::now();
+        let at = |seconds: f64| start + Duration::from_secs_f64(seconds);
+        let second = SAMPLE_RATE as usize;
+        let mut world = World::new();
+        let mut recorder = Recorder::new(&mut world, at(0.0));
+        let voice = WavFile::new(vec![1; 10 * second], at(1.0));
+        recorder.start_narrating(&mut world, Box::new(voice), at(1.0));
+        // talking over a long pause in the drawing
+        recorder.listen(&mut world, at(4.0));
+        world.add_layer();
+        recorder.board_changed(&mut world, at(6.0));
+        recorder.stop_narrating(&mut world, at(7.0));
+        assert_eq!(world.recording.narration[0].at, 1.0);
+        assert_eq!(world.recording.narration[0].samples.len(), 6 * second);
+        assert_eq!(world.recording.moments.last().unwrap().at, 6.0);
+        assert_eq!(world.recording.duration(), 7.0);
+        // pauses are cut short again once the narration stops
+        world.add_layer();
+        recorder.board_changed(&mut world, at(60.0));
+        assert_eq!(world.recording.duration(), 7.0 + MAX_PAUSE);
+
+        let mut replay = Replay::new(world.recording.clone());
+        replay.seek(6.0);
+        assert_eq!(layer_names(replay.world()).len(), 2);
+        let heard = samples_between(replay.narration(), 6.5, 7.5, 1.0);
+        assert_eq!((heard[0], heard[second - 1]), (1, 0));
+    }
+
+    #[test]
+    fn drawing_is_recorded_as_it_grows_and_images_by_name() {
+        let start = Instant::now();
+        let at = |seconds: f64| start + Duration::from_secs_f64(seconds);
+        let screen = V2f64::new(800.0, 600.0);
+        let mut world = World::new();
+        let mut recorder = Recorder::new(&mut world, at(0.0));
+        let mut line = SavedLine {
+            points: vec![P2::origin()],
+            transform: ZoomTransform::does_nothing(),
+            stroke: Stroke {
+                color: [0.0, 0.0, 0.0],
+                width: 1.0,
+            },
+        };
+        for i in 1..=20 {
+            line.points.push(P2::new(i as f32, 0.0));
+            recorder.sample(&mut world, Some(&line), screen, at(i as f64 * 0.1));
+        }
+        let grew = world.recording.moments.iter();
+        let grew = grew.filter(|m| matches!(m.change, Change::DrawingGrew { .. }));
+        assert_eq!(grew.count(), 19);
+        world.add(Box::new(line));
+        recorder.board_changed(&mut world, at(2.5));
+
+        // an image that's removed again is still in the replay after the board is saved
+        let mut png = vec![];
+        let pixels = image::DynamicImage::ImageRgba8(image::RgbaImage::new(2, 2));
+        pixels
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .unwrap();
+        let picture = Picture::from_bytes(Rc::from(png)).unwrap();
+        let image = world.add(Box::new(picture));
+        recorder.board_changed(&mut world, at(3.0));
+        world.remove(image);
+        recorder.board_changed(&mut world, at(4.0));
+        let bytes = encode(&world.to_saved(), SaveFormat::Bincode);
+        let world = World::from_saved(decode(&bytes).unwrap()).unwrap();
+        for moment in world.recording.moments.iter() {
+            if let Change::Board(Operation::Add { object, .. }) = &moment.change {
+                assert!(object.attachments.is_empty());
+            }
+        }
+
+        let mut replay = Replay::new(world.recording.clone());
+        replay.seek(2.2);
+        let (_, _, drawing) = replay.drawing().next().unwrap();
+        assert_eq!(drawing.save()["points"].as_array().unwrap().len(), 21);
+        replay.seek(3.5);
+        assert_eq!(replay.drawing().count(), 0);
+        assert_eq!(replay.world().object_count(), 2);
+    }
+
+    #[test]
+    fn merged_boards_play_one_after_the_other() {
+        let start = Instant::now();
+        let at = |seconds: f64| start + Duration::from_secs_f64(seconds);
+        let line = SavedLine {
+            points: vec![P2::origin(), P2::new(1.0, 0.0)],
+            transform: ZoomTransform::does_nothing(),
+            stroke: Stroke {
+                color: [0.0, 0.0, 0.0],
+                width: 1.0,
+            },
+        };
+        let board = |name: &str, seconds: f64| {
+            let mut world = World::new();
+            let mut recorder = Recorder::new(&mut world, at(0.0));
+            world.current_layer_mut().name = String::from(name);
+            world.add(Box::new(line.clone()));
+            // gone before the board was saved, so its id is only in the recording
+            let removed = world.add(Box::new(line.clone()));
+            recorder.board_changed(&mut world, at(seconds));
+            world.remove(removed);
+            recorder.board_changed(&mut world, at(seconds));
+            world
+        };
+        let (first, second) = (board("First", 1.0), board("Second", 1.5));
+        let mut merged = first.to_saved();
+        assert_eq!(merged.merge(second.to_saved()), None);
+        let merged = World::from_saved(merged).unwrap();
+        assert_eq!(merged.recording.duration(), 2.5);
+
+        let mut replay = Replay::new(merged.recording.clone());
+        replay.seek(0.5);
+        assert_eq!(layer_names(replay.world()), ["Layer 1"]);
+        replay.seek(1.2);
+        assert_eq!(layer_names(replay.world()), ["First", "Layer 1"]);
+        replay.seek(2.5);
+        assert_eq!(layer_names(replay.world()), ["First", "Second"]);
+        let ids = |world: &World| -> Vec<ObjectId> {
+            world
+                .layers()
+                .flat_map(|l| l.objects().map(|(id, _)| id))
+                .collect()
+        };
+        assert_eq!(ids(replay.world()), ids(&merged));
+        assert_eq!(replay.world().object_count(), 2);
+
+        let mut merged = first.to_saved();
+        let never_recorded = world::SavedWorld::from_saved_objects(
+            ZoomTransform::does_nothing(),
+            vec![(String::from(SavedLine::KIND), line.save())],
+        );
+        assert!(merged.merge(never_recorded).is_some());
+        assert!(merged.recording.moments.is_empty());
+    }
+}
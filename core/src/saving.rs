@@ -0,0 +1,273 @@
+use crate::excalidraw;
+use crate::line::{SavedLine, Stroke};
+use crate::text::SavedText;
+use crate::util::*;
+use crate::world::SavedWorld;
+use crate::zooming::ZoomTransform;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Which layout of `SavedWorld` boards are written in. Bump it whenever `SavedWorld` or how
+/// anything on it is saved changes, and teach `decode` to bring the older version up to date
+pub const VERSION: u32 = 1;
+
+/// What bincode boards start with, so they can be told apart from ones saved before boards had
+/// a version
+const MAGIC: &[u8] = b"explain\0";
+
+/// A board along with the version it was saved in
+#[derive(Serialize, Deserialize)]
+struct Versioned<B> {
+    version: u32,
+    board: B,
+}
+
+/// Just the version of a json board, to check it before trying to read the rest
+#[derive(Deserialize)]
+struct JsonVersion {
+    version: u32,
+}
+
+/// How boards are written to disk. Either can be read whatever the setting is
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SaveFormat {
+    /// Small and quick, but only explain can make sense of it
+    Bincode,
+    /// Readable json, so boards can be diffed in git or made by scripts
+    Json,
+}
+
+impl SaveFormat {
+    /// What a file at `path` should be written as, going by its name
+    pub fn from_path(path: &Path) -> Self {
+        match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) if name.ends_with(".json") => SaveFormat::Json,
+            _ => SaveFormat::Bincode,
+        }
+    }
+
+    /// What the app's own board is called in this format
+    pub fn file_name(self) -> &'static str {
+        match self {
+            SaveFormat::Bincode => "save.explain",
+            SaveFormat::Json => "save.explain.json",
+        }
+    }
+}
+
+pub fn encode(saved_world: &SavedWorld, format: SaveFormat) -> Vec<u8> {
+    let versioned = Versioned {
+        version: VERSION,
+        board: saved_world,
+    };
+    match format {
+        SaveFormat::Bincode => {
+            let mut to_return = MAGIC.to_vec();
+            bincode::serialize_into(&mut to_return, &versioned).unwrap();
+            to_return
+        }
+        SaveFormat::Json => serde_json::to_vec_pretty(&versioned).unwrap(),
+    }
+}
+
+/// A saved board in either format and any version, json being told apart by starting with a
+/// brace. Boards from older versions are brought up to date
+pub fn decode(bytes: &[u8]) -> Result<SavedWorld<'static>, String> {
+    if let Some(bytes) = bytes.strip_prefix(MAGIC) {
+        let version = bincode::deserialize(bytes).map_err(|e| format!("not a board - {}", e))?;
+        check_version(version)?;
+        let versioned: Versioned<_> =
+            bincode::deserialize(bytes).map_err(|e| format!("not a board - {}", e))?;
+        return Ok(versioned.board);
+    }
+    let first = bytes.iter().find(|b| !b.is_ascii_whitespace());
+    if first == Some(&b'{') {
+        // a json board with a mistake in it is more likely than an old board starting with a brace
+        return decode_json(bytes)
+            .or_else(|json_error| decode_baseline(bytes).map_err(|_| json_error));
+    }
+    decode_baseline(bytes)
+}
+
+fn decode_json(bytes: &[u8]) -> Result<SavedWorld<'static>, String> {
+    let JsonVersion { version } = serde_json::from_slice(bytes).map_err(|e| e.to_string())?;
+    check_version(version)?;
+    let versioned: Versioned<_> = serde_json::from_slice(bytes).map_err(|e| e.to_string())?;
+    Ok(versioned.board)
+}
+
+/// A board saved before boards had a version
+fn decode_baseline(bytes: &[u8]) -> Result<SavedWorld<'static>, String> {
+    let baseline: BaselineWorld =
+        bincode::deserialize(bytes).map_err(|e| format!("not a board - {}", e))?;
+    Ok(baseline.migrate())
+}
+
+fn check_version(version: u32) -> Result<(), String> {
+    if version > VERSION {
+        return Err(format!(
+            "saved by a newer explain (version {}, this one reads up to {})",
+            version, VERSION
+        ));
+    }
+    Ok(())
+}
+
+/// How transforms were saved before boards had a version. The same as `ZoomTransform`, mapping
+/// points like `point*scale + offset`, but in floating point
+#[derive(Deserialize)]
+struct BaselineTransform {
+    scale: f64,
+    offset: V2f64,
+}
+
+impl BaselineTransform {
+    fn migrate(&self) -> ZoomTransform {
+        ZoomTransform::new(self.scale, self.offset)
+    }
+}
+
+#[derive(Deserialize)]
+struct BaselineLine {
+    points: Vec<P2>,
+    transform: BaselineTransform,
+}
+
+#[derive(Deserialize)]
+struct BaselineText {
+    text: String,
+    transform: BaselineTransform,
+    origin: P2,
+}
+
+/// A board saved before boards had a version, which only had lines and text
+#[derive(Deserialize)]
+struct BaselineWorld {
+    /// From the world onto the screen, the other way around from the camera now
+    camera: BaselineTransform,
+    lines: Vec<BaselineLine>,
+    texts: Vec<BaselineText>,
+}
+
+impl BaselineWorld {
+    fn migrate(self) -> SavedWorld<'static> {
+        let camera = &self.camera;
+        let camera = ZoomTransform::new(1.0 / camera.scale, -camera.offset / camera.scale);
+        // text was drawn first, with the lines over it
+        let texts = self.texts.into_iter().map(|t| {
+            let text = SavedText {
+                text: t.text,
+                transform: t.transform.migrate(),
+                origin: t.origin,
+            };
+            (SavedText::KIND, serde_json::to_value(text).unwrap())
+        });
+        let lines = self.lines.into_iter().map(|l| {
+            let line = SavedLine {
+                points: l.points,
+                transform: l.transform.migrate(),
+                // every line was black and this wide
+                stroke: Stroke {
+                    color: [0.0, 0.0, 0.0],
+                    width: 2.0,
+                },
+            };
+            (SavedLine::KIND, serde_json::to_value(line).unwrap())
+        });
+        let objects = texts
+            .chain(lines)
+            .map(|(kind, data)| (String::from(kind), data))
+            .collect();
+        SavedWorld::from_saved_objects(camera, objects)
+    }
+}
+
+/// The board in the file at `path`, which can also be an excalidraw drawing. Nothing is loaded,
+/// so it can be read without a window
+pub fn read(path: &Path) -> Result<SavedWorld<'static>, String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    if excalidraw::is_excalidraw(path) {
+        excalidraw::import(&bytes)
+    } else {
+        decode(&bytes)
+    }
+}
+
+/// Writes `saved_world` to `path` in the format it's named for, which can also be an excalidraw
+/// drawing. Returns anything worth knowing about what couldn't be written
+pub fn write(saved_world: &SavedWorld, path: &Path) -> Result<Option<String>, String> {
+    let (encoded, note) = if excalidraw::is_excalidraw(path) {
+        let (drawing, left_out) = excalidraw::export(saved_world);
+        let note = Some(format!("left out {} images", left_out)).filter(|_| left_out > 0);
+        (serde_json::to_vec_pretty(&drawing).unwrap(), note)
+    } else {
+        (encode(saved_world, SaveFormat::from_path(path)), None)
+    };
+    std::fs::write(path, encoded).map_err(|e| e.to_string())?;
+    Ok(note)
+}
+
+/// Rewrites the board at `from` in the format `to` is named for, without loading it so nothing
+/// needs to be drawn
+pub fn convert(from: &Path, to: &Path) -> Result<Option<String>, String> {
+    write(&read(from)?, to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zooming::ZoomTransform;
+
+    #[test]
+    fn boards_are_read_back_in_either_format() {
+        let mut saved_world = SavedWorld::from_saved_objects(ZoomTransform::does_nothing(), vec![]);
+        saved_world.layers[0].name = String::from("Notes");
+        for &format in [SaveFormat::Bincode, SaveFormat::Json].iter() {
+            let bytes = encode(&saved_world, format);
+            let saved_world = decode(&bytes).unwrap();
+            assert_eq!(saved_world.layers[0].name, "Notes");
+        }
+        let json = String::from_utf8(encode(&saved_world, SaveFormat::Json)).unwrap();
+        assert!(json.contains("\"name\": \"Notes\""), "{}", json);
+        let error = decode(b"{ \"layers\": 3 }").err().unwrap();
+        assert!(error.contains("line 1"), "{}", error);
+
+        assert_eq!(
+            SaveFormat::from_path(Path::new("a/board.explain.json")),
+            SaveFormat::Json
+        );
+        assert_eq!(
+            SaveFormat::from_path(Path::new("board.explain")),
+            SaveFormat::Bincode
+        );
+    }
+
+    #[test]
+    fn boards_from_before_versions_are_brought_up_to_date() {
+        // the camera took the world onto the screen like `point*2 + (10, 20)`
+        let camera = (2.0f64, vec![10.0f64, 20.0]);
+        let line = (
+            vec![vec![0.0f32, 0.0], vec![3.0, 4.0]],
+            (0.5f64, vec![-5.0f64, -10.0]),
+        );
+        let text = ("hi", (1.0f64, vec![0.0f64, 0.0]), vec![1.0f32, 1.0]);
+        let bytes = bincode::serialize(&(camera, vec![line], vec![text])).unwrap();
+
+        let saved_world = decode(&bytes).unwrap();
+        let kinds: Vec<&str> = saved_world.layers[0].kinds().collect();
+        assert_eq!(kinds, ["text", "line"]);
+        let on_screen = saved_world.camera.transform_point(P2f64::new(10.0, 20.0));
+        assert_eq!(on_screen, P2f64::new(0.0, 0.0));
+        let world = crate::world::World::from_saved(saved_world).unwrap();
+        assert_eq!(world.object_count(), 2);
+
+        let saved_world = SavedWorld::from_saved_objects(ZoomTransform::does_nothing(), vec![]);
+        let mut bytes = encode(&saved_world, SaveFormat::Bincode);
+        bytes[MAGIC.len()] = VERSION as u8 + 1;
+        assert!(decode(&bytes).err().unwrap().contains("newer"));
+        let json = String::from_utf8(encode(&saved_world, SaveFormat::Json)).unwrap();
+        let json = json.replace(&format!("\"version\": {}", VERSION), "\"version\": 99");
+        assert!(decode(json.as_bytes()).err().unwrap().contains("newer"));
+    }
+}
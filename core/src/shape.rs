@@ -0,0 +1,191 @@
+use crate::fixed::FixedV2;
+use crate::geometry::Rect;
+use crate::svg;
+use crate::util::*;
+use crate::zooming::ZoomTransform;
+use image::{Rgba, RgbaImage};
+use serde_json::Value;
+use std::ops::Range;
+use std::rc::Rc;
+
+/// An object on the board as plain data, so it can be measured, picked, saved and drawn without
+/// gl, like from the command line. The app keeps what draws it on the screen separately
+pub trait Shape {
+    /// Where the object is in the world, `None` if there's nothing to it
+    fn bounding_box(&self) -> Option<Rect>;
+    /// If `point` in the world is on the object, or less than `tolerance` world units from it
+    fn hit(&self, point: FixedV2, tolerance: f64) -> bool {
+        match self.bounding_box() {
+            Some(b) => b.contains(point, tolerance),
+            None => false,
+        }
+    }
+    fn transform(&self) -> &ZoomTransform;
+    fn set_transform(&mut self, z: ZoomTransform);
+    /// Name of the object's kind in `object_kinds::KINDS`, which knows how to read it back
+    fn kind(&self) -> &'static str;
+    /// Everything needed to make the object again, read back by its kind
+    fn save(&self) -> Value;
+    /// Data too big to go in `save`, by the name `save` refers to it with
+    fn attachments(&self) -> Vec<(String, Rc<[u8]>)> {
+        vec![]
+    }
+    /// An svg element drawing it as it looks through `view`, which is the camera of the page
+    fn to_svg(&self, view: &ZoomTransform) -> String;
+    /// Draws it onto `canvas` as it looks through `view`, the way it's drawn on the screen
+    fn paint(&self, canvas: &mut Canvas, view: &ZoomTransform);
+}
+
+/// The smallest rect around all of `rects`
+pub fn bounds<I: Iterator<Item = Rect>>(rects: I) -> Option<Rect> {
+    rects.fold(None, |acc: Option<Rect>, r| match acc {
+        Some(acc) => Some(acc.union(&r)),
+        None => Some(r),
+    })
+}
+
+/// Pixels drawn without the graphics card
+pub struct Canvas {
+    pub image: RgbaImage,
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32, background: [f32; 3]) -> Self {
+        let [r, g, b] = background;
+        let byte = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        Self {
+            image: RgbaImage::from_pixel(width, height, Rgba([byte(r), byte(g), byte(b), 255])),
+        }
+    }
+
+    /// Mixes `color` into the pixel at `x`, `y` by `alpha` from 0 to 1
+    pub fn blend(&mut self, x: u32, y: u32, color: [f32; 3], alpha: f32) {
+        let alpha = alpha.clamp(0.0, 1.0);
+        let pixel = self.image.get_pixel_mut(x, y);
+        for (channel, c) in pixel.0.iter_mut().zip(color.iter()) {
+            let mixed = *channel as f32 * (1.0 - alpha) + c.clamp(0.0, 1.0) * 255.0 * alpha;
+            *channel = mixed.round() as u8;
+        }
+    }
+
+    /// The columns and rows of the pixels with their middles between `min` and `max`, leaving
+    /// out any off the canvas
+    pub fn pixels_between(&self, min: P2f64, max: P2f64) -> (Range<u32>, Range<u32>) {
+        let range = |low: f64, high: f64, size: u32| {
+            let start = (low - 0.5).ceil().max(0.0).min(size as f64) as u32;
+            let end = ((high - 0.5).floor() + 1.0).max(0.0).min(size as f64) as u32;
+            start..end.max(start)
+        };
+        (
+            range(min.x, max.x, self.image.width()),
+            range(min.y, max.y, self.image.height()),
+        )
+    }
+
+    /// A line through `points`, reaching out `width` pixels either side of them with the last
+    /// pixel faded like the line shader does
+    pub fn stroke(&mut self, points: &[P2f64], width: f64, color: [f32; 3]) {
+        if points.len() < 2 {
+            return;
+        }
+        let reach = V2f64::repeat(width + 1.0);
+        let (low, high) = points.iter().fold(
+            (V2f64::repeat(f64::MAX), V2f64::repeat(f64::MIN)),
+            |(low, high), p| (low.inf(&p.coords), high.sup(&p.coords)),
+        );
+        let (columns, rows) =
+            self.pixels_between(P2f64::from(low - reach), P2f64::from(high + reach));
+        if columns.is_empty() || rows.is_empty() {
+            return;
+        }
+        // the most each pixel is covered by any segment, so corners aren't drawn over twice
+        let columns_count = columns.len();
+        let mut coverage = vec![0f32; columns_count * rows.len()];
+        for segment in points.windows(2) {
+            let (a, b) = (segment[0], segment[1]);
+            let (xs, ys) = self.pixels_between(
+                P2f64::from(a.coords.inf(&b.coords) - reach),
+                P2f64::from(a.coords.sup(&b.coords) + reach),
+            );
+            let along = b - a;
+            for y in ys {
+                for x in xs.clone() {
+                    let p = P2f64::new(x as f64 + 0.5, y as f64 + 0.5);
+                    let t = ((p - a).dot(&along) / along.norm_squared()).clamp(0.0, 1.0);
+                    let closest = if t.is_nan() { a } else { a + along * t };
+                    let covered = (width - (p - closest).norm()).clamp(0.0, 1.0) as f32;
+                    let i =
+                        (y - rows.start) as usize * columns_count + (x - columns.start) as usize;
+                    coverage[i] = coverage[i].max(covered);
+                }
+            }
+        }
+        for y in rows.clone() {
+            for x in columns.clone() {
+                let i = (y - rows.start) as usize * columns_count + (x - columns.start) as usize;
+                if coverage[i] > 0.0 {
+                    self.blend(x, y, color, coverage[i]);
+                }
+            }
+        }
+    }
+}
+
+/// Widest or tallest a picture is made when no size is asked for, so things far apart don't
+/// make an enormous image
+const MAX_PICTURE_SIZE: f64 = 4096.0;
+
+/// Where a picture just big enough for what's in `bounds` looks from, zoomed in as far as
+/// `camera` unless that would make it bigger than `MAX_PICTURE_SIZE`, and how big it is
+pub fn picture_page(bounds: Rect, camera: &ZoomTransform) -> (ZoomTransform, (u32, u32)) {
+    let (mut view, mut page) = svg::page(Some(bounds), camera);
+    let largest = page.x.max(page.y);
+    if largest > MAX_PICTURE_SIZE {
+        let scale = camera.scale() * largest / MAX_PICTURE_SIZE;
+        let zoomed_out = ZoomTransform::new(scale, V2f64::zeros());
+        let (v, p) = svg::page(Some(bounds), &zoomed_out);
+        view = v;
+        page = p;
+    }
+    (view, (page.x.ceil() as u32, page.y.ceil() as u32))
+}
+
+/// `shapes` drawn in order onto a `size` canvas, looking through `camera`
+pub fn paint(
+    shapes: &[Box<dyn Shape>],
+    camera: &ZoomTransform,
+    size: (u32, u32),
+    background: [f32; 3],
+) -> RgbaImage {
+    let mut canvas = Canvas::new(size.0, size.1, background);
+    for shape in shapes {
+        shape.paint(&mut canvas, camera);
+    }
+    canvas.image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strokes_cover_the_pixels_along_them() {
+        let mut canvas = Canvas::new(20, 10, [1.0, 1.0, 1.0]);
+        let points = [P2f64::new(2.0, 5.0), P2f64::new(18.0, 5.0)];
+        canvas.stroke(&points, 2.0, [1.0, 0.0, 0.0]);
+        assert_eq!(canvas.image.get_pixel(10, 5).0, [255, 0, 0, 255]);
+        assert_eq!(canvas.image.get_pixel(10, 4).0, [255, 0, 0, 255]);
+        // the edge fades out, and past it is left alone
+        let edge = canvas.image.get_pixel(10, 6).0;
+        assert!(edge[1] > 0 && edge[1] < 255, "{:?}", edge);
+        assert_eq!(canvas.image.get_pixel(10, 8).0, [255, 255, 255, 255]);
+        // lines running off the canvas are cut off at the edge
+        canvas.stroke(
+            &[P2f64::new(-50.0, 1.5), P2f64::new(50.0, 1.5)],
+            1.0,
+            [0.0; 3],
+        );
+        assert_eq!(canvas.image.get_pixel(0, 0).0, [255, 255, 255, 255]);
+        assert_eq!(canvas.image.get_pixel(19, 1).0, [0, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,904 @@
+use crate::geometry::Rect;
+use crate::line::Stroke;
+use crate::shape::{bounds, Shape};
+use crate::util::*;
+use crate::zooming::ZoomTransform;
+
+/// Room left around the objects, in pixels
+const MARGIN: f64 = 8.0;
+/// How many straight pieces each curve in an imported svg is broken into
+const CURVE_SEGMENTS: usize = 16;
+/// Height of imported text when the svg doesn't say, in svg units
+const DEFAULT_FONT_SIZE: f64 = 16.0;
+/// Roughly how far down text's baseline is, as a fraction of the font size. Svgs place text by
+/// its baseline, and text objects by their top
+pub const BASELINE: f64 = 0.8;
+
+/// `color` as an svg color like `#ff8000`
+pub fn color(color: [f32; 3]) -> String {
+    let [r, g, b] = color;
+    let byte = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", byte(r), byte(g), byte(b))
+}
+
+/// `text` with the characters that mean something in xml replaced
+pub fn escape(text: &str) -> String {
+    let mut to_return = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => to_return.push_str("&amp;"),
+            '<' => to_return.push_str("&lt;"),
+            '>' => to_return.push_str("&gt;"),
+            '"' => to_return.push_str("&quot;"),
+            '\'' => to_return.push_str("&apos;"),
+            c => to_return.push(c),
+        }
+    }
+    to_return
+}
+
+/// Where a page just big enough for what's in `bounds` looks from, zoomed in as far as
+/// `camera`, and how big the page is
+pub fn page(bounds: Option<Rect>, camera: &ZoomTransform) -> (ZoomTransform, V2f64) {
+    match bounds {
+        Some(bounds) => {
+            let margin = V2f64::new(MARGIN, MARGIN);
+            let view =
+                ZoomTransform::new(camera.scale(), -margin * camera.scale()).translated(bounds.min);
+            (view, bounds.size() / camera.scale() + margin * 2.0)
+        }
+        None => (camera.clone(), V2f64::zeros()),
+    }
+}
+
+/// An svg document `size` big holding `elements`, with `metadata` tucked into an element with the
+/// given id if there is any. It's written as is, so it has to be something like base64 that
+/// doesn't need escaping
+pub fn document<I: Iterator<Item = String>>(
+    size: V2f64,
+    metadata: Option<(&str, &str)>,
+    elements: I,
+) -> String {
+    let mut to_return = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"0 0 {:.2} {:.2}\">\n",
+        size.x.ceil(),
+        size.y.ceil(),
+        size.x,
+        size.y
+    );
+    if let Some((id, metadata)) = metadata {
+        to_return.push_str(&format!(
+            "<metadata id=\"{}\">{}</metadata>\n",
+            id, metadata
+        ));
+    }
+    for element in elements {
+        to_return.push_str(&element);
+        to_return.push('\n');
+    }
+    to_return.push_str("</svg>\n");
+    to_return
+}
+
+/// An svg document drawing `shapes` at the size they look through `camera`
+pub fn render_shapes(shapes: &[Box<dyn Shape>], camera: &ZoomTransform) -> String {
+    let (view, size) = page(
+        bounds(shapes.iter().filter_map(|s| s.bounding_box())),
+        camera,
+    );
+    document(size, None, shapes.iter().map(|s| s.to_svg(&view)))
+}
+
+/// What's in the element with the id `id` in an svg made by `document`
+pub fn metadata<'a>(svg: &'a str, id: &str) -> Option<&'a str> {
+    let start_tag = format!("<metadata id=\"{}\">", id);
+    let start = svg.find(&start_tag)? + start_tag.len();
+    let length = svg[start..].find("</metadata>")?;
+    Some(&svg[start..start + length])
+}
+
+/// Something drawable found in an svg, in the svg's own units
+#[derive(Clone, PartialEq, Debug)]
+pub enum Imported {
+    Stroke {
+        points: Vec<P2f64>,
+        stroke: Stroke,
+    },
+    Label {
+        /// Where the baseline starts
+        at: P2f64,
+        text: String,
+        size: f64,
+    },
+}
+
+type Affine = na::Matrix3<f64>;
+
+/// What elements get from the groups they're in
+#[derive(Clone)]
+struct Inherited {
+    transform: Affine,
+    /// `None` for `none`
+    stroke: Option<[f32; 3]>,
+    fill: Option<[f32; 3]>,
+    stroke_width: f64,
+    font_size: f64,
+}
+
+impl Default for Inherited {
+    fn default() -> Self {
+        Self {
+            transform: Affine::identity(),
+            stroke: None,
+            fill: Some([0.0, 0.0, 0.0]),
+            stroke_width: 1.0,
+            font_size: DEFAULT_FONT_SIZE,
+        }
+    }
+}
+
+impl Inherited {
+    /// What `tag` and anything inside it uses
+    fn within(&self, tag: &Tag) -> Self {
+        let mut to_return = self.clone();
+        if let Some(transform) = tag.attribute("transform") {
+            to_return.transform *= parse_transform(transform);
+        }
+        if let Some(paint) = tag.property("stroke").and_then(|p| parse_paint(&p)) {
+            to_return.stroke = paint;
+        }
+        if let Some(paint) = tag.property("fill").and_then(|p| parse_paint(&p)) {
+            to_return.fill = paint;
+        }
+        if let Some(width) = tag.property("stroke-width").and_then(|w| first_number(&w)) {
+            to_return.stroke_width = width;
+        }
+        if let Some(size) = tag.property("font-size").and_then(|s| first_number(&s)) {
+            to_return.font_size = size;
+        }
+        to_return
+    }
+
+    /// How much the transform scales things up by, on average
+    fn scale(&self) -> f64 {
+        let m = self.transform;
+        (m[(0, 0)] * m[(1, 1)] - m[(0, 1)] * m[(1, 0)]).abs().sqrt()
+    }
+
+    fn apply(&self, p: P2f64) -> P2f64 {
+        let p = self.transform * na::Vector3::new(p.x, p.y, 1.0);
+        P2f64::new(p.x, p.y)
+    }
+
+    /// Lines through each of `pieces`, outlined in the stroke color or else the fill color
+    fn strokes(&self, pieces: Vec<Vec<P2f64>>) -> Vec<Imported> {
+        let color = match self.stroke.or(self.fill) {
+            Some(color) => color,
+            None => return vec![],
+        };
+        // lines are as wide on the screen whatever the zoom, so the transform doesn't change it
+        let stroke = Stroke {
+            color,
+            width: self.stroke_width as f32,
+        };
+        pieces
+            .into_iter()
+            .filter(|points| points.len() > 1)
+            .map(|points| Imported::Stroke {
+                points: points.into_iter().map(|p| self.apply(p)).collect(),
+                stroke,
+            })
+            .collect()
+    }
+}
+
+struct Tag<'a> {
+    name: &'a str,
+    attributes: Vec<(&'a str, &'a str)>,
+    /// Like `</g>`
+    closing: bool,
+    /// Like `<path/>`, which has nothing inside it
+    empty: bool,
+}
+
+impl<'a> Tag<'a> {
+    fn attribute(&self, name: &str) -> Option<&'a str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, value)| *value)
+    }
+
+    /// A presentation attribute, which can also be set in the `style` attribute
+    fn property(&self, name: &str) -> Option<String> {
+        let styled = self.attribute("style").and_then(|style| {
+            style.split(';').find_map(|declaration| {
+                let (property, value) = declaration.split_once(':')?;
+                Some(value.trim()).filter(|_| property.trim() == name)
+            })
+        });
+        styled
+            .or_else(|| self.attribute(name))
+            .map(|v| decode_entities(v.trim()))
+    }
+}
+
+/// The next tag in `svg` from `at`, moving `at` past it. Comments, declarations and processing
+/// instructions are skipped
+fn next_tag<'a>(svg: &'a str, at: &mut usize) -> Option<Tag<'a>> {
+    loop {
+        let start = *at + svg[*at..].find('<')?;
+        let rest = &svg[start..];
+        let skip_to = if rest.starts_with("<!--") {
+            Some("-->")
+        } else if rest.starts_with("<![CDATA[") {
+            Some("]]>")
+        } else if rest.starts_with("<?") {
+            Some("?>")
+        } else if rest.starts_with("<!") {
+            Some(">")
+        } else {
+            None
+        };
+        if let Some(end) = skip_to {
+            *at = start + rest.find(end)? + end.len();
+            continue;
+        }
+        // quoted attribute values can have a > in them
+        let mut quote = None;
+        let length = rest.char_indices().position(|(_, c)| {
+            match quote {
+                Some(q) if c == q => quote = None,
+                None if c == '"' || c == '\'' => quote = Some(c),
+                None if c == '>' => return true,
+                _ => {}
+            }
+            false
+        })?;
+        let inside = &rest[1..rest.char_indices().nth(length)?.0];
+        *at = start + inside.len() + 2;
+        let closing = inside.starts_with('/');
+        let empty = inside.ends_with('/');
+        let inside = inside.trim_start_matches('/').trim_end_matches('/');
+        let name_length = inside
+            .find(|c: char| c.is_whitespace())
+            .unwrap_or(inside.len());
+        return Some(Tag {
+            name: &inside[..name_length],
+            attributes: attributes(&inside[name_length..]),
+            closing,
+            empty,
+        });
+    }
+}
+
+/// Pairs like `name="value"`
+fn attributes(text: &str) -> Vec<(&str, &str)> {
+    let mut to_return = vec![];
+    let mut rest = text;
+    while let Some(equals) = rest.find('=') {
+        let name = rest[..equals].trim();
+        let after = rest[equals + 1..].trim_start();
+        let quote = match after.chars().next() {
+            Some(q) if q == '"' || q == '\'' => q,
+            _ => break,
+        };
+        let length = match after[1..].find(quote) {
+            Some(length) => length,
+            None => break,
+        };
+        to_return.push((name, &after[1..1 + length]));
+        rest = &after[length + 2..];
+    }
+    to_return
+}
+
+/// `text` with xml entities like `&amp;` replaced by what they stand for
+fn decode_entities(text: &str) -> String {
+    let mut to_return = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        to_return.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => ' ',
+                number => {
+                    let code = match number.strip_prefix("#x") {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => number.strip_prefix('#')?.parse().ok()?,
+                    };
+                    std::char::from_u32(code)?
+                }
+            };
+            Some((c, end + 1))
+        });
+        match decoded {
+            Some((c, length)) => {
+                to_return.push(c);
+                rest = &rest[length..];
+            }
+            None => {
+                to_return.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    to_return.push_str(rest);
+    to_return
+}
+
+/// Reads the numbers and commands of path data and other lists of numbers
+struct NumberReader<'a> {
+    text: &'a [u8],
+    at: usize,
+}
+
+impl<'a> NumberReader<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            text: text.as_bytes(),
+            at: 0,
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        while let Some(c) = self.text.get(self.at) {
+            if !(c.is_ascii_whitespace() || *c == b',') {
+                break;
+            }
+            self.at += 1;
+        }
+    }
+
+    /// The next path command letter, if that's what's next
+    fn command(&mut self) -> Option<u8> {
+        self.skip_separators();
+        let c = *self.text.get(self.at)?;
+        if c.is_ascii_alphabetic() && c != b'e' && c != b'E' {
+            self.at += 1;
+            Some(c)
+        } else {
+            None
+        }
+    }
+
+    fn number(&mut self) -> Option<f64> {
+        self.skip_separators();
+        let start = self.at;
+        let mut end = start;
+        let mut seen_dot = false;
+        let mut seen_exponent = false;
+        while let Some(&c) = self.text.get(end) {
+            let fits = match c {
+                b'0'..=b'9' => true,
+                b'+' | b'-' => end == start || matches!(self.text[end - 1], b'e' | b'E'),
+                // a second dot starts another number, like in `0.5.5`
+                b'.' if !seen_dot && !seen_exponent => {
+                    seen_dot = true;
+                    true
+                }
+                b'e' | b'E' if !seen_exponent && end > start => {
+                    seen_exponent = true;
+                    true
+                }
+                _ => false,
+            };
+            if !fits {
+                break;
+            }
+            end += 1;
+        }
+        let number = std::str::from_utf8(&self.text[start..end])
+            .ok()?
+            .parse()
+            .ok()?;
+        self.at = end;
+        Some(number)
+    }
+
+    /// A flag of an arc, which doesn't need anything separating it from what comes next
+    fn flag(&mut self) -> Option<bool> {
+        self.skip_separators();
+        let flag = match self.text.get(self.at)? {
+            b'0' => false,
+            b'1' => true,
+            _ => return None,
+        };
+        self.at += 1;
+        Some(flag)
+    }
+
+    fn point(&mut self) -> Option<P2f64> {
+        Some(P2f64::new(self.number()?, self.number()?))
+    }
+
+    fn is_done(&mut self) -> bool {
+        self.skip_separators();
+        self.at >= self.text.len()
+    }
+}
+
+/// The first number in something like `12px`
+fn first_number(text: &str) -> Option<f64> {
+    NumberReader::new(text).number()
+}
+
+fn numbers(text: &str) -> Vec<f64> {
+    let mut reader = NumberReader::new(text);
+    std::iter::from_fn(|| reader.number()).collect()
+}
+
+fn parse_transform(text: &str) -> Affine {
+    let mut to_return = Affine::identity();
+    for part in text.split(')') {
+        let (name, arguments) = match part.split_once('(') {
+            Some(split) => split,
+            None => continue,
+        };
+        let translation = |x: f64, y: f64| Affine::new(1.0, 0.0, x, 0.0, 1.0, y, 0.0, 0.0, 1.0);
+        let transform = match (
+            name.trim_matches(|c: char| c.is_whitespace() || c == ','),
+            &numbers(arguments)[..],
+        ) {
+            ("matrix", &[a, b, c, d, e, f]) => Affine::new(a, c, e, b, d, f, 0.0, 0.0, 1.0),
+            ("translate", &[x]) => translation(x, 0.0),
+            ("translate", &[x, y]) => translation(x, y),
+            ("scale", &[s]) => Affine::new_nonuniform_scaling(&na::Vector2::new(s, s)),
+            ("scale", &[x, y]) => Affine::new_nonuniform_scaling(&na::Vector2::new(x, y)),
+            ("rotate", &[angle]) => Affine::new_rotation(angle.to_radians()),
+            ("rotate", &[angle, x, y]) => {
+                translation(x, y) * Affine::new_rotation(angle.to_radians()) * translation(-x, -y)
+            }
+            ("skewX", &[angle]) => Affine::new(
+                1.0,
+                angle.to_radians().tan(),
+                0.0,
+                0.0,
+                1.0,
+                0.0,
+                0.0,
+                0.0,
+                1.0,
+            ),
+            ("skewY", &[angle]) => Affine::new(
+                1.0,
+                0.0,
+                0.0,
+                angle.to_radians().tan(),
+                1.0,
+                0.0,
+                0.0,
+                0.0,
+                1.0,
+            ),
+            _ => continue,
+        };
+        to_return *= transform;
+    }
+    to_return
+}
+
+/// A color like `#f80`, `#ff8800`, `rgb(255, 136, 0)` or a common name. `Some(None)` for
+/// `none`, and `None` for anything else, which is left as whatever it was
+pub fn parse_paint(text: &str) -> Option<Option<[f32; 3]>> {
+    let text = text.trim().to_ascii_lowercase();
+    let byte = |hex: &str| u8::from_str_radix(hex, 16).ok().map(|b| b as f32 / 255.0);
+    let color = match text.as_str() {
+        "none" | "transparent" => return Some(None),
+        "black" => [0.0, 0.0, 0.0],
+        "white" => [1.0, 1.0, 1.0],
+        "red" => [1.0, 0.0, 0.0],
+        "green" => [0.0, 0.5, 0.0],
+        "blue" => [0.0, 0.0, 1.0],
+        "yellow" => [1.0, 1.0, 0.0],
+        "orange" => [1.0, 0.65, 0.0],
+        "purple" => [0.5, 0.0, 0.5],
+        "gray" | "grey" => [0.5, 0.5, 0.5],
+        hex if hex.starts_with('#') && hex.len() == 7 => {
+            [byte(&hex[1..3])?, byte(&hex[3..5])?, byte(&hex[5..7])?]
+        }
+        hex if hex.starts_with('#') && hex.len() == 4 => {
+            let short = |i: usize| byte(&hex[i..=i].repeat(2));
+            [short(1)?, short(2)?, short(3)?]
+        }
+        rgb if rgb.starts_with("rgb(") => match &numbers(&rgb[4..])[..] {
+            &[r, g, b] => [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0],
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(Some(color))
+}
+
+fn cubic(from: P2f64, c1: P2f64, c2: P2f64, to: P2f64) -> impl Iterator<Item = P2f64> {
+    (1..=CURVE_SEGMENTS).map(move |i| {
+        let t = i as f64 / CURVE_SEGMENTS as f64;
+        let u = 1.0 - t;
+        P2f64::from(
+            from.coords * u * u * u
+                + c1.coords * 3.0 * u * u * t
+                + c2.coords * 3.0 * u * t * t
+                + to.coords * t * t * t,
+        )
+    })
+}
+
+fn quadratic(from: P2f64, control: P2f64, to: P2f64) -> impl Iterator<Item = P2f64> {
+    (1..=CURVE_SEGMENTS).map(move |i| {
+        let t = i as f64 / CURVE_SEGMENTS as f64;
+        let u = 1.0 - t;
+        P2f64::from(from.coords * u * u + control.coords * 2.0 * u * t + to.coords * t * t)
+    })
+}
+
+/// Points along an elliptical arc from `from` to `to`, worked out the way the svg spec says to
+fn arc(
+    from: P2f64,
+    radii: V2f64,
+    rotation: f64,
+    large: bool,
+    sweep: bool,
+    to: P2f64,
+) -> Vec<P2f64> {
+    let (mut rx, mut ry) = (radii.x.abs(), radii.y.abs());
+    if from == to {
+        return vec![];
+    }
+    if rx == 0.0 || ry == 0.0 {
+        return vec![to];
+    }
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    let half = (from - to) / 2.0;
+    let x1 = cos * half.x + sin * half.y;
+    let y1 = -sin * half.x + cos * half.y;
+    // radii too small to reach are scaled up until they just do
+    let reach = x1 * x1 / (rx * rx) + y1 * y1 / (ry * ry);
+    if reach > 1.0 {
+        rx *= reach.sqrt();
+        ry *= reach.sqrt();
+    }
+    let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let sign = if large == sweep { -1.0 } else { 1.0 };
+    let coefficient = sign * (numerator / denominator).max(0.0).sqrt();
+    let (cx1, cy1) = (coefficient * rx * y1 / ry, -coefficient * ry * x1 / rx);
+    let middle = (from.coords + to.coords) / 2.0;
+    let center = V2f64::new(cos * cx1 - sin * cy1, sin * cx1 + cos * cy1) + middle;
+    let angle = |u: V2f64, v: V2f64| (u.x * v.y - u.y * v.x).atan2(u.dot(&v));
+    let start = V2f64::new((x1 - cx1) / rx, (y1 - cy1) / ry);
+    let end = V2f64::new((-x1 - cx1) / rx, (-y1 - cy1) / ry);
+    let theta = angle(V2f64::new(1.0, 0.0), start);
+    let mut delta = angle(start, end);
+    if !sweep && delta > 0.0 {
+        delta -= std::f64::consts::TAU;
+    } else if sweep && delta < 0.0 {
+        delta += std::f64::consts::TAU;
+    }
+    let mut to_return: Vec<P2f64> = (1..CURVE_SEGMENTS)
+        .map(|i| {
+            let (s, c) = (theta + delta * i as f64 / CURVE_SEGMENTS as f64).sin_cos();
+            let (x, y) = (rx * c, ry * s);
+            P2f64::from(V2f64::new(cos * x - sin * y, sin * x + cos * y) + center)
+        })
+        .collect();
+    to_return.push(to);
+    to_return
+}
+
+/// Every connected piece of the path in the `d` attribute of a path, as points along it
+fn path_pieces(d: &str) -> Vec<Vec<P2f64>> {
+    let mut pieces: Vec<Vec<P2f64>> = vec![];
+    let mut reader = NumberReader::new(d);
+    let mut current = P2f64::origin();
+    let mut start = current;
+    // the last control point of a curve, for smooth curves carrying on from it
+    let mut last_cubic: Option<P2f64> = None;
+    let mut last_quadratic: Option<P2f64> = None;
+    let mut command = None;
+    // stops at the first thing that doesn't make sense, keeping what came before
+    let _ = (|| -> Option<()> {
+        loop {
+            match reader.command() {
+                Some(c) => command = Some(c),
+                None if reader.is_done() => return None,
+                None => {}
+            }
+            let c = command?;
+            let relative = c.is_ascii_lowercase();
+            let base = if relative {
+                current.coords
+            } else {
+                V2f64::zeros()
+            };
+            let mut added = vec![];
+            let (mut cubic_control, mut quadratic_control) = (None, None);
+            match c.to_ascii_uppercase() {
+                b'M' => {
+                    current = reader.point()? + base;
+                    start = current;
+                    pieces.push(vec![current]);
+                    // more points after a move are lines to them
+                    command = Some(if relative { b'l' } else { b'L' });
+                }
+                b'L' => added.push(reader.point()? + base),
+                b'H' => added.push(P2f64::new(reader.number()? + base.x, current.y)),
+                b'V' => added.push(P2f64::new(current.x, reader.number()? + base.y)),
+                b'C' | b'S' => {
+                    let c1 = if c.eq_ignore_ascii_case(&b'C') {
+                        reader.point()? + base
+                    } else {
+                        // the last control point reflected, or the current point if there isn't one
+                        last_cubic.map_or(current, |l| current + (current - l))
+                    };
+                    let c2 = reader.point()? + base;
+                    let to = reader.point()? + base;
+                    added.extend(cubic(current, c1, c2, to));
+                    cubic_control = Some(c2);
+                }
+                b'Q' | b'T' => {
+                    let control = if c.eq_ignore_ascii_case(&b'Q') {
+                        reader.point()? + base
+                    } else {
+                        last_quadratic.map_or(current, |l| current + (current - l))
+                    };
+                    let to = reader.point()? + base;
+                    added.extend(quadratic(current, control, to));
+                    quadratic_control = Some(control);
+                }
+                b'A' => {
+                    let radii = V2f64::new(reader.number()?, reader.number()?);
+                    let rotation = reader.number()?;
+                    let (large, sweep) = (reader.flag()?, reader.flag()?);
+                    let to = reader.point()? + base;
+                    added.extend(arc(current, radii, rotation, large, sweep, to));
+                }
+                b'Z' => {
+                    added.push(start);
+                    // nothing can follow without a new command
+                    command = None;
+                }
+                _ => return None,
+            }
+            if let Some(&last) = added.last() {
+                if pieces.is_empty() {
+                    pieces.push(vec![current]);
+                }
+                pieces.last_mut().unwrap().extend(added);
+                current = last;
+            }
+            last_cubic = cubic_control;
+            last_quadratic = quadratic_control;
+        }
+    })();
+    pieces
+}
+
+/// Points around an ellipse, ending where they start
+pub fn ellipse(center: P2f64, radii: V2f64) -> Vec<P2f64> {
+    let count = CURVE_SEGMENTS * 2;
+    (0..=count)
+        .map(|i| {
+            let (s, c) = (i as f64 / count as f64 * std::f64::consts::TAU).sin_cos();
+            center + V2f64::new(radii.x * c, radii.y * s)
+        })
+        .collect()
+}
+
+/// What an svg document draws, as lines and text in the svg's units. Anything that can't be
+/// drawn with lines and text, like gradients and images, is left out
+pub fn import(svg: &str) -> Vec<Imported> {
+    let mut to_return = vec![];
+    let mut groups = vec![Inherited::default()];
+    let mut at = 0;
+    while let Some(tag) = next_tag(svg, &mut at) {
+        let is_group = matches!(tag.name, "svg" | "g" | "a");
+        if tag.closing {
+            if is_group && groups.len() > 1 {
+                groups.pop();
+            }
+            continue;
+        }
+        let inherited = groups.last().unwrap().within(&tag);
+        let number = |name: &str| tag.attribute(name).and_then(first_number).unwrap_or(0.0);
+        let pieces = match tag.name {
+            _ if is_group => {
+                if !tag.empty {
+                    groups.push(inherited);
+                }
+                continue;
+            }
+            // only drawn where they're used, which isn't supported
+            "defs" | "symbol" | "clipPath" | "mask" | "marker" | "pattern" | "title" | "desc"
+            | "metadata" | "style" | "script" => {
+                if !tag.empty {
+                    let end = format!("</{}", tag.name);
+                    at = svg[at..].find(&end).map_or(svg.len(), |e| at + e);
+                }
+                continue;
+            }
+            "path" => path_pieces(tag.attribute("d").unwrap_or("")),
+            "line" => vec![vec![
+                P2f64::new(number("x1"), number("y1")),
+                P2f64::new(number("x2"), number("y2")),
+            ]],
+            "polyline" | "polygon" => {
+                let coordinates = numbers(tag.attribute("points").unwrap_or(""));
+                let mut points: Vec<P2f64> = coordinates
+                    .chunks_exact(2)
+                    .map(|c| P2f64::new(c[0], c[1]))
+                    .collect();
+                if tag.name == "polygon" && !points.is_empty() {
+                    points.push(points[0]);
+                }
+                vec![points]
+            }
+            "rect" => {
+                let (x, y) = (number("x"), number("y"));
+                let (width, height) = (number("width"), number("height"));
+                vec![vec![
+                    P2f64::new(x, y),
+                    P2f64::new(x + width, y),
+                    P2f64::new(x + width, y + height),
+                    P2f64::new(x, y + height),
+                    P2f64::new(x, y),
+                ]]
+            }
+            "circle" | "ellipse" => {
+                let center = P2f64::new(number("cx"), number("cy"));
+                let radii = if tag.name == "circle" {
+                    V2f64::new(number("r"), number("r"))
+                } else {
+                    V2f64::new(number("rx"), number("ry"))
+                };
+                vec![ellipse(center, radii)]
+            }
+            // a self-closing text has nothing in it, and mustn't swallow what comes after it
+            "text" if tag.empty => continue,
+            "text" => {
+                let end = svg[at..].find("</text>").map_or(svg.len(), |e| at + e);
+                // anything in tspans is kept, the tspans themselves aren't
+                let mut content = String::new();
+                let mut rest = &svg[at..end];
+                while let Some(start) = rest.find('<') {
+                    content.push_str(&rest[..start]);
+                    rest = rest[start..]
+                        .find('>')
+                        .map_or("", |e| &rest[start + e + 1..]);
+                }
+                content.push_str(rest);
+                let text = decode_entities(&content)
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                at = end;
+                if !text.is_empty() {
+                    to_return.push(Imported::Label {
+                        at: inherited.apply(P2f64::new(number("x"), number("y"))),
+                        text,
+                        size: inherited.font_size * inherited.scale(),
+                    });
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        to_return.extend(inherited.strokes(pieces));
+    }
+    to_return
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata_can_be_found_again() {
+        let svg = document(V2f64::zeros(), Some(("stuff", "abc")), std::iter::empty());
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(metadata(&svg, "stuff"), Some("abc"));
+        assert_eq!(metadata(&svg, "other"), None);
+        assert_eq!(escape("a<b & \"c\""), "a&lt;b &amp; &quot;c&quot;");
+        assert_eq!(color([1.0, 0.5, 0.0]), "#ff8000");
+    }
+
+    fn strokes(imported: &[Imported]) -> Vec<(Vec<P2f64>, Stroke)> {
+        imported
+            .iter()
+            .filter_map(|i| match i {
+                Imported::Stroke { points, stroke } => Some((points.clone(), *stroke)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn svg_shapes_become_lines() {
+        let svg = r##"<?xml version="1.0"?>
+            <!-- drawn by hand -->
+            <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+              <defs><path d="M 0 0 L 99 99"/></defs>
+              <g transform="translate(10, 20) scale(2)" stroke="#f00" fill="none">
+                <path d="M0,0 h5 v5 l-5-5 z M 1 1 c 0 1 1 1 1 0"/>
+                <rect x="1" y="1" width="2" height="3" style="stroke: blue; stroke-width: 3"/>
+              </g>
+              <polygon points="0,0 1,0 1,1"/>
+              <circle cx="5" cy="5" r="2" fill="none"/>
+            </svg>"##;
+        let found = strokes(&import(svg));
+        // nothing from the defs, or from the circle with neither a stroke nor a fill
+        assert_eq!(found.len(), 4);
+        let (square, stroke) = &found[0];
+        let corners = [
+            (10.0, 20.0),
+            (20.0, 20.0),
+            (20.0, 30.0),
+            (10.0, 20.0),
+            (10.0, 20.0),
+        ];
+        let expected: Vec<P2f64> = corners.iter().map(|&(x, y)| P2f64::new(x, y)).collect();
+        assert_eq!(square, &expected);
+        assert_eq!(stroke.color, [1.0, 0.0, 0.0]);
+        assert_eq!(stroke.width, 1.0);
+        // the curve is smooth, starting where it was moved to and ending a step right
+        let (curve, _) = &found[1];
+        assert_eq!(curve.len(), CURVE_SEGMENTS + 1);
+        assert_eq!(curve.last(), Some(&P2f64::new(14.0, 22.0)));
+        let (_, rect) = &found[2];
+        assert_eq!(rect.color, [0.0, 0.0, 1.0]);
+        assert_eq!(rect.width, 3.0);
+        // filled shapes are outlined in their fill color
+        let (triangle, fill) = &found[3];
+        assert_eq!(triangle.len(), 4);
+        assert_eq!(fill.color, [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn arcs_go_the_right_way_round() {
+        let points = path_pieces("M 0 0 A 1 1 0 0 1 2 0").remove(0);
+        assert_eq!(points.last(), Some(&P2f64::new(2.0, 0.0)));
+        // sweeping clockwise on the screen, so through the top, y being down
+        let middle = points[CURVE_SEGMENTS / 2];
+        assert!((middle - P2f64::new(1.0, -1.0)).norm() < 1e-9, "{}", middle);
+        // numbers run together the way svgs squash them
+        let squashed = path_pieces("M0-1.5.5.5L3e1,0");
+        assert_eq!(
+            squashed,
+            [vec![
+                P2f64::new(0.0, -1.5),
+                P2f64::new(0.5, 0.5),
+                P2f64::new(30.0, 0.0)
+            ]]
+        );
+    }
+
+    #[test]
+    fn svg_text_becomes_labels() {
+        let svg = r#"<svg><g font-size="10" transform="scale(2)">
+            <text x="1" y="2">Fish &amp; <tspan>chips</tspan>&#33;</text>
+        </g></svg>"#;
+        assert_eq!(
+            import(svg),
+            [Imported::Label {
+                at: P2f64::new(2.0, 4.0),
+                text: String::from("Fish & chips!"),
+                size: 20.0,
+            }]
+        );
+    }
+
+    #[test]
+    fn self_closing_text_is_empty() {
+        let svg = r#"<svg><text x="1" y="2"/><path d="M 0 0 L 5 0" stroke="red"/>
+            <text>Hi</text></svg>"#;
+        let found = import(svg);
+        assert_eq!(found.len(), 2);
+        assert!(matches!(&found[0], Imported::Stroke { .. }));
+        assert!(matches!(&found[1], Imported::Label { text, .. } if text == "Hi"));
+    }
+}
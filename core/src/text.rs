@@ -0,0 +1,198 @@
+use crate::geometry::Rect;
+use crate::shape::{Canvas, Shape};
+use crate::svg;
+use crate::util::*;
+use crate::zooming::ZoomTransform;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, Map, Value};
+
+/// The font's characters drawn as distances to their edges, for the text shader
+pub const FONT_IMAGE: &[u8] = include_bytes!("arial-font.png");
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedText {
+    pub text: String,
+    pub transform: ZoomTransform,
+    pub origin: P2,
+}
+
+impl SavedText {
+    pub const KIND: &'static str = "text";
+
+    /// The upper left and lower right of each character before the transform, and of where it
+    /// is in the font's image
+    fn glyphs(&self) -> Vec<((P2, P2), (P2, P2))> {
+        FONT.with(|font| {
+            let mut pen = self.origin;
+            self.text
+                .chars()
+                .filter_map(|c| glyph(font, &c.to_string()))
+                .map(|g| {
+                    let corners = (g.corners.0 + pen.coords, g.corners.1 + pen.coords);
+                    pen.x += g.advance;
+                    (corners, g.atlas)
+                })
+                .collect()
+        })
+    }
+}
+
+impl Shape for SavedText {
+    fn bounding_box(&self) -> Option<Rect> {
+        let corners = self
+            .glyphs()
+            .into_iter()
+            .flat_map(|((upper, lower), _)| vec![na::convert(upper), na::convert(lower)]);
+        Rect::around_points(corners, &self.transform)
+    }
+
+    fn transform(&self) -> &ZoomTransform {
+        &self.transform
+    }
+
+    fn set_transform(&mut self, z: ZoomTransform) {
+        self.transform = z;
+    }
+
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn save(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+
+    fn to_svg(&self, view: &ZoomTransform) -> String {
+        let on_page = self.transform.relative_to(view);
+        let size = font_size() as f64;
+        let baseline = self.origin + V2::new(0.0, font_size());
+        let baseline = on_page.transform_point(na::convert(baseline));
+        format!(
+            "<text x=\"{:.2}\" y=\"{:.2}\" font-family=\"Arial, sans-serif\" font-size=\"{:.2}\">{}</text>",
+            baseline.x,
+            baseline.y,
+            size * on_page.scale(),
+            svg::escape(&self.text)
+        )
+    }
+
+    fn paint(&self, canvas: &mut Canvas, view: &ZoomTransform) {
+        let on_page = self.transform.relative_to(view);
+        // as soft around the edges as the text shader makes it
+        let softness = 3.0 / (on_page.scale() * 40.0);
+        ATLAS.with(|atlas| {
+            for ((upper, lower), (atlas_upper, atlas_lower)) in self.glyphs() {
+                let upper = on_page.transform_point(na::convert(upper));
+                let lower = on_page.transform_point(na::convert(lower));
+                let (atlas_upper, atlas_lower): (P2f64, P2f64) =
+                    (na::convert(atlas_upper), na::convert(atlas_lower));
+                let (columns, rows) = canvas.pixels_between(upper, lower);
+                for y in rows {
+                    for x in columns.clone() {
+                        let middle = P2f64::new(x as f64 + 0.5, y as f64 + 0.5);
+                        let along = (middle - upper).component_div(&(lower - upper));
+                        let at = atlas_upper + (atlas_lower - atlas_upper).component_mul(&along);
+                        let distance = sample(atlas, at);
+                        let alpha = smoothstep(0.5 - softness, 0.5, distance);
+                        canvas.blend(x, y, [0.0, 0.0, 0.0], alpha as f32);
+                    }
+                }
+            }
+        });
+    }
+}
+
+thread_local! {
+    /// Where each character is in arial-font.png and how it's laid out
+    static FONT: Map<String, Value> = match from_str(include_str!("arial-font.json")) {
+        Ok(Value::Object(m)) => m,
+        _ => panic!("Unexpected json type from sdf character location map"),
+    };
+    /// The distances to the edges of the characters, for drawing text without gl
+    static ATLAS: image::RgbaImage = image::load_from_memory(FONT_IMAGE)
+        .unwrap()
+        .to_rgba8();
+}
+
+/// Where each character is in `FONT_IMAGE` and how it's laid out
+pub fn font() -> Map<String, Value> {
+    FONT.with(|font| font.clone())
+}
+
+/// How tall a line of text is before the transform
+pub fn font_size() -> f32 {
+    FONT.with(|font| font.get("size").and_then(Value::as_i64).unwrap_or(0) as f32)
+}
+
+/// How one character of the font is drawn
+pub struct Glyph {
+    /// Upper left and lower right, from where the pen is
+    pub corners: (P2, P2),
+    /// Upper left and lower right in the font's image, from 0 to 1
+    pub atlas: (P2, P2),
+    /// How far the pen moves on after it
+    pub advance: f32,
+}
+
+/// How `character` is drawn in `font`, or `None` if the font doesn't have it
+pub fn glyph(font: &Map<String, Value>, character: &str) -> Option<Glyph> {
+    let rect = font.get("characters")?.get(character)?;
+    let number = |name: &str| rect.get(name).and_then(Value::as_i64).unwrap_or(0) as f32;
+    let font_number = |name: &str| font.get(name).and_then(Value::as_i64).unwrap_or(1) as f32;
+    let atlas_size = V2::new(font_number("width"), font_number("height"));
+    let upper = P2::new(-number("originX"), font_number("size") - number("originY"));
+    let size = V2::new(number("width"), number("height"));
+    let atlas_upper = P2::new(number("x"), number("y"))
+        .coords
+        .component_div(&atlas_size);
+    Some(Glyph {
+        corners: (upper, upper + size),
+        atlas: (
+            P2::from(atlas_upper),
+            P2::from(atlas_upper + size.component_div(&atlas_size)),
+        ),
+        advance: number("advance"),
+    })
+}
+
+/// The red channel of `image` at `at`, from 0 to 1 across it, mixed between the nearest pixels
+/// like a gl texture is
+fn sample(image: &image::RgbaImage, at: P2f64) -> f64 {
+    let x = at.x * image.width() as f64 - 0.5;
+    let y = at.y * image.height() as f64 - 0.5;
+    let pixel = |x: f64, y: f64| {
+        let x = x.max(0.0).min(image.width() as f64 - 1.0) as u32;
+        let y = y.max(0.0).min(image.height() as f64 - 1.0) as u32;
+        image.get_pixel(x, y)[0] as f64 / 255.0
+    };
+    let (left, top) = (x.floor(), y.floor());
+    let (across, down) = (x - left, y - top);
+    let upper = pixel(left, top) * (1.0 - across) + pixel(left + 1.0, top) * across;
+    let lower = pixel(left, top + 1.0) * (1.0 - across) + pixel(left + 1.0, top + 1.0) * across;
+    upper * (1.0 - down) + lower * down
+}
+
+fn smoothstep(from: f64, to: f64, x: f64) -> f64 {
+    let t = ((x - from) / (to - from)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_text_is_measured_without_gl() {
+        let mut saved = SavedText {
+            text: String::from("Hi"),
+            transform: ZoomTransform::does_nothing(),
+            origin: P2::origin(),
+        };
+        let size = saved.bounding_box().unwrap().size();
+        assert!(size.x > 0.0 && size.y > 0.0 && size.y <= font_size() as f64);
+        // characters the font doesn't have take up no room
+        saved.text.push('\u{2603}');
+        saved.transform = ZoomTransform::new(2.0, V2f64::zeros());
+        assert_eq!(saved.bounding_box().unwrap().size(), size * 2.0);
+    }
+}
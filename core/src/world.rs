@@ -0,0 +1,1002 @@
+use crate::bookmarks::{Bookmark, Bookmarks};
+use crate::changes::Operation;
+use crate::fixed::FixedV2;
+use crate::object_kinds::{self, Attachments};
+use crate::recording::{Change, Recording};
+use crate::shape::Shape;
+use crate::zooming::ZoomTransform;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Names an object on the board for as long as it's there, no matter what else is added or
+/// removed
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub struct ObjectId(pub u64);
+
+/// Names a layer for as long as it's there, however the layers are reordered
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct LayerId(pub u64);
+
+/// An object in a save, stored under the name of its kind so it can be read back without the
+/// save format having to know about every kind of object
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedObject {
+    pub id: ObjectId,
+    pub kind: String,
+    #[serde(with = "crate::encoding::json")]
+    pub data: serde_json::Value,
+}
+
+/// Stored alongside the objects, so objects sharing one only store its name
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedAttachment {
+    pub name: String,
+    #[serde(with = "crate::encoding::bytes")]
+    pub bytes: Vec<u8>,
+}
+
+/// `attachments` as they're saved
+pub fn save_attachments(attachments: Attachments) -> Vec<SavedAttachment> {
+    let mut to_return: Vec<SavedAttachment> = attachments
+        .into_iter()
+        .map(|(name, bytes)| SavedAttachment {
+            name,
+            bytes: bytes.to_vec(),
+        })
+        .collect();
+    // so saving the same objects twice writes the same thing
+    to_return.sort_by(|a, b| a.name.cmp(&b.name));
+    to_return
+}
+
+pub fn load_attachments(saved: Vec<SavedAttachment>) -> Attachments {
+    saved
+        .into_iter()
+        .map(|a| (a.name, Rc::from(a.bytes)))
+        .collect()
+}
+
+/// Some objects saved on their own, like ones being copied to another board
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedObjects {
+    pub objects: Vec<SavedObject>,
+    pub attachments: Vec<SavedAttachment>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedLayer {
+    pub id: LayerId,
+    pub name: String,
+    pub visible: bool,
+    pub locked: bool,
+    pub objects: Vec<SavedObject>,
+}
+
+impl SavedLayer {
+    /// The kind of each object on the layer, in the order they're drawn
+    pub fn kinds(&self) -> impl Iterator<Item = &str> {
+        self.objects.iter().map(|o| o.kind.as_str())
+    }
+}
+
+/// A whole board as it's saved. The recording is borrowed from the board being saved, since it
+/// gets long
+#[derive(Serialize, Deserialize)]
+pub struct SavedWorld<'a> {
+    /// Where the board was looked at from when it was saved
+    pub camera: ZoomTransform,
+    /// Bottom to top
+    pub layers: Vec<SavedLayer>,
+    pub attachments: Vec<SavedAttachment>,
+    pub current_layer: LayerId,
+    pub views: Vec<Bookmark>,
+    pub recording: Cow<'a, Recording>,
+    /// What the next object drawn gets, so ids of deleted objects aren't handed out again
+    pub next_object_id: u64,
+    pub next_layer_id: u64,
+}
+
+impl SavedWorld<'_> {
+    /// A board of just `objects`, each the name of its kind and what it saved, on one layer
+    pub fn from_saved_objects(camera: ZoomTransform, objects: Vec<(String, Value)>) -> Self {
+        let next_object_id = objects.len() as u64;
+        let objects = objects
+            .into_iter()
+            .enumerate()
+            .map(|(i, (kind, data))| SavedObject {
+                id: ObjectId(i as u64),
+                kind,
+                data,
+            })
+            .collect();
+        Self {
+            camera,
+            layers: vec![SavedLayer {
+                id: LayerId(0),
+                name: String::from("Layer 1"),
+                visible: true,
+                locked: false,
+                objects,
+            }],
+            attachments: vec![],
+            current_layer: LayerId(0),
+            views: vec![],
+            recording: Cow::Owned(Recording::default()),
+            next_object_id,
+            next_layer_id: 1,
+        }
+    }
+    /// What everything on a visible layer looks like, in the order it's drawn, read without gl
+    pub fn shapes(&self) -> Result<Vec<Box<dyn Shape>>, String> {
+        let attachments = load_attachments(self.attachments.clone());
+        self.layers
+            .iter()
+            .filter(|l| l.visible)
+            .flat_map(|l| l.objects.iter())
+            .map(|o| object_kinds::shape(&o.kind, o.data.clone(), &attachments))
+            .collect()
+    }
+    /// Everything that would stop the board loading, found without gl
+    pub fn problems(&self) -> Vec<String> {
+        let mut to_return = vec![];
+        if !self.layers.iter().any(|l| l.id == self.current_layer) {
+            to_return.push(format!("no layer {:?} to draw on", self.current_layer));
+        }
+        let attachments = load_attachments(self.attachments.clone());
+        let mut layer_ids = HashSet::new();
+        let mut object_ids = HashSet::new();
+        for layer in self.layers.iter() {
+            if !layer_ids.insert(layer.id) {
+                to_return.push(format!("more than one layer is {:?}", layer.id));
+            }
+            for object in layer.objects.iter() {
+                if !object_ids.insert(object.id) {
+                    to_return.push(format!("more than one object is {:?}", object.id));
+                }
+                let shape = object_kinds::shape(&object.kind, object.data.clone(), &attachments);
+                if let Err(e) = shape {
+                    to_return.push(format!("{:?} on {} - {}", object.id, layer.name, e));
+                }
+            }
+        }
+        to_return
+    }
+    /// Puts everything on `other` on top of this board, on layers of its own. Its layers and
+    /// objects get new ids so they don't clash with the ones already here, and how it was drawn
+    /// plays after how this one was. Returns what was left out, if anything
+    pub fn merge(&mut self, other: SavedWorld) -> Option<String> {
+        // a board with things on it that were never recorded can't be replayed along with one
+        // that was
+        let recorded = |board: &SavedWorld| {
+            !board.recording.moments.is_empty() || board.layers.iter().all(|l| l.objects.is_empty())
+        };
+        let keep_recordings = recorded(self) && recorded(&other);
+        let own_layers: Vec<LayerId> = self.layers.iter().map(|l| l.id).collect();
+        let (mut layer_ids, mut object_ids) = (HashMap::new(), HashMap::new());
+        let (mut next_layer_id, mut next_object_id) = (self.next_layer_id, self.next_object_id);
+        let mut renumber_layer = |id: &mut LayerId| {
+            *id = *layer_ids.entry(*id).or_insert_with(|| {
+                next_layer_id += 1;
+                LayerId(next_layer_id - 1)
+            });
+        };
+        let mut renumber_object = |id: &mut ObjectId| {
+            *id = *object_ids.entry(*id).or_insert_with(|| {
+                next_object_id += 1;
+                ObjectId(next_object_id - 1)
+            });
+        };
+        for mut layer in other.layers {
+            renumber_layer(&mut layer.id);
+            for object in layer.objects.iter_mut() {
+                renumber_object(&mut object.id);
+            }
+            self.layers.push(layer);
+        }
+        let mut recording = other.recording.into_owned();
+        // the same ids as on the board, and new ones for anything that was removed before it
+        // was saved
+        for moment in recording.moments.iter_mut() {
+            if let Change::Board(operation) = &mut moment.change {
+                let (layers, objects) = operation.ids_mut();
+                layers.into_iter().for_each(&mut renumber_layer);
+                objects.into_iter().for_each(&mut renumber_object);
+                // its layers go on top of this board's, like they do on the merged board
+                if let Operation::OrderLayers { ids } = operation {
+                    ids.splice(0..0, own_layers.iter().cloned());
+                }
+            }
+        }
+        self.next_layer_id = next_layer_id;
+        self.next_object_id = next_object_id;
+        let left_out = if keep_recordings {
+            let start = self.recording.duration();
+            let own = self.recording.to_mut();
+            own.moments
+                .extend(recording.moments.into_iter().map(|mut m| {
+                    m.at += start;
+                    m
+                }));
+            own.narration
+                .extend(recording.narration.into_iter().map(|mut c| {
+                    c.at += start;
+                    c
+                }));
+            None
+        } else if !self.recording.moments.is_empty() || !recording.moments.is_empty() {
+            self.recording = Cow::Owned(Recording::default());
+            Some(String::from(
+                "left out how they were drawn, since one of them was never recorded",
+            ))
+        } else {
+            None
+        };
+        // attachments are named for what's in them, so ones with the same name are the same
+        for attachment in other.attachments {
+            if !self.attachments.iter().any(|a| a.name == attachment.name) {
+                self.attachments.push(attachment);
+            }
+        }
+        self.attachments.sort_by(|a, b| a.name.cmp(&b.name));
+        self.views.extend(other.views);
+        left_out
+    }
+    /// The kind and saved data of everything on a visible layer, in the order it's drawn
+    pub fn visible_objects(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.layers
+            .iter()
+            .filter(|l| l.visible)
+            .flat_map(|l| l.objects.iter())
+            .map(|o| (o.kind.as_str(), &o.data))
+    }
+}
+
+/// Counts up every time an object is put on any board, so no two objects ever share a revision
+static NEXT_REVISION: AtomicU64 = AtomicU64::new(0);
+
+/// An object on a layer
+struct Placed {
+    id: ObjectId,
+    /// Changes whenever the object is replaced, so whatever draws it knows to make it again.
+    /// Moving it keeps the same revision
+    revision: u64,
+    shape: Box<dyn Shape>,
+}
+
+/// A revision no object has had before
+pub fn next_revision() -> u64 {
+    NEXT_REVISION.fetch_add(1, Ordering::Relaxed)
+}
+
+impl Placed {
+    fn new(id: ObjectId, shape: Box<dyn Shape>) -> Self {
+        Self {
+            id,
+            revision: next_revision(),
+            shape,
+        }
+    }
+}
+
+/// A named group of objects that are hidden or locked together
+pub struct Layer {
+    pub id: LayerId,
+    pub name: String,
+    pub visible: bool,
+    /// Objects on locked layers can't be picked, so they can't be selected, moved or erased
+    pub locked: bool,
+    /// In the order they're drawn, the last one on top
+    objects: Vec<Placed>,
+}
+
+impl Layer {
+    fn new(id: LayerId, name: String) -> Self {
+        Self {
+            id,
+            name,
+            visible: true,
+            locked: false,
+            objects: vec![],
+        }
+    }
+
+    /// If what's on the layer can be picked with the mouse
+    fn pickable(&self) -> bool {
+        self.visible && !self.locked
+    }
+
+    /// Everything on the layer, in the order it's drawn
+    pub fn objects(&self) -> impl DoubleEndedIterator<Item = (ObjectId, &dyn Shape)> {
+        self.objects.iter().map(|o| (o.id, o.shape.as_ref()))
+    }
+}
+
+/// How many of the low bits of an id count up, the bits above them saying who made it. Boards
+/// shared between several people each make ids with their own high bits, so they never clash
+pub const ID_COUNTER_BITS: u32 = 48;
+
+/// The first id not in `used` that has `site` in its high bits
+fn next_id_for(site: u16, used: impl Iterator<Item = u64>) -> u64 {
+    let first = (site as u64) << ID_COUNTER_BITS;
+    used.filter(|id| id >> ID_COUNTER_BITS == site as u64)
+        .map(|id| id + 1)
+        .max()
+        .unwrap_or(first)
+}
+
+/// The board being drawn on
+pub struct World {
+    /// Maps the screen into the world
+    pub camera: ZoomTransform,
+    /// Drawn in order, the last one on top
+    layers: Vec<Layer>,
+    /// Where new objects go
+    current_layer: LayerId,
+    next_object_id: u64,
+    next_layer_id: u64,
+    /// What is currently selected
+    pub selection: Vec<ObjectId>,
+    pub bookmarks: Bookmarks,
+    /// How the board was drawn
+    pub recording: Recording,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl World {
+    pub fn new() -> World {
+        World {
+            camera: ZoomTransform::does_nothing(),
+            layers: vec![Layer::new(LayerId(0), String::from("Layer 1"))],
+            current_layer: LayerId(0),
+            next_object_id: 0,
+            next_layer_id: 1,
+            selection: vec![],
+            bookmarks: Bookmarks::new(vec![]),
+            recording: Recording::default(),
+        }
+    }
+    /// Puts `object` on top of everything else on the current layer
+    pub fn add(&mut self, object: Box<dyn Shape>) -> ObjectId {
+        self.add_to(self.current_layer, object)
+    }
+    /// Puts `object` on top of everything else on `layer`, or the current layer if there's no
+    /// such layer
+    pub fn add_to(&mut self, layer: LayerId, object: Box<dyn Shape>) -> ObjectId {
+        let id = ObjectId(self.next_object_id);
+        self.next_object_id += 1;
+        self.put(id, layer, object);
+        id
+    }
+    /// Everything on the whiteboard, in the order it's drawn
+    pub fn objects(&self) -> impl DoubleEndedIterator<Item = (ObjectId, &dyn Shape)> {
+        self.layers.iter().flat_map(|l| l.objects())
+    }
+    /// Bottom to top
+    pub fn layers(&self) -> impl Iterator<Item = &Layer> {
+        self.layers.iter()
+    }
+    /// Everything on layers that aren't hidden, in the order it's drawn
+    pub fn visible_objects(&self) -> impl DoubleEndedIterator<Item = (ObjectId, &dyn Shape)> {
+        self.layers
+            .iter()
+            .filter(|l| l.visible)
+            .flat_map(|l| l.objects())
+    }
+    /// Like `visible_objects`, along with the revision of each object, which changes whenever
+    /// it's replaced by something else with the same id
+    pub fn visible_revisions(&self) -> impl Iterator<Item = (ObjectId, u64, &dyn Shape)> {
+        self.layers
+            .iter()
+            .filter(|l| l.visible)
+            .flat_map(|l| l.objects.iter())
+            .map(|o| (o.id, o.revision, o.shape.as_ref()))
+    }
+    /// Everything that can be picked with the mouse, in the order it's drawn
+    pub fn pickable_objects(&self) -> impl Iterator<Item = (ObjectId, &dyn Shape)> {
+        self.layers
+            .iter()
+            .filter(|l| l.pickable())
+            .flat_map(|l| l.objects())
+    }
+    pub fn object_count(&self) -> usize {
+        self.layers.iter().map(|l| l.objects.len()).sum()
+    }
+    pub fn object(&self, id: ObjectId) -> Option<&dyn Shape> {
+        self.objects().find(|(i, _)| *i == id).map(|(_, o)| o)
+    }
+    /// Which layer `id` is on
+    pub fn layer_of(&self, id: ObjectId) -> Option<LayerId> {
+        let mut layers = self.layers.iter();
+        layers
+            .find(|l| l.objects.iter().any(|o| o.id == id))
+            .map(|l| l.id)
+    }
+    pub fn object_mut(&mut self, id: ObjectId) -> Option<&mut dyn Shape> {
+        let placed = self
+            .layers
+            .iter_mut()
+            .flat_map(|l| l.objects.iter_mut())
+            .find(|o| o.id == id)?;
+        Some(placed.shape.as_mut())
+    }
+    /// The selected objects that are still on the board
+    pub fn selected(&self) -> impl Iterator<Item = &dyn Shape> + Clone {
+        self.selection.iter().filter_map(move |&id| self.object(id))
+    }
+    /// The topmost object less than `tolerance` world units from `point`, skipping hidden and
+    /// locked layers
+    pub fn object_at(&self, point: FixedV2, tolerance: f64) -> Option<ObjectId> {
+        self.layers
+            .iter()
+            .rev()
+            .filter(|l| l.pickable())
+            .flat_map(|l| l.objects.iter().rev())
+            .find(|o| o.shape.hit(point, tolerance))
+            .map(|o| o.id)
+    }
+    /// Takes the object off the board and out of the selection
+    pub fn remove(&mut self, id: ObjectId) -> Option<Box<dyn Shape>> {
+        self.selection.retain(|&i| i != id);
+        for layer in self.layers.iter_mut() {
+            if let Some(index) = layer.objects.iter().position(|o| o.id == id) {
+                return Some(layer.objects.remove(index).shape);
+            }
+        }
+        None
+    }
+    /// Moves `ids` above everything else on their layers, keeping their order among themselves
+    pub fn bring_to_front(&mut self, ids: &[ObjectId]) {
+        for layer in self.layers.iter_mut() {
+            let (mut moved, kept): (Vec<_>, Vec<_>) =
+                layer.objects.drain(..).partition(|o| ids.contains(&o.id));
+            layer.objects = kept;
+            layer.objects.append(&mut moved);
+        }
+    }
+    /// Moves `ids` below everything else on their layers, keeping their order among themselves
+    pub fn send_to_back(&mut self, ids: &[ObjectId]) {
+        for layer in self.layers.iter_mut() {
+            let (moved, mut kept): (Vec<_>, Vec<_>) =
+                layer.objects.drain(..).partition(|o| ids.contains(&o.id));
+            layer.objects = moved;
+            layer.objects.append(&mut kept);
+        }
+    }
+
+    fn current_layer_index(&self) -> usize {
+        self.layers
+            .iter()
+            .position(|l| l.id == self.current_layer)
+            .unwrap()
+    }
+    pub fn current_layer(&self) -> &Layer {
+        &self.layers[self.current_layer_index()]
+    }
+    pub fn current_layer_mut(&mut self) -> &mut Layer {
+        let index = self.current_layer_index();
+        &mut self.layers[index]
+    }
+    /// Why new things shouldn't go on the current layer, if it's hidden or locked, since they
+    /// couldn't be seen or picked there
+    pub fn cant_add_reason(&self) -> Option<String> {
+        let layer = self.current_layer();
+        match (layer.visible, layer.locked) {
+            (true, false) => None,
+            (false, _) => Some(format!("{} is hidden, show it to draw on it", layer.name)),
+            (true, true) => Some(format!("{} is locked, unlock it to draw on it", layer.name)),
+        }
+    }
+    /// Makes a new layer just above the current one, and draws on it from then on
+    pub fn add_layer(&mut self) -> LayerId {
+        let id = LayerId(self.next_layer_id);
+        self.next_layer_id += 1;
+        let name = format!("Layer {}", self.layers.len() + 1);
+        let index = self.current_layer_index() + 1;
+        self.layers.insert(index, Layer::new(id, name));
+        self.current_layer = id;
+        id
+    }
+    /// Removes the current layer if there's nothing on it and it isn't the last one left.
+    /// Returns if it was removed
+    pub fn remove_current_layer(&mut self) -> bool {
+        let index = self.current_layer_index();
+        if self.layers.len() == 1 || !self.layers[index].objects.is_empty() {
+            return false;
+        }
+        self.layers.remove(index);
+        self.current_layer = self.layers[index.saturating_sub(1)].id;
+        true
+    }
+    /// Removes the layer `id` and everything on it, like when someone else sharing the board
+    /// removed it. If it was the last one an empty layer takes its place, with the same id
+    /// wherever that happens so everyone's boards still match
+    pub fn remove_layer(&mut self, id: LayerId) {
+        let index = match self.layers.iter().position(|l| l.id == id) {
+            Some(index) => index,
+            None => return,
+        };
+        let removed = self.layers.remove(index);
+        let gone: Vec<ObjectId> = removed.objects.iter().map(|o| o.id).collect();
+        self.selection.retain(|id| !gone.contains(id));
+        if self.layers.is_empty() {
+            self.layers
+                .push(Layer::new(LayerId(0), String::from("Layer 1")));
+        }
+        if !self.layers.iter().any(|l| l.id == self.current_layer) {
+            self.current_layer = self.layers[index.saturating_sub(1)].id;
+        }
+    }
+    /// Puts the layers in the order of `ids`, bottom to top. Any that aren't in `ids` go on top,
+    /// keeping their order among themselves
+    pub fn order_layers(&mut self, ids: &[LayerId]) {
+        self.layers
+            .sort_by_key(|l| ids.iter().position(|&id| id == l.id).unwrap_or(ids.len()));
+    }
+    /// Puts the objects on `layer` in the order of `ids`, bottom to top. Any that aren't in `ids`
+    /// go on top, keeping their order among themselves
+    pub fn order_objects(&mut self, layer: LayerId, ids: &[ObjectId]) {
+        if let Some(layer) = self.layers.iter_mut().find(|l| l.id == layer) {
+            layer
+                .objects
+                .sort_by_key(|o| ids.iter().position(|&id| id == o.id).unwrap_or(ids.len()));
+        }
+    }
+    /// Draws on the layer `by` above the current one from then on, stopping at the top or bottom
+    pub fn step_layer(&mut self, by: isize) {
+        let index = self.current_layer_index() as isize + by;
+        let index = index.clamp(0, self.layers.len() as isize - 1) as usize;
+        self.current_layer = self.layers[index].id;
+    }
+    /// Moves the current layer `by` places up, stopping at the top or bottom
+    pub fn move_current_layer(&mut self, by: isize) {
+        let from = self.current_layer_index();
+        let to = (from as isize + by).clamp(0, self.layers.len() as isize - 1) as usize;
+        let layer = self.layers.remove(from);
+        self.layers.insert(to, layer);
+    }
+    /// Puts `ids` on top of the current layer
+    pub fn move_to_current_layer(&mut self, ids: &[ObjectId]) {
+        let mut moved = vec![];
+        for layer in self.layers.iter_mut() {
+            let (mut taken, kept): (Vec<_>, Vec<_>) =
+                layer.objects.drain(..).partition(|o| ids.contains(&o.id));
+            layer.objects = kept;
+            moved.append(&mut taken);
+        }
+        self.current_layer_mut().objects.append(&mut moved);
+    }
+    /// Puts `object` on the board as `id` on top of `layer`, or the current layer if there's no
+    /// such layer. An object already there with that id is replaced, staying where it is in the
+    /// drawing order if it's on the same layer
+    pub fn put(&mut self, id: ObjectId, layer: LayerId, object: Box<dyn Shape>) {
+        let layer = if self.layers.iter().any(|l| l.id == layer) {
+            layer
+        } else {
+            self.current_layer
+        };
+        for l in self.layers.iter_mut() {
+            if let Some(index) = l.objects.iter().position(|o| o.id == id) {
+                if l.id == layer {
+                    l.objects[index] = Placed::new(id, object);
+                    return;
+                }
+                l.objects.remove(index);
+                break;
+            }
+        }
+        let index = self.layers.iter().position(|l| l.id == layer).unwrap();
+        self.layers[index].objects.push(Placed::new(id, object));
+    }
+    /// Names the layer `id`, making it on top of the others if there's no such layer yet
+    pub fn put_layer(&mut self, id: LayerId, name: String) {
+        match self.layers.iter_mut().find(|l| l.id == id) {
+            Some(layer) => layer.name = name,
+            None => self.layers.push(Layer::new(id, name)),
+        }
+    }
+    /// Makes ids from now on with `site` in their high bits, so they can't clash with ones made
+    /// by anyone else sharing the board
+    pub fn claim_ids(&mut self, site: u16) {
+        // ids already handed out for the same site stay used, even for things since deleted that
+        // the recording still names
+        let keep = |next: u64, used: u64| {
+            if next >> ID_COUNTER_BITS == site as u64 {
+                next.max(used)
+            } else {
+                used
+            }
+        };
+        let objects = self.objects().map(|(ObjectId(id), _)| id);
+        self.next_object_id = keep(self.next_object_id, next_id_for(site, objects));
+        let layers = self.layers.iter().map(|l| l.id.0);
+        self.next_layer_id = keep(self.next_layer_id, next_id_for(site, layers));
+    }
+    /// Drops anything on hidden or locked layers from the selection, so it can't be changed
+    pub fn deselect_unpickable(&mut self) {
+        let layers = &self.layers;
+        self.selection.retain(|id| {
+            layers
+                .iter()
+                .any(|l| l.pickable() && l.objects.iter().any(|o| o.id == *id))
+        });
+    }
+}
+
+fn saved_object(id: ObjectId, object: &dyn Shape) -> SavedObject {
+    SavedObject {
+        id,
+        kind: String::from(object.kind()),
+        data: object.save(),
+    }
+}
+
+/// Everything `objects` refer to, each only once
+fn attachments_of<'a, I>(objects: I) -> Vec<SavedAttachment>
+where
+    I: Iterator<Item = &'a dyn Shape>,
+{
+    let mut attachments: Attachments = HashMap::new();
+    for object in objects {
+        attachments.extend(object.attachments());
+    }
+    save_attachments(attachments)
+}
+
+/// `objects` saved on their own, like ones being copied to another board
+pub fn save_objects<'a, I>(objects: I) -> SavedObjects
+where
+    I: Iterator<Item = &'a dyn Shape> + Clone,
+{
+    SavedObjects {
+        attachments: attachments_of(objects.clone()),
+        // ids only mean something on the board they came from
+        objects: objects.map(|o| saved_object(ObjectId(0), o)).collect(),
+    }
+}
+
+pub fn load_objects(saved: SavedObjects) -> Result<Vec<Box<dyn Shape>>, String> {
+    load_objects_using(saved, &Attachments::new())
+}
+
+/// Like `load_objects`, finding any attachments `saved` only names in `attachments`
+pub fn load_objects_using(
+    saved: SavedObjects,
+    attachments: &Attachments,
+) -> Result<Vec<Box<dyn Shape>>, String> {
+    let with_sent;
+    let attachments = if saved.attachments.is_empty() {
+        attachments
+    } else {
+        let mut all = attachments.clone();
+        all.extend(load_attachments(saved.attachments));
+        with_sent = all;
+        &with_sent
+    };
+    saved
+        .objects
+        .into_iter()
+        .map(|o| object_kinds::shape(&o.kind, o.data, attachments))
+        .collect()
+}
+
+impl World {
+    pub fn to_saved(&self) -> SavedWorld<'_> {
+        let layers = self
+            .layers
+            .iter()
+            .map(|l| SavedLayer {
+                id: l.id,
+                name: l.name.clone(),
+                visible: l.visible,
+                locked: l.locked,
+                objects: l.objects().map(|(id, o)| saved_object(id, o)).collect(),
+            })
+            .collect();
+        // the recording can still show what's been removed from the board since
+        let mut attachments = self.recording.attachments.clone();
+        for (_, object) in self.objects() {
+            attachments.extend(object.attachments());
+        }
+        SavedWorld {
+            layers,
+            attachments: save_attachments(attachments),
+            current_layer: self.current_layer,
+            camera: self.camera.clone(),
+            views: self.bookmarks.views.clone(),
+            recording: Cow::Borrowed(&self.recording),
+            next_object_id: self.next_object_id,
+            next_layer_id: self.next_layer_id,
+        }
+    }
+    /// The saved world, or what couldn't be read from the save
+    pub fn from_saved(saved: SavedWorld) -> Result<World, String> {
+        let attachments = load_attachments(saved.attachments);
+        let mut layers = Vec::with_capacity(saved.layers.len());
+        for saved_layer in saved.layers {
+            let mut layer = Layer::new(saved_layer.id, saved_layer.name);
+            layer.visible = saved_layer.visible;
+            layer.locked = saved_layer.locked;
+            for saved in saved_layer.objects {
+                let object = object_kinds::shape(&saved.kind, saved.data, &attachments)?;
+                layer.objects.push(Placed::new(saved.id, object));
+            }
+            layers.push(layer);
+        }
+        let current_layer = saved.current_layer;
+        if !layers.iter().any(|l| l.id == current_layer) {
+            return Err(format!("no layer {:?} to draw on", current_layer));
+        }
+        // never behind what's on the board, even if the save was edited by hand
+        let next_object_id = layers
+            .iter()
+            .flat_map(|l| l.objects.iter())
+            .map(|o| o.id.0 + 1)
+            .fold(saved.next_object_id, u64::max);
+        let next_layer_id = layers
+            .iter()
+            .map(|l| l.id.0 + 1)
+            .fold(saved.next_layer_id, u64::max);
+        let mut recording = saved.recording.into_owned();
+        recording.attachments = attachments;
+        Ok(World {
+            layers,
+            current_layer,
+            next_object_id,
+            next_layer_id,
+            camera: saved.camera,
+            selection: vec![],
+            bookmarks: Bookmarks::new(saved.views),
+            recording,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Rect;
+    use crate::line::{SavedLine, Stroke};
+    use crate::shape::Canvas;
+
+    /// Only ever at one point, so it's easy to tell what's picked
+    struct Dot(ZoomTransform);
+
+    impl Shape for Dot {
+        fn bounding_box(&self) -> Option<Rect> {
+            None
+        }
+        fn hit(&self, point: FixedV2, _tolerance: f64) -> bool {
+            point == self.0.transform_point_exact(na::Point2::origin())
+        }
+        fn transform(&self) -> &ZoomTransform {
+            &self.0
+        }
+        fn set_transform(&mut self, z: ZoomTransform) {
+            self.0 = z;
+        }
+        fn kind(&self) -> &'static str {
+            "dot"
+        }
+        fn save(&self) -> Value {
+            Value::Null
+        }
+        fn to_svg(&self, _: &ZoomTransform) -> String {
+            String::new()
+        }
+        fn paint(&self, _: &mut Canvas, _: &ZoomTransform) {}
+    }
+
+    fn dot_at(x: f64) -> Box<dyn Shape> {
+        Box::new(Dot(ZoomTransform::new(1.0, na::Vector2::new(x, 0.0))))
+    }
+
+    #[test]
+    fn ids_outlive_removing_other_objects() {
+        let mut world = World::new();
+        let first = world.add(dot_at(0.0));
+        let second = world.add(dot_at(1.0));
+        let third = world.add(dot_at(1.0));
+        world.selection = vec![first, third];
+
+        // the one on top is found first
+        let point = FixedV2::from_f64(na::Vector2::new(1.0, 0.0));
+        assert_eq!(world.object_at(point, 0.0), Some(third));
+
+        assert!(world.remove(first).is_some());
+        assert!(world.remove(first).is_none());
+        assert_eq!(world.selection, vec![third]);
+        assert_eq!(world.object_at(point, 0.0), Some(third));
+        world.remove(third);
+        assert_eq!(world.object_at(point, 0.0), Some(second));
+
+        // ids of removed objects aren't handed out again
+        let fourth = world.add(dot_at(2.0));
+        assert!(fourth != first && fourth != third);
+        let order: Vec<ObjectId> = world.objects().map(|(id, _)| id).collect();
+        assert_eq!(order, vec![second, fourth]);
+    }
+
+    #[test]
+    fn reordering_stays_within_layers() {
+        let mut world = World::new();
+        let a = world.add(dot_at(0.0));
+        let b = world.add(dot_at(0.0));
+        world.add_layer();
+        let c = world.add(dot_at(0.0));
+        let order =
+            |world: &World| -> Vec<ObjectId> { world.objects().map(|(id, _)| id).collect() };
+
+        world.send_to_back(&[c, b]);
+        assert_eq!(order(&world), vec![b, a, c]);
+        world.bring_to_front(&[b]);
+        assert_eq!(order(&world), vec![a, b, c]);
+
+        // moving the top layer down puts its objects under the others
+        world.move_current_layer(-1);
+        assert_eq!(order(&world), vec![c, a, b]);
+        world.move_to_current_layer(&[a]);
+        assert_eq!(order(&world), vec![c, a, b]);
+        assert_eq!(world.layers[0].objects.len(), 2);
+    }
+
+    #[test]
+    fn hidden_and_locked_layers_cant_be_picked() {
+        let mut world = World::new();
+        let below = world.add(dot_at(0.0));
+        world.add_layer();
+        let above = world.add(dot_at(0.0));
+        let point = FixedV2::from_f64(na::Vector2::zeros());
+        assert_eq!(world.object_at(point, 0.0), Some(above));
+
+        world.selection = vec![below, above];
+        world.current_layer_mut().locked = true;
+        world.deselect_unpickable();
+        assert_eq!(world.selection, vec![below]);
+        assert_eq!(world.object_at(point, 0.0), Some(below));
+
+        world.step_layer(-1);
+        world.current_layer_mut().visible = false;
+        assert_eq!(world.object_at(point, 0.0), None);
+        assert_eq!(world.visible_objects().count(), 1);
+
+        // only empty layers can be removed
+        assert!(!world.remove_current_layer());
+        world.remove(below);
+        assert!(world.remove_current_layer());
+        assert_eq!(world.current_layer().name, "Layer 2");
+        assert!(!world.remove_current_layer());
+    }
+
+    #[test]
+    fn hidden_and_locked_layers_cant_be_added_to() {
+        let mut world = World::new();
+        assert_eq!(world.cant_add_reason(), None);
+        world.current_layer_mut().visible = false;
+        assert!(world.cant_add_reason().unwrap().contains("hidden"));
+        world.current_layer_mut().visible = true;
+        world.current_layer_mut().locked = true;
+        assert!(world.cant_add_reason().unwrap().contains("locked"));
+    }
+
+    #[test]
+    fn layers_are_saved() {
+        let mut world = World::new();
+        world.add_layer();
+        world.current_layer_mut().name = String::from("Notes");
+        world.current_layer_mut().locked = true;
+        world.move_current_layer(-1);
+        let world = World::from_saved(world.to_saved()).unwrap();
+        let layers: Vec<(&str, bool)> = world
+            .layers
+            .iter()
+            .map(|l| (l.name.as_str(), l.locked))
+            .collect();
+        assert_eq!(layers, vec![("Notes", true), ("Layer 1", false)]);
+        assert_eq!(world.current_layer().name, "Notes");
+    }
+
+    #[test]
+    fn objects_from_others_keep_their_ids() {
+        let mut world = World::new();
+        let mine = world.add(dot_at(0.0));
+        world.claim_ids(3);
+        let later = world.add(dot_at(0.0));
+        assert_eq!(later.0 >> ID_COUNTER_BITS, 3);
+
+        // put on the current layer until the layer it's meant for shows up
+        let theirs = ObjectId(1 << ID_COUNTER_BITS);
+        world.put(theirs, LayerId(7), dot_at(1.0));
+        let revision = |world: &World, id: ObjectId| {
+            world
+                .visible_revisions()
+                .find(|&(i, _, _)| i == id)
+                .map(|(_, revision, _)| revision)
+        };
+        let before = revision(&world, mine);
+        world.put(mine, LayerId(0), dot_at(2.0));
+        assert!(revision(&world, mine) != before);
+        let order: Vec<ObjectId> = world.objects().map(|(id, _)| id).collect();
+        assert_eq!(order, vec![mine, later, theirs]);
+        let point = FixedV2::from_f64(na::Vector2::new(2.0, 0.0));
+        assert_eq!(world.object_at(point, 0.0), Some(mine));
+
+        world.put_layer(LayerId(7), String::from("Theirs"));
+        world.put(theirs, LayerId(7), dot_at(1.0));
+        assert_eq!(world.layers[1].name, "Theirs");
+        assert_eq!(
+            world.layers[1].objects().map(|(id, _)| id).next(),
+            Some(theirs)
+        );
+
+        world.claim_ids(0);
+        assert_eq!(world.add(dot_at(0.0)), ObjectId(1));
+    }
+
+    #[test]
+    fn unknown_kinds_are_reported() {
+        let mut world = World::new();
+        world.add(dot_at(0.0));
+        let error = World::from_saved(world.to_saved()).err().unwrap();
+        assert!(error.contains("dot"), "{}", error);
+    }
+
+    fn saved_line(x: f32) -> (String, Value) {
+        let saved = SavedLine {
+            points: vec![na::Point2::new(x, 0.0), na::Point2::new(x, 1.0)],
+            transform: ZoomTransform::does_nothing(),
+            stroke: Stroke {
+                color: [0.0, 0.0, 0.0],
+                width: 1.0,
+            },
+        };
+        (
+            String::from(SavedLine::KIND),
+            serde_json::to_value(saved).unwrap(),
+        )
+    }
+
+    #[test]
+    fn ids_of_removed_objects_stay_used_after_reloading() {
+        let camera = ZoomTransform::does_nothing();
+        let saved = SavedWorld::from_saved_objects(camera, vec![saved_line(0.0)]);
+        let mut world = World::from_saved(saved).unwrap();
+        let (kind, data) = saved_line(1.0);
+        let removed = world.add(object_kinds::shape(&kind, data, &Attachments::new()).unwrap());
+        world.remove(removed);
+
+        let mut world = World::from_saved(world.to_saved()).unwrap();
+        let (kind, data) = saved_line(2.0);
+        let added = world.add(object_kinds::shape(&kind, data, &Attachments::new()).unwrap());
+        assert!(added != removed);
+    }
+
+    #[test]
+    fn merged_boards_keep_their_ids_apart() {
+        let camera = ZoomTransform::does_nothing();
+        let mut first =
+            SavedWorld::from_saved_objects(camera.clone(), vec![saved_line(0.0), saved_line(1.0)]);
+        first.merge(SavedWorld::from_saved_objects(
+            camera.clone(),
+            vec![saved_line(2.0)],
+        ));
+        assert_eq!(first.layers.len(), 2);
+        assert!(first.problems().is_empty(), "{:?}", first.problems());
+        assert_eq!(first.shapes().unwrap().len(), 3);
+
+        let unknown = (String::from("blob"), Value::Null);
+        let mut broken = SavedWorld::from_saved_objects(camera, vec![saved_line(0.0), unknown]);
+        broken.layers[0].objects[1].id = ObjectId(0);
+        let problems = broken.problems();
+        assert_eq!(problems.len(), 2, "{:?}", problems);
+        assert!(problems[1].contains("blob"), "{}", problems[1]);
+    }
+}
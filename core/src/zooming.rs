@@ -0,0 +1,294 @@
+use crate::fixed::*;
+use crate::geometry::Rect;
+use crate::util::*;
+use serde::{Deserialize, Serialize};
+
+/// How far the camera is allowed to zoom in and out, in world units per pixel. Bounded by the
+/// fractional and integer bits of `Fixed` respectively
+const MIN_SCALE: f64 = 1e-30;
+const MAX_SCALE: f64 = 1e12;
+
+/// A change to the camera that happened over one frame, in screen space
+pub struct Movement {
+    pub wrt_point: P2f64,
+    pub zoom: f64,
+    pub pan: V2f64,
+}
+
+impl Default for Movement {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Movement {
+    pub fn new() -> Self {
+        Self {
+            wrt_point: P2f64::new(0.0, 0.0),
+            zoom: 1.0,
+            pan: V2f64::new(0.0, 0.0),
+        }
+    }
+    /// Zooms the camera by `zoom` keeping the world under the screen point `wrt_point` where it
+    /// is, then pans the screen by `pan`. `camera` maps screen space into world space.
+    pub fn apply_to_transform(&self, camera: &mut ZoomTransform) {
+        // only the (small) change is computed in floating point, which is then added onto the
+        // exact fixed point offset, so nothing about where the camera already is gets rounded
+        let zoom = self
+            .zoom
+            .max(camera.scale / MAX_SCALE)
+            .min(camera.scale / MIN_SCALE);
+        let zoom_shift = self.wrt_point.coords * (camera.scale * (1.0 - 1.0 / zoom));
+        camera.offset += FixedV2::from_f64(zoom_shift);
+        camera.scale /= zoom;
+        camera.offset += FixedV2::from_f64(self.pan * camera.scale);
+    }
+}
+
+/// Maps points from one space into a bigger one like `point*scale + offset`, e.g. from an object's
+/// own coordinates into world coordinates, or from the screen into the world for the camera.
+///
+/// The offset is always in world units and stored as fixed point, so an object drawn 30 orders of
+/// magnitude deep stays exactly where it was drawn. Transforms are only ever turned into floating
+/// point after being re-based around the camera with `relative_to`.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ZoomTransform {
+    scale: f64,
+    offset: FixedV2,
+}
+
+impl ZoomTransform {
+    pub fn new(scale: f64, offset: V2f64) -> Self {
+        Self {
+            scale,
+            offset: FixedV2::from_f64(offset),
+        }
+    }
+    pub fn does_nothing() -> Self {
+        Self {
+            scale: 1.0,
+            offset: FixedV2::zero(),
+        }
+    }
+    /// The camera that shows all of `rect` in the middle of a screen of size `screen_size`, with
+    /// `margin` times as much space as needed so it doesn't touch the edges. `None` if `rect`
+    /// has no area to zoom in on
+    pub fn fitting(rect: &Rect, screen_size: V2f64, margin: f64) -> Option<Self> {
+        let size = rect.size();
+        let scale = (size.x / screen_size.x).max(size.y / screen_size.y) * margin;
+        if scale.is_nan() || scale <= 0.0 {
+            return None;
+        }
+        let scale = scale.clamp(MIN_SCALE, MAX_SCALE);
+        Some(Self {
+            scale,
+            offset: rect.center() - FixedV2::from_f64(screen_size * (scale / 2.0)),
+        })
+    }
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+    pub fn transform_point(&self, other: P2f64) -> P2f64 {
+        P2f64::from(self.transform_point_exact(other).to_f64())
+    }
+    /// Like `transform_point`, without rounding the result back into floating point
+    pub fn transform_point_exact(&self, other: P2f64) -> FixedV2 {
+        self.offset + FixedV2::from_f64(other.coords * self.scale)
+    }
+    pub fn inverse_transform_point(&self, other: P2f64) -> P2f64 {
+        self.inverse_transform_exact(FixedV2::from_f64(other.coords))
+    }
+    /// Like `inverse_transform_point`, for a point that was never rounded into floating point
+    pub fn inverse_transform_exact(&self, other: FixedV2) -> P2f64 {
+        P2f64::from((other - self.offset).to_f64() / self.scale)
+    }
+    /// The same transform, moved `by` in world units
+    pub fn translated(&self, by: FixedV2) -> ZoomTransform {
+        ZoomTransform {
+            scale: self.scale,
+            offset: self.offset + by,
+        }
+    }
+    /// The same transform grown `factor` times around the world point `anchor`, which stays put
+    pub fn scaled_about(&self, anchor: FixedV2, factor: f64) -> ZoomTransform {
+        ZoomTransform {
+            scale: self.scale * factor,
+            offset: anchor + FixedV2::from_f64((self.offset - anchor).to_f64() * factor),
+        }
+    }
+    /// The transform from this space directly onto the screen of `camera`. The offsets are
+    /// subtracted in fixed point first, so the result is small and precise enough for the GPU
+    /// no matter how far either of them is from the origin.
+    pub fn relative_to(&self, camera: &ZoomTransform) -> ZoomTransform {
+        let offset = (self.offset - camera.offset).to_f64() / camera.scale;
+        ZoomTransform::new(self.scale / camera.scale, offset)
+    }
+    /// Undoes `relative_to`, putting a transform on the screen of `camera` back into the world
+    pub fn inverse_relative_to(&self, camera: &ZoomTransform) -> ZoomTransform {
+        ZoomTransform {
+            scale: self.scale * camera.scale,
+            offset: camera.offset + FixedV2::from_f64(self.offset.to_f64() * camera.scale),
+        }
+    }
+    /// Moves part of the way towards `target`, `fraction` being 0 for not at all and 1 for all
+    /// the way. The scale is interpolated logarithmically and the world point at the middle of
+    /// the screen linearly, so flying between very different zoom levels moves evenly.
+    pub fn interpolate_towards(
+        &mut self,
+        target: &ZoomTransform,
+        fraction: f64,
+        screen_center: P2f64,
+    ) {
+        if fraction >= 1.0 {
+            // the difference below is rounded, which is only exact enough once it's small
+            *self = target.clone();
+            return;
+        }
+        let center = self.transform_point_exact(screen_center);
+        let target_center = target.transform_point_exact(screen_center);
+        let center = center + FixedV2::from_f64((target_center - center).to_f64() * fraction);
+        self.scale *= (target.scale / self.scale).powf(fraction);
+        self.offset = center - FixedV2::from_f64(screen_center.coords * self.scale);
+    }
+    /// If this and `other` put everything on the screen less than `pixels` away from each other
+    pub fn is_close_to(&self, other: &ZoomTransform, pixels: f64) -> bool {
+        let between = other.relative_to(self);
+        (between.scale - 1.0).abs() < 1e-6 && between.offset.to_f64().norm() < pixels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// How far apart, in pixels, the screens of two cameras are
+    fn screen_drift(a: &ZoomTransform, b: &ZoomTransform) -> f64 {
+        let between = a.relative_to(b);
+        let corners = [P2f64::new(0.0, 0.0), P2f64::new(800.0, 600.0)];
+        corners
+            .iter()
+            .map(|c| (between.transform_point(*c) - c).norm())
+            .fold(0.0, f64::max)
+    }
+
+    fn movement(zoom: f64, wrt_point: P2f64, pan: V2f64) -> Movement {
+        Movement {
+            wrt_point,
+            zoom,
+            pan,
+        }
+    }
+
+    fn camera() -> impl Strategy<Value = ZoomTransform> {
+        (-25i32..6, -1e3f64..1e3, -1e3f64..1e3).prop_map(|(exponent, x, y)| {
+            let scale = 10f64.powi(exponent);
+            let mut to_return = ZoomTransform::new(1.0, V2f64::new(x, y));
+            // offset by something far below f64 precision at this position
+            to_return.offset += FixedV2::from_f64(V2f64::new(x, y) * scale);
+            to_return.scale = scale;
+            to_return
+        })
+    }
+
+    fn screen_point() -> impl Strategy<Value = P2f64> {
+        (0.0f64..800.0, 0.0f64..600.0).prop_map(|(x, y)| P2f64::new(x, y))
+    }
+
+    #[test]
+    fn fitting_puts_rect_in_the_middle_of_the_screen() {
+        let deep = ZoomTransform::new(1e-20, V2f64::new(105.0, 73.0));
+        let rect = Rect::around_points(vec![P2f64::new(10.0, 10.0), P2f64::new(50.0, 20.0)], &deep)
+            .unwrap();
+        let screen_size = V2f64::new(800.0, 600.0);
+        let camera = ZoomTransform::fitting(&rect, screen_size, 1.0).unwrap();
+        let on_screen = deep.relative_to(&camera);
+        let min = on_screen.transform_point(P2f64::new(10.0, 10.0));
+        let max = on_screen.transform_point(P2f64::new(50.0, 20.0));
+        assert!((min.x - 0.0).abs() < 1e-6 && (max.x - 800.0).abs() < 1e-6);
+        assert!(((min.y + max.y) / 2.0 - 300.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn interpolating_reaches_target() {
+        let mut camera = ZoomTransform::does_nothing();
+        let target = ZoomTransform::new(1e-15, V2f64::new(-3.0, 1e4));
+        let screen_center = P2f64::new(400.0, 300.0);
+        camera.interpolate_towards(&target, 0.5, screen_center);
+        assert!(!camera.is_close_to(&target, 1.0));
+        camera.interpolate_towards(&target, 1.0, screen_center);
+        assert!(camera.is_close_to(&target, 1e-6));
+    }
+
+    #[test]
+    fn objects_stay_put_deep_in_a_zoom() {
+        let mut camera = ZoomTransform::does_nothing();
+        let dive = movement(10.0, P2f64::new(105.0, 73.0), V2f64::zeros());
+        for _ in 0..30 {
+            dive.apply_to_transform(&mut camera);
+        }
+        // an object drawn here, at the same size as the screen
+        let object = camera.clone();
+        let pixel = P2f64::new(400.0, 300.0);
+        let pan = movement(1.0, P2f64::origin(), V2f64::new(1.0, 1.0));
+        pan.apply_to_transform(&mut camera);
+        let on_screen = object.relative_to(&camera).transform_point(pixel);
+        assert!((on_screen - (pixel - V2f64::new(1.0, 1.0))).norm() < 1e-6);
+    }
+
+    #[test]
+    fn relative_transforms_can_be_put_back() {
+        let object = ZoomTransform::new(3e-12, V2f64::new(5.0, 9.0));
+        let camera = ZoomTransform::new(1e-10, V2f64::new(5.0, 9.0 - 1e-8));
+        let back = object.relative_to(&camera).inverse_relative_to(&camera);
+        assert!(back.is_close_to(&object, 1e-6));
+    }
+
+    #[test]
+    fn scaling_keeps_the_anchor_in_place() {
+        let object = ZoomTransform::new(1e-18, V2f64::new(7.0, -2.0));
+        let anchor = object.transform_point_exact(P2f64::new(10.0, 20.0));
+        let scaled = object.scaled_about(anchor, 3.0);
+        assert!((scaled.scale() / 3e-18 - 1.0).abs() < 1e-12);
+        let still = scaled.inverse_transform_exact(anchor);
+        assert!((still - P2f64::new(10.0, 20.0)).norm() < 1e-9);
+        // everything else moves three times as far from the anchor
+        let corner = scaled.transform_point_exact(P2f64::origin()) - anchor;
+        let before = object.transform_point_exact(P2f64::origin()) - anchor;
+        assert!((corner.to_f64() - before.to_f64() * 3.0).norm() < 1e-30);
+    }
+
+    proptest! {
+        #[test]
+        fn movement_round_trips(
+            start in camera(),
+            zoom in 0.1f64..10.0,
+            wrt_point in screen_point(),
+            pan in (-500.0f64..500.0, -500.0f64..500.0),
+        ) {
+            let pan = V2f64::new(pan.0, pan.1);
+            let mut camera = start.clone();
+            movement(zoom, wrt_point, pan).apply_to_transform(&mut camera);
+            movement(1.0, wrt_point, -pan).apply_to_transform(&mut camera);
+            movement(1.0 / zoom, wrt_point, V2f64::zeros()).apply_to_transform(&mut camera);
+            prop_assert!(screen_drift(&start, &camera) < 1e-9);
+        }
+
+        #[test]
+        fn scrolling_back_and_forth_doesnt_drift(
+            start in camera(),
+            steps in proptest::collection::vec((-3i32..=3, screen_point()), 1..60),
+        ) {
+            let mut camera = start.clone();
+            for (y, wrt_point) in steps.iter() {
+                let zoom = 1.0 + (*y as f64) * 0.05;
+                movement(zoom, *wrt_point, V2f64::zeros()).apply_to_transform(&mut camera);
+            }
+            for (y, wrt_point) in steps.iter().rev() {
+                let zoom = 1.0 + (*y as f64) * 0.05;
+                movement(1.0 / zoom, *wrt_point, V2f64::zeros()).apply_to_transform(&mut camera);
+            }
+            prop_assert!(screen_drift(&start, &camera) < 1e-6);
+        }
+    }
+}
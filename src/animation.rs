@@ -0,0 +1,222 @@
+use explain_core::util::*;
+use explain_core::zooming::*;
+
+/// How quickly the remaining zoom is eaten up, per second. Higher is snappier
+const ZOOM_SPEED: f64 = 18.0;
+/// How quickly a flung pan slows down, per second
+const PAN_FRICTION: f64 = 5.0;
+/// How quickly the measured drag velocity follows the mouse, per second
+const VELOCITY_SMOOTHING: f64 = 30.0;
+/// Below this many pixels per second panning is considered stopped
+const MIN_PAN_SPEED: f64 = 5.0;
+/// Below this much zoom left to do (as a factor) zooming is considered finished
+const MIN_ZOOM_LEFT: f64 = 1e-4;
+/// How quickly the camera flies to where it's been sent, per second
+const FLIGHT_SPEED: f64 = 6.0;
+/// Flights end once everything on the screen is within this many pixels of where it should be
+const FLIGHT_DONE_PIXELS: f64 = 0.1;
+
+/// Fraction of the remaining distance an exponential decay at `speed` covers in `dt` seconds. Used
+/// for all of the easing so that it looks the same no matter how long frames take.
+fn ease_fraction(speed: f64, dt: f64) -> f64 {
+    1.0 - (-speed * dt).exp()
+}
+
+/// Sits on top of `Movement` and spreads zooming and panning out over several frames, so the
+/// camera eases into zooms and keeps gliding after a pan is let go of
+pub struct CameraAnimator {
+    /// Zoom factor that still has to be applied
+    zoom_left: f64,
+    zoom_point: P2f64,
+    dragging: bool,
+    /// Pixels dragged since the last update
+    dragged: V2f64,
+    /// Pixels per second
+    pan_velocity: V2f64,
+    /// Camera to fly to, and the middle of the screen it's flying on
+    flight: Option<(ZoomTransform, P2f64)>,
+}
+
+impl CameraAnimator {
+    pub fn new() -> Self {
+        Self {
+            zoom_left: 1.0,
+            zoom_point: P2f64::origin(),
+            dragging: false,
+            dragged: V2f64::zeros(),
+            pan_velocity: V2f64::zeros(),
+            flight: None,
+        }
+    }
+
+    /// Queues up zooming by `zoom` around the screen point `wrt_point`. Zooms pile up on
+    /// whatever is still left to do from previous calls
+    pub fn zoom_towards(&mut self, zoom: f64, wrt_point: P2f64) {
+        self.flight = None;
+        self.zoom_left *= zoom;
+        self.zoom_point = wrt_point;
+    }
+
+    /// Pans the screen by `pan` pixels right away, like dragging the board with the mouse
+    pub fn drag(&mut self, pan: V2f64) {
+        if !self.dragging {
+            self.pan_velocity = V2f64::zeros();
+        }
+        self.flight = None;
+        self.dragging = true;
+        self.dragged += pan;
+    }
+
+    /// Lets go of the board, which keeps moving with the velocity it was dragged at
+    pub fn release(&mut self) {
+        self.dragging = false;
+    }
+
+    /// Smoothly moves the camera until it's `target`. `screen_center` is the middle of the screen,
+    /// which is kept moving in a straight line
+    pub fn fly_to(&mut self, target: ZoomTransform, screen_center: P2f64) {
+        self.stop();
+        self.flight = Some((target, screen_center));
+    }
+
+    /// Stops all movement in its tracks
+    pub fn stop(&mut self) {
+        self.flight = None;
+        self.zoom_left = 1.0;
+        self.dragged = V2f64::zeros();
+        self.pan_velocity = V2f64::zeros();
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.flight.is_some()
+            || self.zoom_left != 1.0
+            || self.dragged != V2f64::zeros()
+            // keeps going while a held still drag slows down, so letting go doesn't fling it
+            || self.pan_velocity.norm() > MIN_PAN_SPEED
+    }
+
+    /// Advances the animation by `dt` seconds, moving `camera` along
+    pub fn update(&mut self, dt: f64, camera: &mut ZoomTransform) {
+        self.movement(dt).apply_to_transform(camera);
+
+        if let Some((target, screen_center)) = &self.flight {
+            camera.interpolate_towards(target, ease_fraction(FLIGHT_SPEED, dt), *screen_center);
+            if camera.is_close_to(target, FLIGHT_DONE_PIXELS) {
+                *camera = target.clone();
+                self.flight = None;
+            }
+        }
+    }
+
+    fn movement(&mut self, dt: f64) -> Movement {
+        let mut to_return = Movement::new();
+        if dt <= 0.0 {
+            return to_return;
+        }
+
+        // zoom is eased in log space so that zooming in and then out by the same amount
+        // animates symmetrically
+        if (self.zoom_left.ln()).abs() > MIN_ZOOM_LEFT {
+            let step = self.zoom_left.powf(ease_fraction(ZOOM_SPEED, dt));
+            to_return.zoom = step;
+            to_return.wrt_point = self.zoom_point;
+            self.zoom_left /= step;
+        } else if self.zoom_left != 1.0 {
+            to_return.zoom = self.zoom_left;
+            to_return.wrt_point = self.zoom_point;
+            self.zoom_left = 1.0;
+        }
+
+        if self.dragging {
+            let measured = self.dragged / dt;
+            self.pan_velocity +=
+                (measured - self.pan_velocity) * ease_fraction(VELOCITY_SMOOTHING, dt);
+            to_return.pan = self.dragged;
+            self.dragged = V2f64::zeros();
+        } else if self.pan_velocity.norm() > MIN_PAN_SPEED {
+            // integral of the decaying velocity over the frame, so the total distance travelled
+            // doesn't depend on the frame rate
+            let decay = ease_fraction(PAN_FRICTION, dt);
+            to_return.pan = self.dragged + self.pan_velocity * (decay / PAN_FRICTION);
+            self.dragged = V2f64::zeros();
+            self.pan_velocity *= 1.0 - decay;
+        } else {
+            to_return.pan = self.dragged;
+            self.dragged = V2f64::zeros();
+            self.pan_velocity = V2f64::zeros();
+        }
+
+        to_return
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(animator: &mut CameraAnimator, camera: &mut ZoomTransform, frames: usize, dt: f64) {
+        for _ in 0..frames {
+            animator.update(dt, camera);
+        }
+    }
+
+    #[test]
+    fn zoom_ends_up_where_it_was_asked_to() {
+        let mut animator = CameraAnimator::new();
+        let mut camera = ZoomTransform::does_nothing();
+        animator.zoom_towards(1.05, P2f64::new(400.0, 300.0));
+        animator.zoom_towards(1.05, P2f64::new(400.0, 300.0));
+        run(&mut animator, &mut camera, 120, 1.0 / 60.0);
+        assert!(!animator.is_animating());
+        assert!((camera.scale() - 1.0 / (1.05 * 1.05)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn zoom_doesnt_depend_on_frame_rate() {
+        let mut slow = (CameraAnimator::new(), ZoomTransform::does_nothing());
+        let mut fast = (CameraAnimator::new(), ZoomTransform::does_nothing());
+        slow.0.zoom_towards(2.0, P2f64::new(10.0, 20.0));
+        fast.0.zoom_towards(2.0, P2f64::new(10.0, 20.0));
+        run(&mut slow.0, &mut slow.1, 3, 1.0 / 30.0);
+        run(&mut fast.0, &mut fast.1, 12, 1.0 / 120.0);
+        assert!((slow.1.scale() - fast.1.scale()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn flung_pan_glides_the_same_distance_at_any_frame_rate() {
+        let mut distances = vec![];
+        for &fps in [30.0, 144.0].iter() {
+            let dt = 1.0 / fps;
+            let mut animator = CameraAnimator::new();
+            let mut camera = ZoomTransform::does_nothing();
+            // drag at 600 pixels per second for half a second, then let go
+            for _ in 0..(fps as usize / 2) {
+                animator.drag(V2f64::new(600.0 * dt, 0.0));
+                animator.update(dt, &mut camera);
+            }
+            let let_go_at = camera.transform_point(P2f64::origin());
+            animator.release();
+            run(&mut animator, &mut camera, fps as usize * 5, dt);
+            assert!(!animator.is_animating());
+            distances.push((camera.transform_point(P2f64::origin()) - let_go_at).x);
+        }
+        assert!(distances[0] > 50.0);
+        assert!((distances[0] - distances[1]).abs() < distances[0] * 0.05);
+    }
+
+    #[test]
+    fn flights_land_and_get_interrupted() {
+        let mut animator = CameraAnimator::new();
+        let mut camera = ZoomTransform::does_nothing();
+        let target = ZoomTransform::new(1e-10, V2f64::new(5.0, 5.0));
+        animator.fly_to(target.clone(), P2f64::new(400.0, 300.0));
+        run(&mut animator, &mut camera, 10, 1.0 / 60.0);
+        assert!(animator.flight.is_some());
+        run(&mut animator, &mut camera, 600, 1.0 / 60.0);
+        assert_eq!(camera, target);
+
+        animator.fly_to(ZoomTransform::does_nothing(), P2f64::new(400.0, 300.0));
+        animator.drag(V2f64::new(1.0, 0.0));
+        assert!(animator.flight.is_none());
+    }
+}
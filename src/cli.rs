@@ -0,0 +1,265 @@
+use crate::export::ExportOptions;
+use crate::settings::Settings;
+use explain_core::navigation::FIT_MARGIN;
+use explain_core::saving::{self, SaveFormat};
+use explain_core::shape;
+use explain_core::svg;
+use explain_core::util::*;
+use explain_core::zooming::ZoomTransform;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Something done to board files from the command line, without a window
+#[derive(Clone, PartialEq, Debug)]
+pub enum Task<'a> {
+    /// Says what's on a board
+    Info(&'a str),
+    /// Draws a board into a png or svg, or writes it out as json. A png is fitted into `size` if
+    /// there is one, otherwise it's as big as the board looks from where it was left
+    Export {
+        board: &'a str,
+        to: &'a str,
+        size: Option<(u32, u32)>,
+    },
+    Convert(&'a str, &'a str),
+    /// Puts everything on `second` on top of `first`
+    Merge {
+        first: &'a str,
+        second: &'a str,
+        to: &'a str,
+    },
+    /// Checks a board can be loaded
+    Validate(&'a str),
+}
+
+pub const USAGE: &str = "explain info board.explain
+explain export board.explain out.png|out.svg|out.json [--size WxH]
+explain convert from.explain to.explain.json|to.excalidraw
+explain merge first.explain second.explain out.explain
+explain validate board.explain";
+
+impl<'a> Task<'a> {
+    /// The task `args` ask for, or `None` if they don't start with the name of one
+    pub fn parse(args: &[&'a str]) -> Option<Result<Self, String>> {
+        let task = match *args {
+            ["info", board] => Task::Info(board),
+            ["export", board, to] => Task::Export {
+                board,
+                to,
+                size: None,
+            },
+            ["export", board, to, "--size", size] => {
+                let size = ExportOptions::parse(&["--size", size])
+                    .map(|options| (options.width, options.height));
+                match size {
+                    Ok(size) => Task::Export {
+                        board,
+                        to,
+                        size: Some(size),
+                    },
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+            ["convert", from, to] => Task::Convert(from, to),
+            ["merge", first, second, to] => Task::Merge { first, second, to },
+            ["validate", board] => Task::Validate(board),
+            [name, ..] if ["info", "export", "convert", "merge", "validate"].contains(&name) => {
+                return Some(Err(format!("that's not how to use {}", name)))
+            }
+            _ => return None,
+        };
+        Some(Ok(task))
+    }
+}
+
+/// Like "3 line, 1 text"
+fn describe(counts: &BTreeMap<&str, usize>) -> String {
+    if counts.is_empty() {
+        return String::from("nothing");
+    }
+    let counts: Vec<String> = counts
+        .iter()
+        .map(|(kind, count)| format!("{} {}", count, kind))
+        .collect();
+    counts.join(", ")
+}
+
+fn info(path: &str) -> Result<String, String> {
+    let board = saving::read(Path::new(path))?;
+    let mut everything = BTreeMap::new();
+    let mut layers = vec![];
+    for layer in board.layers.iter() {
+        let mut counts = BTreeMap::new();
+        for kind in layer.kinds() {
+            *counts.entry(kind).or_insert(0) += 1;
+            *everything.entry(kind).or_insert(0) += 1;
+        }
+        let mut flags = vec![];
+        if !layer.visible {
+            flags.push("hidden");
+        }
+        if layer.locked {
+            flags.push("locked");
+        }
+        let flags = if flags.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", flags.join(", "))
+        };
+        layers.push(format!("  {}{} - {}", layer.name, flags, describe(&counts)));
+    }
+    let mut lines = vec![
+        format!("objects: {}", describe(&everything)),
+        format!("layers: {}", board.layers.len()),
+    ];
+    lines.extend(layers);
+    let shapes = board.shapes()?;
+    let bounds = shape::bounds(shapes.iter().filter_map(|s| s.bounding_box()));
+    lines.push(match bounds {
+        Some(bounds) => {
+            let (min, max, size) = (bounds.min.to_f64(), bounds.max.to_f64(), bounds.size());
+            format!(
+                "bounds: {:.2}, {:.2} to {:.2}, {:.2} ({:.2} x {:.2})",
+                min.x, min.y, max.x, max.y, size.x, size.y
+            )
+        }
+        None => String::from("bounds: nothing visible"),
+    });
+    lines.push(format!("views: {}", board.views.len()));
+    let recording = &board.recording;
+    let narrated = if recording.narration.is_empty() {
+        ""
+    } else {
+        ", narrated"
+    };
+    lines.push(if recording.moments.is_empty() {
+        String::from("recording: none")
+    } else {
+        format!("recording: {:.1} seconds{}", recording.duration(), narrated)
+    });
+    Ok(lines.join("\n"))
+}
+
+fn export(
+    board: &str,
+    to: &str,
+    size: Option<(u32, u32)>,
+    settings: &Settings,
+) -> Result<String, String> {
+    let saved_world = saving::read(Path::new(board))?;
+    let to_path = Path::new(to);
+    let extension = to_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    let written = match extension.as_deref() {
+        Some("png") => {
+            let shapes = saved_world.shapes()?;
+            let bounds = shape::bounds(shapes.iter().filter_map(|s| s.bounding_box()))
+                .ok_or("there's nothing on the board to draw")?;
+            let (camera, (width, height)) = match size {
+                Some((width, height)) => {
+                    let screen_size = V2f64::new(width as f64, height as f64);
+                    let camera = ZoomTransform::fitting(&bounds, screen_size, FIT_MARGIN)
+                        .unwrap_or_else(|| saved_world.camera.clone());
+                    (camera, (width, height))
+                }
+                None => shape::picture_page(bounds, &saved_world.camera),
+            };
+            let image = shape::paint(&shapes, &camera, (width, height), settings.background_color);
+            image.save(to_path).map_err(|e| e.to_string())?;
+            format!("{}x{} png", width, height)
+        }
+        Some("svg") => {
+            let shapes = saved_world.shapes()?;
+            let svg = svg::render_shapes(&shapes, &saved_world.camera);
+            std::fs::write(to_path, svg).map_err(|e| e.to_string())?;
+            String::from("svg")
+        }
+        Some("json") => {
+            let json = saving::encode(&saved_world, SaveFormat::Json);
+            std::fs::write(to_path, json).map_err(|e| e.to_string())?;
+            String::from("json")
+        }
+        _ => {
+            return Err(String::from(
+                "boards can be exported to .png, .svg or .json",
+            ))
+        }
+    };
+    Ok(format!("exported {} to {} as {}", board, to, written))
+}
+
+fn merge(first: &str, second: &str, to: &str) -> Result<String, String> {
+    let mut merged = saving::read(Path::new(first))?;
+    let left_out = merged.merge(saving::read(Path::new(second))?);
+    let note = saving::write(&merged, Path::new(to))?;
+    let notes: Vec<String> = left_out.into_iter().chain(note).collect();
+    let merged = format!("merged {} and {} into {}", first, second, to);
+    if notes.is_empty() {
+        return Ok(merged);
+    }
+    Ok(format!("{}, but {}", merged, notes.join(" and ")))
+}
+
+fn validate(path: &str) -> Result<String, String> {
+    let board = saving::read(Path::new(path))?;
+    let problems = board.problems();
+    if !problems.is_empty() {
+        return Err(problems.join("\n"));
+    }
+    let objects: usize = board.layers.iter().map(|l| l.kinds().count()).sum();
+    Ok(format!(
+        "{} is fine, with {} objects on {} layers",
+        path,
+        objects,
+        board.layers.len()
+    ))
+}
+
+/// Does `task` with the user's `settings`, returning what to tell whoever asked for it, or what
+/// went wrong
+pub fn run(task: Task, settings: &Settings) -> Result<String, String> {
+    match task {
+        Task::Info(board) => info(board),
+        Task::Export { board, to, size } => export(board, to, size, settings),
+        Task::Convert(from, to) => match saving::convert(Path::new(from), Path::new(to))? {
+            Some(note) => Ok(format!("converted {} to {}, but {}", from, to, note)),
+            None => Ok(format!("converted {} to {}", from, to)),
+        },
+        Task::Merge { first, second, to } => merge(first, second, to),
+        Task::Validate(board) => validate(board),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tasks_come_from_the_command_line() {
+        assert_eq!(
+            Task::parse(&["export", "a.explain", "a.png", "--size", "64x32"]),
+            Some(Ok(Task::Export {
+                board: "a.explain",
+                to: "a.png",
+                size: Some((64, 32)),
+            }))
+        );
+        assert_eq!(
+            Task::parse(&["merge", "a", "b", "c"]),
+            Some(Ok(Task::Merge {
+                first: "a",
+                second: "b",
+                to: "c",
+            }))
+        );
+        assert!(matches!(Task::parse(&["info"]), Some(Err(_))));
+        assert!(matches!(
+            Task::parse(&["export", "a", "b", "--size", "big"]),
+            Some(Err(_))
+        ));
+        // anything else is for the app
+        assert_eq!(Task::parse(&["--host", "localhost:4000"]), None);
+    }
+}
@@ -0,0 +1,373 @@
+use crate::svg;
+use arboard::ImageData;
+use explain_core::encoding::{base64_decode, base64_encode};
+use explain_core::fixed::FixedV2;
+use explain_core::images::Picture;
+use explain_core::shape::{self, bounds, Shape};
+use explain_core::text::{font_size, SavedText};
+use explain_core::util::*;
+use explain_core::world::{self, LayerId, ObjectId, SavedObjects, World};
+use explain_core::zooming::ZoomTransform;
+use image::{DynamicImage, ImageOutputFormat, RgbaImage};
+use sdl2::clipboard::ClipboardUtil;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::path::Path;
+use std::rc::Rc;
+
+/// Id of the svg element copied objects are hidden in, so they can be pasted back into a board
+const OBJECTS_ID: &str = "explain-objects";
+
+#[derive(Serialize, Deserialize)]
+struct Clip {
+    /// The camera the objects were seen through when copied, so they paste at the size they
+    /// looked
+    camera: ZoomTransform,
+    objects: SavedObjects,
+}
+
+/// How far a duplicate lands from what it's a copy of, in pixels on the screen
+const DUPLICATE_OFFSET: f64 = 20.0;
+
+/// Copies go to the system clipboard so they can be pasted into other boards and programs, and
+/// are also kept here in case it can't be used. SDL's clipboard only holds text, so pictures go
+/// through `arboard` instead, when there's a clipboard it can reach
+pub struct Clipboard {
+    system: ClipboardUtil,
+    images: Option<arboard::Clipboard>,
+    last_copy: Option<String>,
+}
+
+impl Clipboard {
+    pub fn new(system: ClipboardUtil) -> Self {
+        Self {
+            system,
+            images: arboard::Clipboard::new().ok(),
+            last_copy: None,
+        }
+    }
+
+    /// Copies the selection, returning false if nothing is selected
+    pub fn copy(&mut self, world: &World) -> bool {
+        let copied = match copy(world) {
+            Some(c) => c,
+            None => return false,
+        };
+        if let Err(e) = self.system.set_clipboard_text(&copied) {
+            println!("couldn't copy to the system clipboard - {}", e);
+        }
+        self.last_copy = Some(copied);
+        true
+    }
+
+    /// Copies a picture of the selection as it looks now on `background`, for programs that
+    /// can't paste svg. Returns false if nothing is selected
+    pub fn copy_image(&mut self, world: &World, background: [f32; 3]) -> bool {
+        let image = match picture(world, background) {
+            Some(i) => i,
+            None => return false,
+        };
+        let copied = match self.images.as_mut() {
+            Some(images) => images
+                .set_image(ImageData {
+                    width: image.width() as usize,
+                    height: image.height() as usize,
+                    bytes: Cow::Owned(image.into_raw()),
+                })
+                .map_err(|e| e.to_string()),
+            None => Err(String::from("there's no clipboard for pictures")),
+        };
+        if let Err(e) = copied {
+            println!("couldn't copy the picture to the system clipboard - {}", e);
+        }
+        true
+    }
+
+    /// What was copied last, from this board or anywhere else, as objects to add to the board.
+    /// A picture becomes an image, and text is pasted like `paste` says
+    pub fn paste(
+        &mut self,
+        camera: &ZoomTransform,
+        screen_point: P2f64,
+        screen_size: V2f64,
+    ) -> Result<Vec<Box<dyn Shape>>, String> {
+        if let Some(png) = self.images.as_mut().and_then(clipboard_png) {
+            let mut image = Picture::from_bytes(Rc::from(png))?;
+            image.place(camera, screen_point, screen_size);
+            return Ok(vec![Box::new(image)]);
+        }
+        let from_system = if self.system.has_clipboard_text() {
+            self.system.clipboard_text().ok()
+        } else {
+            None
+        };
+        match from_system.as_ref().or(self.last_copy.as_ref()) {
+            Some(text) => paste(text, camera, screen_point, screen_size),
+            None => Ok(vec![]),
+        }
+    }
+}
+
+/// The selected objects as an svg other programs can use, with everything needed to paste them
+/// back into a board hidden inside. `None` if nothing is selected
+pub fn copy(world: &World) -> Option<String> {
+    world.selected().next()?;
+    let clip = Clip {
+        camera: world.camera.clone(),
+        objects: world::save_objects(world.selected()),
+    };
+    let encoded = base64_encode(serde_json::to_string(&clip).unwrap().as_bytes());
+    Some(svg::render(
+        world.selected(),
+        &world.camera,
+        Some((OBJECTS_ID, &encoded)),
+    ))
+}
+
+/// The selected objects drawn as they look through the camera, cropped to them. `None` if
+/// nothing is selected
+fn picture(world: &World, background: [f32; 3]) -> Option<RgbaImage> {
+    let shapes = world::load_objects(world::save_objects(world.selected())).ok()?;
+    let bounds = bounds(shapes.iter().filter_map(|s| s.bounding_box()))?;
+    let (view, size) = shape::picture_page(bounds, &world.camera);
+    Some(shape::paint(&shapes, &view, size, background))
+}
+
+/// The picture on the system clipboard as a png, if there is one
+fn clipboard_png(images: &mut arboard::Clipboard) -> Option<Vec<u8>> {
+    let copied = images.get_image().ok()?;
+    let (width, height) = (copied.width as u32, copied.height as u32);
+    let image = RgbaImage::from_raw(width, height, copied.bytes.into_owned())?;
+    let mut png = vec![];
+    DynamicImage::ImageRgba8(image)
+        .write_to(&mut png, ImageOutputFormat::Png)
+        .ok()?;
+    Some(png)
+}
+
+/// Adds copies of the selected objects a little below and to the right of them on the screen,
+/// each on top of the layer its original is on. Returns the copies' ids
+pub fn duplicate(world: &mut World) -> Result<Vec<ObjectId>, String> {
+    let sources: Vec<(LayerId, &dyn Shape)> = world
+        .selection
+        .iter()
+        .filter_map(|&id| Some((world.layer_of(id)?, world.object(id)?)))
+        .collect();
+    let copies = world::load_objects(world::save_objects(sources.iter().map(|(_, o)| *o)))?;
+    let layers: Vec<LayerId> = sources.iter().map(|(l, _)| *l).collect();
+    let camera = &world.camera;
+    let offset = camera.transform_point_exact(P2f64::new(DUPLICATE_OFFSET, DUPLICATE_OFFSET))
+        - camera.transform_point_exact(P2f64::origin());
+    let mut to_return = vec![];
+    for (layer, mut copy) in layers.into_iter().zip(copies) {
+        let moved = copy.transform().translated(offset);
+        copy.set_transform(moved);
+        to_return.push(world.add_to(layer, copy));
+    }
+    Ok(to_return)
+}
+
+/// Turns clipboard text into objects: ones copied out of a board, an image if it's a data url or
+/// the path of an image file, and otherwise a text object per line. They're put at
+/// `screen_point` on the screen `camera` is looking through
+pub fn paste(
+    text: &str,
+    camera: &ZoomTransform,
+    screen_point: P2f64,
+    screen_size: V2f64,
+) -> Result<Vec<Box<dyn Shape>>, String> {
+    if let Some(encoded) = explain_core::svg::metadata(text, OBJECTS_ID) {
+        let json = base64_decode(encoded)?;
+        let clip: Clip = serde_json::from_slice(&json).map_err(|e| e.to_string())?;
+        let mut objects = world::load_objects(clip.objects)?;
+        for object in objects.iter_mut() {
+            let on_screen = object.transform().relative_to(&clip.camera);
+            object.set_transform(on_screen.inverse_relative_to(camera));
+        }
+        center_on(&mut objects, camera.transform_point_exact(screen_point));
+        return Ok(objects);
+    }
+    // drawings copied out of other programs
+    if text.contains("<svg") {
+        if let Ok(objects) = svg::import_objects(text, camera, screen_point) {
+            return Ok(objects);
+        }
+    }
+    if let Some(file) = image_file(text) {
+        // anything that doesn't decode is pasted as text instead
+        if let Ok(mut image) = Picture::from_bytes(Rc::from(file)) {
+            image.place(camera, screen_point, screen_size);
+            return Ok(vec![Box::new(image)]);
+        }
+    }
+    Ok(text_lines(text, camera, na::convert(screen_point)))
+}
+
+/// Moves `objects` together so they're centered on `point` in the world
+pub fn center_on(objects: &mut [Box<dyn Shape>], point: FixedV2) {
+    if let Some(bounds) = bounds(objects.iter().filter_map(|o| o.bounding_box())) {
+        let by = point - bounds.center();
+        for object in objects.iter_mut() {
+            let moved = object.transform().translated(by);
+            object.set_transform(moved);
+        }
+    }
+}
+
+/// One text object per line of `text`, the first one starting at `screen_point`
+fn text_lines(text: &str, camera: &ZoomTransform, screen_point: P2) -> Vec<Box<dyn Shape>> {
+    let mut to_return: Vec<Box<dyn Shape>> = vec![];
+    let mut origin = screen_point;
+    for line in text.lines() {
+        let object = SavedText {
+            text: String::from(line),
+            transform: camera.clone(),
+            origin,
+        };
+        origin.y += font_size();
+        // empty lines still take up room
+        if object.bounding_box().is_some() {
+            to_return.push(Box::new(object));
+        }
+    }
+    to_return
+}
+
+/// Decodes `%20` and the like in urls
+fn percent_decode(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut to_return = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                to_return.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                to_return.push(byte);
+                i += 1;
+            }
+        }
+    }
+    to_return
+}
+
+/// Where to find the image file clipboard text is talking about, if it's talking about one
+#[derive(PartialEq, Debug)]
+enum ImageSource {
+    /// Already in the text, from a data url
+    Inline(Vec<u8>),
+    Path(String),
+}
+
+fn image_source(text: &str) -> Option<ImageSource> {
+    let text = text.trim();
+    if let Some(rest) = text.strip_prefix("data:image/") {
+        let (_, encoded) = rest.split_at(rest.find(";base64,")? + ";base64,".len());
+        return base64_decode(encoded).ok().map(ImageSource::Inline);
+    }
+    // file managers copy files as a list of urls, one per line
+    if text.lines().count() != 1 {
+        return None;
+    }
+    let path = match text.strip_prefix("file://") {
+        Some(url) => String::from_utf8(percent_decode(url)).ok()?,
+        None => String::from(text),
+    };
+    Some(ImageSource::Path(path))
+}
+
+/// The contents of the image file `text` refers to, if it's an image that exists
+fn image_file(text: &str) -> Option<Vec<u8>> {
+    match image_source(text)? {
+        ImageSource::Inline(bytes) => Some(bytes),
+        ImageSource::Path(path) if Path::new(&path).is_file() => std::fs::read(path).ok(),
+        ImageSource::Path(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use explain_core::line::{SavedLine, Stroke};
+
+    #[test]
+    fn images_are_found_in_urls_and_paths() {
+        assert_eq!(
+            image_source("data:image/png;base64,Zm9v"),
+            Some(ImageSource::Inline(b"foo".to_vec()))
+        );
+        assert_eq!(
+            image_source("file:///home/me/My%20Pictures/a%2Bb.png\n"),
+            Some(ImageSource::Path(String::from(
+                "/home/me/My Pictures/a+b.png"
+            )))
+        );
+        assert_eq!(
+            image_source("/tmp/shot.png"),
+            Some(ImageSource::Path(String::from("/tmp/shot.png")))
+        );
+        assert_eq!(image_source("two\nlines"), None);
+        assert_eq!(percent_decode("100%"), b"100%");
+    }
+
+    #[test]
+    fn duplicates_land_beside_their_originals_on_the_same_layer() {
+        let mut world = World::new();
+        world.camera = ZoomTransform::new(2.0, V2f64::new(5.0, 0.0));
+        let original = world.add(Box::new(SavedLine {
+            points: vec![P2::new(0.0, 0.0), P2::new(10.0, 10.0)],
+            transform: world.camera.clone(),
+            stroke: Stroke {
+                color: [0.0, 0.0, 0.0],
+                width: 1.0,
+            },
+        }));
+        let first_layer = world.current_layer().id;
+        world.add_layer();
+        world.selection = vec![original];
+
+        let copies = duplicate(&mut world).unwrap();
+        assert_eq!(copies.len(), 1);
+        assert!(copies[0] != original);
+        assert_eq!(world.layer_of(copies[0]), Some(first_layer));
+        // 20 pixels seen through a camera 2 world units to the pixel
+        let offset = FixedV2::from_f64(V2f64::new(40.0, 40.0));
+        assert_eq!(
+            world.object(copies[0]).unwrap().transform(),
+            &world.camera.translated(offset)
+        );
+    }
+
+    #[test]
+    fn pictures_are_cropped_to_the_selection() {
+        let mut world = World::new();
+        world.camera = ZoomTransform::new(2.0, V2f64::new(5.0, 0.0));
+        let white = [1.0, 1.0, 1.0];
+        assert!(picture(&world, white).is_none());
+        let line = world.add(Box::new(SavedLine {
+            points: vec![P2::new(0.0, 0.0), P2::new(100.0, 0.0)],
+            transform: ZoomTransform::does_nothing(),
+            stroke: Stroke {
+                color: [0.0, 0.0, 0.0],
+                width: 4.0,
+            },
+        }));
+        world.selection = vec![line];
+
+        let image = picture(&world, white).unwrap();
+        // 100 world units seen 2 to the pixel, with a margin around it
+        assert!(
+            image.width() > 50 && image.width() < 100,
+            "{}",
+            image.width()
+        );
+        assert_eq!(image.get_pixel(0, 0).0, [255, 255, 255, 255]);
+        let y = image.height() / 2;
+        assert!(image.get_pixel(image.width() / 2, y).0[0] < 128);
+    }
+}
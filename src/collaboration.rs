@@ -0,0 +1,760 @@
+use explain_core::bookmarks::Bookmark;
+use explain_core::changes::{apply, everything_on, Operation, Tracker};
+use explain_core::fixed::FixedV2;
+use explain_core::world::World;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Duration;
+
+/// How often the main loop looks for messages while the board is shared
+pub const POLL_INTERVAL: Duration = Duration::from_millis(15);
+
+/// Where someone on a shared board is pointing and what they're looking at
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Presence {
+    pub name: String,
+    /// Their cursor and name tag are drawn in this
+    pub color: [f32; 3],
+    /// Where their mouse is in the world, if it's over the board
+    pub cursor: Option<FixedV2>,
+    /// What they can see, for following them around
+    pub view: Bookmark,
+}
+
+/// Colors people's cursors are drawn in, which stand out from black lines
+const PEER_COLORS: [[f32; 3]; 5] = [
+    [0.85, 0.15, 0.15],
+    [0.95, 0.55, 0.1],
+    [0.15, 0.6, 0.2],
+    [0.15, 0.35, 0.85],
+    [0.55, 0.2, 0.7],
+];
+
+/// The color everyone sees the person with `site` in
+fn color_for(site: u16) -> [f32; 3] {
+    PEER_COLORS[site as usize % PEER_COLORS.len()]
+}
+
+/// What's sent over the network, one json message per line
+#[derive(Serialize, Deserialize)]
+enum Message {
+    /// From the host to someone who just joined, before everything already on the board. Their
+    /// ids are made with `site` in them
+    Welcome { site: u16 },
+    /// From a guest to the host, asking for a change to be made
+    Propose(Operation),
+    /// From the host to everyone. Everyone applies these in the order the host sends them, which
+    /// is what makes boards that were changed at the same time end up the same
+    Apply(Operation),
+    /// Where the person with `site` is now, from them to the host and from the host to everyone
+    /// else
+    Presence { site: u16, presence: Presence },
+    /// From the host to everyone, when someone leaves
+    Gone { site: u16 },
+}
+
+/// What `Session::receive` got
+#[derive(Default)]
+pub struct Received {
+    pub board_changed: bool,
+    /// If anyone else moved their mouse or camera, or came or went
+    pub others_moved: bool,
+    /// What happened that's worth telling, like someone joining or a change that couldn't be
+    /// made, oldest first
+    pub news: Vec<String>,
+}
+
+/// What the network threads hand to the main loop
+enum Incoming {
+    Joined(TcpStream),
+    Message(u16, Message),
+    Left(u16),
+    /// Something a network thread has to tell, see `Received::news`
+    News(String),
+}
+
+fn encode(message: &Message) -> Vec<u8> {
+    let mut line = serde_json::to_vec(message).unwrap();
+    line.push(b'\n');
+    line
+}
+
+/// Someone on the other end of a connection. What's sent to them is written by a thread of its
+/// own, so a slow connection doesn't hold up drawing
+struct Connection {
+    stream: TcpStream,
+    outgoing: Sender<Vec<u8>>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream, news: Sender<Incoming>) -> std::io::Result<Self> {
+        let mut writer = stream.try_clone()?;
+        let (outgoing, lines) = channel::<Vec<u8>>();
+        std::thread::spawn(move || {
+            for line in lines {
+                if let Err(e) = writer.write_all(&line) {
+                    let _ = news.send(Incoming::News(format!("couldn't send - {}", e)));
+                    // hanging up lets the reading thread notice they're gone
+                    let _ = writer.shutdown(Shutdown::Both);
+                    return;
+                }
+            }
+        });
+        Ok(Self { stream, outgoing })
+    }
+
+    /// Queues `line` to be sent, returning false if the connection is gone
+    fn send_line(&self, line: Vec<u8>) -> bool {
+        self.outgoing.send(line).is_ok()
+    }
+
+    fn send(&self, message: &Message) -> bool {
+        self.send_line(encode(message))
+    }
+}
+
+/// Passes everything sent over `stream` on to `sender` from another thread, tagged with `site`
+fn read_messages(stream: TcpStream, site: u16, sender: Sender<Incoming>) {
+    std::thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let message = line
+                .map_err(|e| e.to_string())
+                .and_then(|l| serde_json::from_str(&l).map_err(|e| e.to_string()));
+            match message {
+                Ok(message) => {
+                    if sender.send(Incoming::Message(site, message)).is_err() {
+                        return;
+                    }
+                }
+                Err(e) => {
+                    let problem = format!("stopped listening to {} - {}", site, e);
+                    let _ = sender.send(Incoming::News(problem));
+                    break;
+                }
+            }
+        }
+        let _ = sender.send(Incoming::Left(site));
+    });
+}
+
+enum Role {
+    Host {
+        guests: Vec<(u16, Connection)>,
+        next_site: u16,
+        address: SocketAddr,
+    },
+    Guest {
+        host: Connection,
+    },
+}
+
+/// A board shared over the network. One person hosts it and the others join. Changes go through
+/// the host, which decides what order everyone makes them in
+pub struct Session {
+    role: Role,
+    incoming: Receiver<Incoming>,
+    sender: Sender<Incoming>,
+    /// What everyone else has been told about
+    shared: Tracker,
+    /// Guests don't share anything until the host's board has replaced theirs
+    welcomed: bool,
+    /// Who this is on the board, the host being 0
+    site: u16,
+    /// Where everyone else is, by site
+    others: BTreeMap<u16, Presence>,
+    /// What everyone else was last told about where this person is
+    presence: Option<Presence>,
+    following: Option<u16>,
+    /// If the host hung up, for guests
+    closed: bool,
+    /// What's worth telling but hasn't been yet, see `Received::news`
+    news: Vec<String>,
+}
+
+impl Session {
+    /// Shares `world` with anyone connecting to `address`, like `0.0.0.0:7878`
+    pub fn host<A: ToSocketAddrs>(address: A, world: &mut World) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let (sender, incoming) = channel();
+        let accepted = sender.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if accepted.send(Incoming::Joined(stream)).is_err() {
+                    return;
+                }
+            }
+        });
+        world.claim_ids(0);
+        let mut to_return = Self {
+            role: Role::Host {
+                guests: vec![],
+                next_site: 1,
+                address,
+            },
+            incoming,
+            sender,
+            shared: Tracker::new(),
+            welcomed: true,
+            site: 0,
+            others: BTreeMap::new(),
+            presence: None,
+            following: None,
+            closed: false,
+            news: vec![],
+        };
+        // there's nobody to tell yet, but from now on changes are told
+        to_return.share_changes(world);
+        Ok(to_return)
+    }
+
+    /// Joins the board hosted at `address`, which replaces what's on `world` once it arrives
+    pub fn join<A: ToSocketAddrs>(address: A) -> std::io::Result<Self> {
+        let host = TcpStream::connect(address)?;
+        host.set_nodelay(true)?;
+        let (sender, incoming) = channel();
+        read_messages(host.try_clone()?, 0, sender.clone());
+        Ok(Self {
+            role: Role::Guest {
+                host: Connection::new(host, sender.clone())?,
+            },
+            incoming,
+            sender,
+            shared: Tracker::new(),
+            welcomed: false,
+            site: 0,
+            others: BTreeMap::new(),
+            presence: None,
+            following: None,
+            closed: false,
+            news: vec![],
+        })
+    }
+
+    pub fn is_guest(&self) -> bool {
+        matches!(self.role, Role::Guest { .. })
+    }
+
+    /// Where guests can connect, for the host
+    pub fn address(&self) -> Option<SocketAddr> {
+        match &self.role {
+            Role::Host { address, .. } => Some(*address),
+            Role::Guest { .. } => None,
+        }
+    }
+
+    /// If the host went away, which ends the session for guests
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Tells everyone else about whatever changed on `world` since it was last shared
+    pub fn share_changes(&mut self, world: &World) {
+        if !self.welcomed {
+            return;
+        }
+        for operation in self.shared.changes(world) {
+            match &mut self.role {
+                Role::Host { guests, .. } => {
+                    broadcast(guests, &Message::Apply(operation), &mut self.news)
+                }
+                Role::Guest { host } => {
+                    if !host.send(&Message::Propose(operation)) {
+                        self.news.push(String::from("couldn't reach the host"));
+                        self.closed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Tells everyone else where this person, called `name`, is pointing and looking, if that
+    /// changed since they were last told
+    pub fn share_presence(&mut self, name: &str, cursor: Option<FixedV2>, view: Bookmark) {
+        if !self.welcomed {
+            return;
+        }
+        let presence = Presence {
+            name: String::from(name),
+            color: color_for(self.site),
+            cursor,
+            view,
+        };
+        if self.presence.as_ref() == Some(&presence) {
+            return;
+        }
+        let message = Message::Presence {
+            site: self.site,
+            presence: presence.clone(),
+        };
+        match &mut self.role {
+            Role::Host { guests, .. } => broadcast(guests, &message, &mut self.news),
+            Role::Guest { host } => {
+                if !host.send(&message) {
+                    self.news.push(String::from("couldn't reach the host"));
+                    self.closed = true;
+                }
+            }
+        }
+        self.presence = Some(presence);
+    }
+
+    /// Everyone else on the board
+    pub fn others(&self) -> impl Iterator<Item = &Presence> {
+        self.others.values()
+    }
+
+    /// Who's being followed, if anyone
+    pub fn following(&self) -> Option<&Presence> {
+        self.others.get(&self.following?)
+    }
+
+    /// Starts following the next person along, or stops after the last one
+    pub fn follow_next(&mut self) {
+        self.following = match self.following {
+            Some(site) => self.others.range(site + 1..).next(),
+            None => self.others.iter().next(),
+        }
+        .map(|(&site, _)| site);
+    }
+
+    pub fn stop_following(&mut self) {
+        self.following = None;
+    }
+
+    /// Makes the changes everyone else made on `world`, and notes where they are
+    pub fn receive(&mut self, world: &mut World) -> Received {
+        let mut received = Received::default();
+        while let Ok(incoming) = self.incoming.try_recv() {
+            match incoming {
+                Incoming::Joined(stream) => self.welcome(stream, world),
+                Incoming::Message(_, Message::Welcome { site }) => {
+                    // the host's board replaces this one, seen from the same place
+                    let camera = world.camera.clone();
+                    *world = World::new();
+                    world.camera = camera;
+                    world.claim_ids(site);
+                    self.shared = Tracker::new();
+                    self.welcomed = true;
+                    self.site = site;
+                    received.board_changed = true;
+                }
+                Incoming::Message(site, Message::Propose(operation)) => {
+                    if self.is_guest() {
+                        continue;
+                    }
+                    if let Err(e) = apply(&operation, world) {
+                        let problem = format!("couldn't make a change from {} - {}", site, e);
+                        self.news.push(problem);
+                        continue;
+                    }
+                    self.shared.remember(&operation, world);
+                    if let Role::Host { guests, .. } = &mut self.role {
+                        broadcast(guests, &Message::Apply(operation), &mut self.news);
+                    }
+                    received.board_changed = true;
+                }
+                Incoming::Message(_, Message::Apply(operation)) => {
+                    if let Err(e) = apply(&operation, world) {
+                        self.news.push(format!("couldn't make a change - {}", e));
+                    }
+                    self.shared.remember(&operation, world);
+                    received.board_changed = true;
+                }
+                Incoming::Message(from, Message::Presence { site, presence }) => {
+                    // guests only speak for themselves
+                    let site = if self.is_guest() { site } else { from };
+                    if site == self.site {
+                        continue;
+                    }
+                    if let Role::Host { guests, .. } = &mut self.role {
+                        let message = Message::Presence {
+                            site,
+                            presence: presence.clone(),
+                        };
+                        broadcast(guests, &message, &mut self.news);
+                    }
+                    self.others.insert(site, presence);
+                    received.others_moved = true;
+                }
+                Incoming::Message(_, Message::Gone { site }) => {
+                    self.forget(site);
+                    received.others_moved = true;
+                }
+                Incoming::Left(site) => {
+                    match &mut self.role {
+                        Role::Host { guests, .. } => {
+                            guests.retain(|(s, _)| *s != site);
+                            broadcast(guests, &Message::Gone { site }, &mut self.news);
+                            self.news.push(format!("guest {} left", site));
+                        }
+                        Role::Guest { .. } => self.closed = true,
+                    }
+                    self.forget(site);
+                    received.others_moved = true;
+                }
+                Incoming::News(news) => self.news.push(news),
+            }
+        }
+        received.news = std::mem::take(&mut self.news);
+        received
+    }
+
+    fn forget(&mut self, site: u16) {
+        self.others.remove(&site);
+        if self.following == Some(site) {
+            self.following = None;
+        }
+    }
+
+    /// Sends someone who just joined everything on the board
+    fn welcome(&mut self, stream: TcpStream, world: &World) {
+        let (guests, next_site) = match &mut self.role {
+            Role::Host {
+                guests, next_site, ..
+            } => (guests, next_site),
+            Role::Guest { .. } => return,
+        };
+        let site = match next_site.checked_add(1) {
+            Some(next) => std::mem::replace(next_site, next),
+            None => {
+                // dropping the stream hangs up on them
+                let problem = "couldn't welcome a guest - there's no room for anyone else";
+                self.news.push(String::from(problem));
+                return;
+            }
+        };
+        let mut everything = vec![Message::Welcome { site }];
+        everything.extend(everything_on(world).into_iter().map(Message::Apply));
+        // and where everyone already here is
+        let here = self
+            .presence
+            .iter()
+            .map(|p| (&0, p))
+            .chain(self.others.iter());
+        everything.extend(here.map(|(&site, presence)| Message::Presence {
+            site,
+            presence: presence.clone(),
+        }));
+        let sender = self.sender.clone();
+        let connected = stream.set_nodelay(true).and_then(|_| {
+            let reader = stream.try_clone()?;
+            Ok((reader, Connection::new(stream, sender.clone())?))
+        });
+        match connected {
+            Ok((reader, guest)) => {
+                for message in everything.iter() {
+                    guest.send(message);
+                }
+                read_messages(reader, site, sender);
+                guests.push((site, guest));
+                self.news.push(format!("guest {} joined", site));
+            }
+            Err(e) => self.news.push(format!("couldn't welcome a guest - {}", e)),
+        }
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        // the reading threads have their own handles on the connections, so closing ours isn't
+        // enough to hang up
+        let connections: Vec<&Connection> = match &self.role {
+            Role::Host { guests, .. } => guests.iter().map(|(_, c)| c).collect(),
+            Role::Guest { host } => vec![host],
+        };
+        for connection in connections {
+            let _ = connection.stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// Sends `message` to all `guests`, noting any that were lost in `news`
+fn broadcast(guests: &mut Vec<(u16, Connection)>, message: &Message, news: &mut Vec<String>) {
+    let line = encode(message);
+    // guests that can't be reached are dropped here, their reader notices them leaving
+    guests.retain(|(site, guest)| {
+        let sent = guest.send_line(line.clone());
+        if !sent {
+            news.push(format!("lost guest {}", site));
+        }
+        sent
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use explain_core::line::{SavedLine, Stroke};
+    use explain_core::shape::Shape;
+    use explain_core::util::*;
+    use explain_core::world::{LayerId, ObjectId};
+    use explain_core::zooming::ZoomTransform;
+    use serde_json::Value;
+    use std::time::Instant;
+
+    /// Receives on `session` until `done` or a couple of seconds pass
+    fn receive_until(
+        session: &mut Session,
+        world: &mut World,
+        done: impl Fn(&Session, &World) -> bool,
+    ) {
+        let start = Instant::now();
+        while !done(session, world) {
+            assert!(start.elapsed() < Duration::from_secs(2), "gave up waiting");
+            session.receive(world);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Joins the board `host` shares, receiving on both until the host has welcomed the guest
+    fn join(host: &mut Session, host_world: &mut World, world: &mut World) -> Session {
+        let mut guest = Session::join(host.address().unwrap()).unwrap();
+        let start = Instant::now();
+        while !guest.welcomed {
+            assert!(start.elapsed() < Duration::from_secs(2), "gave up waiting");
+            host.receive(host_world);
+            guest.receive(world);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        guest
+    }
+
+    fn layer_names(world: &World) -> Vec<String> {
+        world.layers().map(|l| l.name.clone()).collect()
+    }
+
+    fn line_at(x: f64) -> Box<dyn Shape> {
+        Box::new(SavedLine {
+            points: vec![P2::new(0.0, 0.0), P2::new(0.0, 1.0)],
+            transform: ZoomTransform::new(1.0, V2f64::new(x, 0.0)),
+            stroke: Stroke {
+                color: [0.0, 0.0, 0.0],
+                width: 1.0,
+            },
+        })
+    }
+
+    fn move_to(world: &mut World, id: ObjectId, x: f64) {
+        let moved = ZoomTransform::new(1.0, V2f64::new(x, 0.0));
+        world.object_mut(id).unwrap().set_transform(moved);
+    }
+
+    /// A layer's id and name, and what's on it
+    type LayerContents = (LayerId, String, Vec<(ObjectId, Value)>);
+
+    /// Everything sharing a board keeps the same for everyone, bottom to top
+    fn contents(world: &World) -> Vec<LayerContents> {
+        world
+            .layers()
+            .map(|l| {
+                let objects = l.objects().map(|(id, o)| (id, o.save())).collect();
+                (l.id, l.name.clone(), objects)
+            })
+            .collect()
+    }
+
+    /// Receives on both until the host's board is `done` and the guest's matches it
+    fn receive_until_same(
+        (host, host_world): (&mut Session, &mut World),
+        (guest, guest_world): (&mut Session, &mut World),
+        done: impl Fn(&World) -> bool,
+    ) {
+        let start = Instant::now();
+        while !done(host_world) || contents(host_world) != contents(guest_world) {
+            assert!(start.elapsed() < Duration::from_secs(2), "gave up waiting");
+            host.receive(host_world);
+            guest.receive(guest_world);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn layers_are_shared_through_the_host() {
+        let mut host_world = World::new();
+        host_world.current_layer_mut().name = String::from("Background");
+        let mut host = Session::host("127.0.0.1:0", &mut host_world).unwrap();
+        let mut guest_world = World::new();
+        guest_world.add_layer();
+        let mut guest = Session::join(host.address().unwrap()).unwrap();
+        assert!(guest.is_guest() && !host.is_guest());
+
+        // joining replaces what the guest had
+        receive_until(&mut host, &mut host_world, |h, _| match &h.role {
+            Role::Host { guests, .. } => !guests.is_empty(),
+            Role::Guest { .. } => false,
+        });
+        receive_until(&mut guest, &mut guest_world, |_, w| {
+            layer_names(w) == ["Background"]
+        });
+
+        // a layer added by the guest gets an id of its own, and comes back from the host
+        guest_world.add_layer();
+        guest_world.current_layer_mut().name = String::from("Guest's");
+        guest.share_changes(&guest_world);
+        host_world.add_layer();
+        host.share_changes(&host_world);
+        receive_until(&mut host, &mut host_world, |_, w| w.layers().count() == 3);
+        receive_until(&mut guest, &mut guest_world, |_, w| w.layers().count() == 3);
+        let mut host_names = layer_names(&host_world);
+        let mut guest_names = layer_names(&guest_world);
+        host_names.sort();
+        guest_names.sort();
+        assert_eq!(host_names, guest_names);
+
+        drop(host);
+        receive_until(&mut guest, &mut guest_world, |g, _| g.is_closed());
+    }
+
+    #[test]
+    fn hosting_keeps_ids_of_deleted_objects_used() {
+        let mut world = World::new();
+        world.add(line_at(0.0));
+        let last = world.add(line_at(1.0));
+        world.remove(last);
+        let _host = Session::host("127.0.0.1:0", &mut world).unwrap();
+        let added = world.add(line_at(2.0));
+        assert!(added.0 > last.0, "{:?} was used before", added);
+    }
+
+    #[test]
+    fn guests_are_turned_away_once_the_sites_run_out() {
+        let mut host_world = World::new();
+        let mut host = Session::host("127.0.0.1:0", &mut host_world).unwrap();
+        if let Role::Host { next_site, .. } = &mut host.role {
+            *next_site = u16::MAX;
+        }
+        let mut guest_world = World::new();
+        let mut guest = Session::join(host.address().unwrap()).unwrap();
+        let mut news = vec![];
+        let start = Instant::now();
+        while news.is_empty() {
+            assert!(start.elapsed() < Duration::from_secs(2), "gave up waiting");
+            news = host.receive(&mut host_world).news;
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(
+            news,
+            ["couldn't welcome a guest - there's no room for anyone else"]
+        );
+        receive_until(&mut guest, &mut guest_world, |g, _| g.is_closed());
+        assert!(!guest.welcomed);
+    }
+
+    #[test]
+    fn objects_changed_at_the_same_time_end_up_the_same() {
+        let mut host_world = World::new();
+        let mut host = Session::host("127.0.0.1:0", &mut host_world).unwrap();
+        let a = host_world.add(line_at(0.0));
+        let b = host_world.add(line_at(1.0));
+        host.share_changes(&host_world);
+        let mut guest_world = World::new();
+        let mut guest = join(&mut host, &mut host_world, &mut guest_world);
+        receive_until(&mut guest, &mut guest_world, |_, w| w.object_count() == 2);
+
+        // both move the same line, and the guest moves one the host removes
+        move_to(&mut host_world, a, 5.0);
+        host_world.remove(b);
+        host.share_changes(&host_world);
+        move_to(&mut guest_world, a, 9.0);
+        move_to(&mut guest_world, b, 9.0);
+        let c = guest_world.add(line_at(2.0));
+        guest.share_changes(&guest_world);
+
+        // the host got the guest's changes last, so theirs win, but removing is for good
+        let moved = |w: &World| w.object(a).map(|o| o.transform().clone());
+        receive_until_same(
+            (&mut host, &mut host_world),
+            (&mut guest, &mut guest_world),
+            |w| {
+                w.object(c).is_some()
+                    && moved(w) == Some(ZoomTransform::new(1.0, V2f64::new(9.0, 0.0)))
+            },
+        );
+        assert!(host_world.object(b).is_none());
+        assert_eq!(host_world.object_count(), 2);
+    }
+
+    #[test]
+    fn reordering_at_the_same_time_ends_up_the_same() {
+        let mut host_world = World::new();
+        let mut host = Session::host("127.0.0.1:0", &mut host_world).unwrap();
+        let bottom = host_world.current_layer().id;
+        let a = host_world.add(line_at(0.0));
+        let b = host_world.add(line_at(1.0));
+        let c = host_world.add(line_at(2.0));
+        let top = host_world.add_layer();
+        host.share_changes(&host_world);
+        let mut guest_world = World::new();
+        let mut guest = join(&mut host, &mut host_world, &mut guest_world);
+        receive_until(&mut guest, &mut guest_world, |_, w| {
+            w.object_count() == 3 && w.layers().count() == 2
+        });
+
+        // the host adds a layer and brings a line to the front, while the guest removes the
+        // layer the host's goes above and sends another line to the back
+        host_world.bring_to_front(&[a]);
+        let added = host_world.add_layer();
+        host.share_changes(&host_world);
+        guest_world.send_to_back(&[c]);
+        guest_world.step_layer(1);
+        assert_eq!(guest_world.current_layer().id, top);
+        assert!(guest_world.remove_current_layer());
+        guest.share_changes(&guest_world);
+
+        let order = |w: &World| -> Vec<ObjectId> { w.objects().map(|(id, _)| id).collect() };
+        receive_until_same(
+            (&mut host, &mut host_world),
+            (&mut guest, &mut guest_world),
+            |w| order(w) == [c, a, b] && w.layers().count() == 2,
+        );
+        let layers: Vec<LayerId> = host_world.layers().map(|l| l.id).collect();
+        assert_eq!(layers, [bottom, added]);
+    }
+
+    #[test]
+    fn everyone_sees_where_everyone_else_is() {
+        let (mut host_world, mut ada_world, mut bob_world) =
+            (World::new(), World::new(), World::new());
+        let mut host = Session::host("127.0.0.1:0", &mut host_world).unwrap();
+        let view = |x| {
+            let camera = ZoomTransform::new(1.0, V2f64::new(x, 0.0));
+            Bookmark::new(String::new(), camera, V2f64::new(800.0, 600.0))
+        };
+        host.share_presence("Host", None, view(0.0));
+        let mut ada = join(&mut host, &mut host_world, &mut ada_world);
+        receive_until(&mut ada, &mut ada_world, |a, _| a.others().count() == 1);
+        let mut bob = join(&mut host, &mut host_world, &mut bob_world);
+        receive_until(&mut bob, &mut bob_world, |b, _| b.others().count() == 1);
+
+        // a guest's cursor reaches the host and the other guest through it
+        let cursor = Some(FixedV2::from_f64(V2f64::new(3.0, 4.0)));
+        ada.share_presence("Ada", cursor, view(10.0));
+        bob.share_presence("Bob", None, view(20.0));
+        receive_until(&mut host, &mut host_world, |h, _| h.others().count() == 2);
+        receive_until(&mut bob, &mut bob_world, |b, _| b.others().count() == 2);
+        let seen = bob.others().find(|p| p.name == "Ada").unwrap();
+        assert_eq!(seen.cursor, cursor);
+        let colors: Vec<[f32; 3]> = host.others().map(|p| p.color).collect();
+        assert_ne!(colors[0], colors[1]);
+
+        // following goes through everyone and then stops
+        bob.follow_next();
+        assert_eq!(bob.following().unwrap().name, "Host");
+        bob.follow_next();
+        assert_eq!(bob.following().unwrap().view, view(10.0));
+        bob.follow_next();
+        assert!(bob.following().is_none());
+
+        // whoever leaves is forgotten
+        bob.follow_next();
+        bob.follow_next();
+        drop(ada);
+        receive_until(&mut host, &mut host_world, |h, _| h.others().count() == 1);
+        receive_until(&mut bob, &mut bob_world, |b, _| b.others().count() == 1);
+        assert!(bob.following().is_none());
+    }
+}
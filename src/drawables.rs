@@ -0,0 +1,67 @@
+use crate::object_kinds;
+use crate::{ExplainObject, Shaders};
+use explain_core::shape::Shape;
+use explain_core::world::ObjectId;
+use explain_core::zooming::ZoomTransform;
+use std::collections::{HashMap, HashSet};
+
+/// What draws each object of a board with gl. They're made from the objects in the world the
+/// first time they're seen and again whenever they're replaced, and follow them when they move
+pub struct Drawables {
+    /// With the revision each was made from. `None` if it couldn't be made, so it isn't tried
+    /// again every frame
+    made: HashMap<ObjectId, (u64, Option<Box<dyn ExplainObject>>)>,
+    /// In the order they're drawn
+    order: Vec<ObjectId>,
+}
+
+impl Drawables {
+    pub fn new() -> Self {
+        Self {
+            made: HashMap::new(),
+            order: vec![],
+        }
+    }
+
+    /// Catches up with `objects`, given with their revisions like `World::visible_revisions`
+    /// does and in the order they're drawn. Anything that isn't in them any more is dropped
+    pub fn update<'a, I>(&mut self, objects: I)
+    where
+        I: Iterator<Item = (ObjectId, u64, &'a dyn Shape)>,
+    {
+        self.order.clear();
+        for (id, revision, shape) in objects {
+            self.order.push(id);
+            match self.made.get_mut(&id) {
+                Some((made_from, drawable)) if *made_from == revision => {
+                    if let Some(drawable) = drawable {
+                        if drawable.transform() != shape.transform() {
+                            drawable.set_transform(shape.transform().clone());
+                        }
+                    }
+                }
+                _ => {
+                    let drawable = object_kinds::drawable(shape)
+                        .map_err(|e| println!("couldn't draw {:?} - {}", id, e))
+                        .ok();
+                    self.made.insert(id, (revision, drawable));
+                }
+            }
+        }
+        // the ids in `order` are all made, so there's more made only if some are gone
+        if self.made.len() > self.order.len() {
+            let seen: HashSet<ObjectId> = self.order.iter().copied().collect();
+            self.made.retain(|id, _| seen.contains(id));
+        }
+    }
+
+    pub fn draw(&self, shaders: &Shaders, projection: &na::Matrix4<f32>, camera: &ZoomTransform) {
+        let drawables = self
+            .order
+            .iter()
+            .filter_map(|id| self.made.get(id)?.1.as_ref());
+        for drawable in drawables {
+            drawable.draw(shaders, projection, camera);
+        }
+    }
+}
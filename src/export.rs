@@ -0,0 +1,381 @@
+use crate::drawables::Drawables;
+use crate::Shaders;
+use explain_core::navigation::fit_board;
+use explain_core::recording::Recording;
+use explain_core::recording::Replay;
+use explain_core::shape::Canvas;
+use explain_core::util::*;
+use explain_core::zooming::ZoomTransform;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// What the camera does in an exported replay
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CameraPath {
+    /// Moves the way it did while the board was drawn
+    Recorded,
+    /// Stays put, showing the whole finished board
+    Board,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ExportOptions {
+    pub width: u32,
+    pub height: u32,
+    /// Frames per second of the recording, which plays at the speed it was drawn at
+    pub fps: u32,
+    pub camera: CameraPath,
+    /// Red, green and blue from 0 to 1
+    pub background: [f32; 3],
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 600,
+            fps: 15,
+            camera: CameraPath::Recorded,
+            background: [1.0, 1.0, 1.0],
+        }
+    }
+}
+
+impl ExportOptions {
+    /// Options from command line arguments like `--size 640x480 --fps 30 --camera board`, with
+    /// anything not mentioned left at its default
+    pub fn parse(args: &[&str]) -> Result<Self, String> {
+        let mut to_return = Self::default();
+        let mut args = args.iter();
+        while let Some(&flag) = args.next() {
+            let value = *args
+                .next()
+                .ok_or_else(|| format!("{} needs a value", flag))?;
+            match flag {
+                "--size" => {
+                    let (width, height) = value
+                        .split_once('x')
+                        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                        .filter(|&(w, h)| w > 0 && h > 0)
+                        .ok_or_else(|| format!("{} isn't a size like 800x600", value))?;
+                    to_return.width = width;
+                    to_return.height = height;
+                }
+                "--fps" => {
+                    to_return.fps = value
+                        .parse()
+                        .ok()
+                        .filter(|&fps| fps > 0)
+                        .ok_or_else(|| format!("{} isn't a frame rate", value))?;
+                }
+                "--camera" => {
+                    to_return.camera = match value {
+                        "recorded" => CameraPath::Recorded,
+                        "board" => CameraPath::Board,
+                        _ => {
+                            return Err(format!(
+                                "the camera can be recorded or board, not {}",
+                                value
+                            ))
+                        }
+                    }
+                }
+                _ => return Err(format!("{} isn't an export option", flag)),
+            }
+        }
+        Ok(to_return)
+    }
+}
+
+/// When each frame of a recording lasting `duration` seconds is taken, the last one right at
+/// the end
+fn frame_times(duration: f64, fps: u32) -> Vec<f64> {
+    let count = (duration * fps as f64).ceil() as u32;
+    (0..=count)
+        .map(|i| (i as f64 / fps as f64).min(duration))
+        .collect()
+}
+
+/// Somewhere to draw that isn't on the screen
+struct Framebuffer {
+    framebuffer: gl::types::GLuint,
+    texture: gl::types::GLuint,
+    width: u32,
+    height: u32,
+}
+
+impl Framebuffer {
+    fn new(width: u32, height: u32) -> Result<Self, String> {
+        let mut to_return = Self {
+            framebuffer: 0,
+            texture: 0,
+            width,
+            height,
+        };
+        unsafe {
+            gl::GenTextures(1, &mut to_return.texture);
+            gl::BindTexture(gl::TEXTURE_2D, to_return.texture);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA as i32,
+                width as i32,
+                height as i32,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null(),
+            );
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::GenFramebuffers(1, &mut to_return.framebuffer);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, to_return.framebuffer);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                to_return.texture,
+                0,
+            );
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(format!("can't draw offscreen at {}x{}", width, height));
+            }
+        }
+        Ok(to_return)
+    }
+
+    /// Makes drawing go here instead of to the screen
+    fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+            gl::Viewport(0, 0, self.width as i32, self.height as i32);
+        }
+    }
+
+    /// What was drawn, top row first
+    fn read(&self) -> RgbaImage {
+        let mut pixels = vec![0u8; (self.width * self.height * 4) as usize];
+        unsafe {
+            gl::ReadPixels(
+                0,
+                0,
+                self.width as i32,
+                self.height as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut gl::types::GLvoid,
+            );
+        }
+        let image = RgbaImage::from_raw(self.width, self.height, pixels).unwrap();
+        // gl reads from the bottom up
+        image::imageops::flip_vertical(&image)
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::DeleteFramebuffers(1, &self.framebuffer);
+            gl::DeleteTextures(1, &self.texture);
+        }
+    }
+}
+
+/// Where the frames go
+enum Output {
+    Gif(GifEncoder<File>),
+    /// A numbered png per frame in a folder, for putting together with something like ffmpeg
+    Frames(PathBuf),
+}
+
+impl Output {
+    /// A gif if `path` ends in `.gif`, otherwise a folder of frames
+    fn create(path: &Path) -> Result<Self, String> {
+        let extension = path.extension().and_then(|e| e.to_str());
+        if matches!(extension, Some(e) if e.eq_ignore_ascii_case("gif")) {
+            let file = File::create(path).map_err(|e| e.to_string())?;
+            let mut encoder = GifEncoder::new_with_speed(file, 10);
+            encoder
+                .set_repeat(Repeat::Infinite)
+                .map_err(|e| e.to_string())?;
+            Ok(Output::Gif(encoder))
+        } else {
+            std::fs::create_dir_all(path).map_err(|e| e.to_string())?;
+            Ok(Output::Frames(path.to_path_buf()))
+        }
+    }
+
+    fn add(&mut self, index: usize, image: RgbaImage, fps: u32) -> Result<(), String> {
+        match self {
+            Output::Gif(encoder) => {
+                let delay = Delay::from_numer_denom_ms(1000, fps);
+                encoder
+                    .encode_frame(Frame::from_parts(image, 0, 0, delay))
+                    .map_err(|e| e.to_string())
+            }
+            Output::Frames(folder) => image
+                .save(folder.join(format!("frame-{:05}.png", index)))
+                .map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Plays `recording` back frame by frame, putting what `draw` makes of each one, given the
+/// replay and the camera to look through, into an animated gif at `path` or a folder of frames.
+/// Returns how many frames there were and what couldn't be replayed
+fn export_frames(
+    recording: Recording,
+    path: &Path,
+    options: &ExportOptions,
+    mut draw: impl FnMut(&Replay, &ZoomTransform) -> RgbaImage,
+) -> Result<(usize, Vec<String>), String> {
+    let screen_size = V2f64::new(options.width as f64, options.height as f64);
+    let times = frame_times(recording.duration(), options.fps);
+    let mut replay = Replay::new(recording);
+    let whole_board = match options.camera {
+        CameraPath::Board => {
+            replay.seek(replay.duration());
+            fit_board(replay.world(), screen_size)
+        }
+        CameraPath::Recorded => None,
+    };
+
+    let mut output = Output::create(path)?;
+    for (index, &time) in times.iter().enumerate() {
+        replay.seek(time);
+        let camera = whole_board
+            .clone()
+            .or_else(|| replay.camera_for(screen_size))
+            .unwrap_or_else(ZoomTransform::does_nothing);
+        output.add(index, draw(&replay, &camera), options.fps)?;
+    }
+    Ok((times.len(), replay.problems().to_vec()))
+}
+
+/// Draws `recording` being played back frame by frame with gl without showing it, into an
+/// animated gif at `path` or a folder of frames. Returns how many frames there were and what
+/// couldn't be replayed
+pub fn export_replay(
+    shaders: &Shaders,
+    recording: Recording,
+    path: &Path,
+    options: &ExportOptions,
+) -> Result<(usize, Vec<String>), String> {
+    let projection = na::Orthographic3::new(
+        0.0,
+        options.width as f32,
+        options.height as f32,
+        0.0,
+        -1.0,
+        1.0,
+    );
+    let framebuffer = Framebuffer::new(options.width, options.height)?;
+    let (mut board, mut drawing) = (Drawables::new(), Drawables::new());
+    export_frames(recording, path, options, |replay, camera| {
+        framebuffer.bind();
+        unsafe {
+            let [r, g, b] = options.background;
+            gl::ClearColor(r, g, b, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+        board.update(replay.world().visible_revisions());
+        board.draw(shaders, projection.as_matrix(), camera);
+        drawing.update(replay.drawing());
+        drawing.draw(shaders, projection.as_matrix(), camera);
+        framebuffer.read()
+    })
+}
+
+/// Like `export_replay`, but painting the frames without gl, for when there's no display to
+/// get gl from
+pub fn paint_replay(
+    recording: Recording,
+    path: &Path,
+    options: &ExportOptions,
+) -> Result<(usize, Vec<String>), String> {
+    export_frames(recording, path, options, |replay, camera| {
+        let mut canvas = Canvas::new(options.width, options.height, options.background);
+        let board = replay.world().visible_objects().map(|(_, o)| o);
+        let drawing = replay.drawing().map(|(_, _, o)| o);
+        for object in board.chain(drawing) {
+            object.paint(&mut canvas, camera);
+        }
+        canvas.image
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use explain_core::line::{SavedLine, Stroke};
+    use explain_core::recording::Recorder;
+    use explain_core::world::World;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn options_come_from_the_command_line() {
+        let options =
+            ExportOptions::parse(&["--fps", "30", "--size", "320x200", "--camera", "board"])
+                .unwrap();
+        assert_eq!(
+            options,
+            ExportOptions {
+                width: 320,
+                height: 200,
+                fps: 30,
+                camera: CameraPath::Board,
+                ..ExportOptions::default()
+            }
+        );
+        assert_eq!(ExportOptions::parse(&[]).unwrap(), ExportOptions::default());
+        assert!(ExportOptions::parse(&["--size", "0x10"]).is_err());
+        assert!(ExportOptions::parse(&["--fps"]).is_err());
+        assert!(ExportOptions::parse(&["--camera", "sideways"]).is_err());
+    }
+
+    #[test]
+    fn frames_cover_the_whole_recording() {
+        assert_eq!(frame_times(0.0, 10), [0.0]);
+        assert_eq!(frame_times(0.25, 10), [0.0, 0.1, 0.2, 0.25]);
+    }
+
+    #[test]
+    fn replays_are_painted_without_gl() {
+        let start = Instant::now();
+        let mut world = World::new();
+        let mut recorder = Recorder::new(&mut world, start);
+        world.add(Box::new(SavedLine {
+            points: vec![P2::new(0.0, 50.0), P2::new(100.0, 50.0)],
+            transform: ZoomTransform::does_nothing(),
+            stroke: Stroke {
+                color: [0.0, 0.0, 0.0],
+                width: 4.0,
+            },
+        }));
+        recorder.board_changed(&mut world, start + Duration::from_secs(1));
+
+        let folder = std::env::temp_dir().join(format!("explain-frames-{}", std::process::id()));
+        let options = ExportOptions {
+            width: 100,
+            height: 100,
+            fps: 2,
+            ..ExportOptions::default()
+        };
+        let (frames, problems) = paint_replay(world.recording.clone(), &folder, &options).unwrap();
+        assert_eq!(frames, 3);
+        assert!(problems.is_empty(), "{:?}", problems);
+        let first = image::open(folder.join("frame-00000.png"))
+            .unwrap()
+            .to_rgba8();
+        let last = image::open(folder.join("frame-00002.png"))
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(first.get_pixel(50, 50).0, [255, 255, 255, 255]);
+        assert_eq!(last.get_pixel(50, 50).0, [0, 0, 0, 255]);
+        std::fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -0,0 +1,129 @@
+use std::time::{Duration, Instant};
+
+/// Longest time step handed to animations, so coming back from being idle doesn't make
+/// everything jump
+const MAX_DT: f64 = 0.25;
+
+/// Decides when frames get drawn: only when something changed or is animating, and then no more
+/// often than the display can show them
+pub struct FrameScheduler {
+    frame_time: Duration,
+    /// If swapping buffers already waits for the display, so frames don't have to be paced here
+    vsync: bool,
+    last_frame: Instant,
+    redraw_requested: bool,
+    /// If there was nothing to draw since the last frame, so the time since then was spent waiting
+    /// rather than animating
+    idled: bool,
+}
+
+impl FrameScheduler {
+    pub fn new(fps: u32, vsync: bool) -> Self {
+        Self {
+            frame_time: Duration::from_secs(1) / fps,
+            vsync,
+            last_frame: Instant::now(),
+            redraw_requested: true,
+            idled: false,
+        }
+    }
+
+    /// Something on screen changed, so the next frame has to be drawn
+    pub fn request_redraw(&mut self) {
+        self.redraw_requested = true;
+    }
+
+    fn time_until_next_frame(&self, now: Instant) -> Duration {
+        if self.vsync {
+            Duration::from_secs(0)
+        } else {
+            (self.last_frame + self.frame_time).saturating_duration_since(now)
+        }
+    }
+
+    /// How long to wait for events before drawing the next frame. `None` means there's nothing to
+    /// draw, so wait for as long as it takes for an event to come in
+    pub fn wait_time(&mut self, animating: bool, now: Instant) -> Option<Duration> {
+        if self.redraw_requested || animating {
+            Some(self.time_until_next_frame(now))
+        } else {
+            self.idled = true;
+            None
+        }
+    }
+
+    /// If a frame should be drawn right now
+    pub fn frame_due(&self, animating: bool, now: Instant) -> bool {
+        (self.redraw_requested || animating)
+            && self.time_until_next_frame(now) == Duration::from_secs(0)
+    }
+
+    /// Marks the start of drawing a frame, returning how many seconds passed since the last one
+    pub fn start_frame(&mut self, now: Instant) -> f64 {
+        // after being idle, animations start from where they were a frame ago
+        if self.idled {
+            self.last_frame = now.checked_sub(self.frame_time).unwrap_or(now);
+            self.idled = false;
+        }
+        let dt = (now - self.last_frame).as_secs_f64();
+        self.last_frame = now;
+        self.redraw_requested = false;
+        dt.min(MAX_DT)
+    }
+}
+
+/// Milliseconds to wait for events, rounded up so a wait shorter than a millisecond still sleeps
+/// instead of spinning
+pub fn timeout_millis(wait: Duration) -> u32 {
+    wait.as_micros().div_ceil(1000) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idles_until_something_happens() {
+        let start = Instant::now();
+        let mut scheduler = FrameScheduler::new(60, false);
+        scheduler.start_frame(start);
+        assert_eq!(scheduler.wait_time(false, start), None);
+        assert!(!scheduler.frame_due(false, start + Duration::from_secs(1)));
+
+        scheduler.request_redraw();
+        let later = start + Duration::from_millis(5);
+        let wait = scheduler.wait_time(false, later).unwrap();
+        assert_eq!(wait, Duration::from_secs(1) / 60 - Duration::from_millis(5));
+        assert!(!scheduler.frame_due(false, later));
+        assert!(scheduler.frame_due(false, later + wait));
+    }
+
+    #[test]
+    fn keeps_drawing_while_animating() {
+        let start = Instant::now();
+        let mut scheduler = FrameScheduler::new(60, true);
+        scheduler.start_frame(start);
+        assert!(scheduler.frame_due(true, start));
+        assert!(!scheduler.frame_due(false, start));
+        let dt = scheduler.start_frame(start + Duration::from_secs(10));
+        assert_eq!(dt, MAX_DT);
+    }
+
+    #[test]
+    fn first_frame_after_idling_is_a_normal_step() {
+        let start = Instant::now();
+        let mut scheduler = FrameScheduler::new(60, true);
+        scheduler.start_frame(start);
+        assert_eq!(scheduler.wait_time(false, start), None);
+        scheduler.request_redraw();
+        let dt = scheduler.start_frame(start + Duration::from_secs(10));
+        assert!((dt - 1.0 / 60.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn short_waits_still_sleep() {
+        assert_eq!(timeout_millis(Duration::from_micros(300)), 1);
+        assert_eq!(timeout_millis(Duration::from_millis(4)), 4);
+        assert_eq!(timeout_millis(Duration::from_micros(4001)), 5);
+    }
+}
@@ -1,3 +1,4 @@
+use explain_core::zooming::ZoomTransform;
 use gl;
 use nalgebra as na;
 use std;
@@ -41,31 +42,30 @@ impl ShaderProgram {
             );
         }
     }
-    pub fn write_mat3(&self, name: &str, mat: &na::Matrix3<f32>) {
+    pub fn write_point2(&self, name: &str, point: &na::Point2<f32>) {
         unsafe {
-            gl::UniformMatrix3fv(
+            gl::Uniform2fv(
                 self.get_location(name),
                 1,
-                gl::FALSE,
-                mat.as_slice().as_ptr(),
+                [point.x, point.y].as_ptr(), // TODO figure out how to slice into pointer without copy like the matrices
             );
         }
     }
-    pub fn write_point2(&self, name: &str, point: &na::Point2<f32>) {
+    pub fn write_vec2(&self, name: &str, vec: &na::Vector2<f32>) {
         unsafe {
             gl::Uniform2fv(
                 self.get_location(name),
                 1,
-                [point.x, point.y].as_ptr(), // TODO figure out how to slice into pointer without copy like the matrices
+                [vec.x, vec.y].as_ptr(),
             );
         }
     }
-    pub fn write_vec2(&self, name: &str, vec: &na::Vector2<f32>) {
+    pub fn write_vec3(&self, name: &str, vec: &na::Vector3<f32>) {
         unsafe {
-            gl::Uniform2fv(
+            gl::Uniform3fv(
                 self.get_location(name),
                 1,
-                [vec.x, vec.y].as_ptr(),
+                [vec.x, vec.y, vec.z].as_ptr(),
             );
         }
     }
@@ -74,6 +74,13 @@ impl ShaderProgram {
             gl::Uniform1f(self.get_location(name), f);
         }
     }
+    /// Writes `transform` to the `offset` and `scale` uniforms. Intended to be processed in the
+    /// vertex shader like: `vec2 newPosition = scale*Position + offset;`
+    pub fn write_transform(&self, transform: &ZoomTransform) {
+        let offset = transform.transform_point(na::Point2::origin()).coords;
+        self.write_vec2("offset", &na::convert(offset));
+        self.write_float("scale", transform.scale() as f32);
+    }
     pub fn from_shaders(shaders: &[Shader]) -> Result<ShaderProgram, String> {
         let program_id = unsafe { gl::CreateProgram() };
 
@@ -19,6 +19,11 @@ pub mod vertex_attribs {
         size: 2 * std::mem::size_of::<f32>() as i32,
         components: 2,
     };
+    pub const COLOR4_F32: VertexAttrib = VertexAttrib {
+        gl_type: gl::FLOAT,
+        size: 4 * std::mem::size_of::<f32>() as i32,
+        components: 4,
+    };
 }
 
 pub struct VertexData<T: Clone> {
@@ -41,6 +46,16 @@ impl<T: Clone> Clone for VertexData<T> {
     }
 }
 
+impl<T: Clone> Drop for VertexData<T> {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.ebo);
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
 impl<T: Clone> VertexData<T> {
     /// The type T should be a single struct or a tuple of types that each vertex should have
     /// attached to it.
@@ -102,6 +117,11 @@ impl<T: Clone> VertexData<T> {
         }
         self.deactivate();
     }
+    /// Removes all of the vertices, e.g. to replace them with new ones
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.indices.clear();
+    }
     pub fn data(&self) -> &Vec<T> {
         &self.data
     }
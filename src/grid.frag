@@ -0,0 +1,35 @@
+#version 330 core
+
+out vec4 Color;
+in vec2 screenPosition;
+
+uniform vec2 phase; // where a point on every level of the grid is on the screen
+uniform float spacing; // pixels between the finest grid lines
+uniform float minSpacing;
+uniform float levels; // each level has lines twice as far apart as the one before
+uniform float dots;
+uniform vec3 color;
+uniform float alpha;
+
+// lines get stronger the further apart they are, so they fade in and out smoothly while zooming
+float strength(float apart)
+{
+    float minor = clamp(apart / minSpacing - 1.0, 0.0, 1.0);
+    float major = clamp(apart / (4.0 * minSpacing) - 1.0, 0.0, 1.0);
+    return 0.4 * minor + 0.6 * major;
+}
+
+void main()
+{
+    float strongest = 0.0;
+    for (float level = 0.0; level < levels; level += 1.0) {
+        float apart = spacing * exp2(level);
+        vec2 offset = mod(screenPosition - phase, apart);
+        vec2 toLine = min(offset, apart - offset);
+        bool on = dots > 0.5 ? length(toLine) < 1.2 : min(toLine.x, toLine.y) < 0.5;
+        if (on) {
+            strongest = max(strongest, strength(apart));
+        }
+    }
+    Color = vec4(color, alpha * strongest);
+}
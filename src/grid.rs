@@ -0,0 +1,260 @@
+use crate::gl_vertices::*;
+use crate::settings::Settings;
+use crate::Shaders;
+use explain_core::fixed::{Fixed, FixedV2};
+use explain_core::geometry::Rect;
+use explain_core::util::*;
+use explain_core::world::{ObjectId, World};
+use explain_core::zooming::ZoomTransform;
+use serde::{Deserialize, Serialize};
+
+/// Fewest pixels between grid lines. Grid lines are a power of two world units apart, the
+/// smallest one at least this far apart on the screen
+pub const MIN_SPACING: f64 = 12.0;
+/// How many times the spacing doubles between the finest grid lines and the ones drawn the
+/// strongest, which also get numbers on the rulers
+pub const COARSEST_LEVEL: i32 = 3;
+/// How close something has to be to snap onto it, in pixels
+const SNAP_DISTANCE: f64 = 8.0;
+const GRID_COLOR: [f32; 3] = [0.0, 0.0, 0.0];
+/// How opaque the strongest grid lines are. Dots cover less, so they're drawn darker
+const LINE_ALPHA: f32 = 0.25;
+const DOT_ALPHA: f32 = 0.7;
+
+/// What the grid behind the board looks like
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GridStyle {
+    Hidden,
+    Dots,
+    Lines,
+}
+
+impl GridStyle {
+    /// The style after this one when cycling through them
+    pub fn next(self) -> Self {
+        match self {
+            GridStyle::Hidden => GridStyle::Dots,
+            GridStyle::Dots => GridStyle::Lines,
+            GridStyle::Lines => GridStyle::Hidden,
+        }
+    }
+}
+
+/// Help with lining things up, which starts out like the settings and can be toggled from there
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Guides {
+    pub grid: GridStyle,
+    /// Snaps to the grid while it's showing, and to the edges and centers of other objects
+    pub snapping: bool,
+    pub rulers: bool,
+}
+
+impl Guides {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            grid: settings.grid,
+            snapping: settings.snapping,
+            rulers: settings.rulers,
+        }
+    }
+}
+
+/// The power of two world units between the finest grid lines seen through a camera with
+/// `scale`, kept where the coarsest lines can still be found exactly in fixed point
+pub fn spacing_exponent(scale: f64) -> i32 {
+    ((MIN_SPACING * scale).log2().ceil() as i32).clamp(-127, 63 - COARSEST_LEVEL)
+}
+
+/// Where a point on every level of the grid is on the screen, up and to the left of the screen.
+/// Found in fixed point, so the grid stays put however far from the origin the camera is
+pub fn phase(camera: &ZoomTransform) -> P2f64 {
+    let exponent = spacing_exponent(camera.scale()) + COARSEST_LEVEL;
+    let corner = camera.transform_point_exact(P2f64::origin());
+    camera.inverse_transform_exact(FixedV2 {
+        x: corner.x.floor_to_power_of_two(exponent),
+        y: corner.y.floor_to_power_of_two(exponent),
+    })
+}
+
+/// Moves things onto the grid and onto the edges and centers of other objects close by
+pub struct Snapper {
+    /// World x of every vertical edge or center line that can be snapped to
+    xs: Vec<Fixed>,
+    ys: Vec<Fixed>,
+    /// Exponent of the grid spacing, if snapping to the grid
+    grid: Option<i32>,
+    tolerance: Fixed,
+}
+
+impl Snapper {
+    /// Snaps to what `guides` says to, as it looks through the camera of `world`. Objects in
+    /// `ignoring` aren't snapped to, like the ones being moved
+    pub fn new(world: &World, guides: &Guides, ignoring: &[ObjectId]) -> Self {
+        let mut to_return = Self {
+            xs: vec![],
+            ys: vec![],
+            grid: None,
+            tolerance: Fixed::from_f64(SNAP_DISTANCE * world.camera.scale()),
+        };
+        if !guides.snapping {
+            return to_return;
+        }
+        if guides.grid != GridStyle::Hidden {
+            to_return.grid = Some(spacing_exponent(world.camera.scale()));
+        }
+        let boxes = world
+            .visible_objects()
+            .filter(|(id, _)| !ignoring.contains(id))
+            .filter_map(|(_, o)| o.bounding_box());
+        for rect in boxes {
+            let center = rect.center();
+            to_return
+                .xs
+                .extend([rect.min.x, center.x, rect.max.x].iter());
+            to_return
+                .ys
+                .extend([rect.min.y, center.y, rect.max.y].iter());
+        }
+        to_return
+    }
+
+    /// How far to move something taking up `rect` so one of its edges or its center lines up
+    /// with what's closest, along each axis
+    pub fn offset(&self, rect: &Rect) -> FixedV2 {
+        let center = rect.center();
+        FixedV2 {
+            x: snap_axis(
+                &[rect.min.x, center.x, rect.max.x],
+                &self.xs,
+                self.grid,
+                self.tolerance,
+            ),
+            y: snap_axis(
+                &[rect.min.y, center.y, rect.max.y],
+                &self.ys,
+                self.grid,
+                self.tolerance,
+            ),
+        }
+    }
+
+    pub fn point(&self, point: FixedV2) -> FixedV2 {
+        point
+            + self.offset(&Rect {
+                min: point,
+                max: point,
+            })
+    }
+}
+
+/// The smallest move along one axis that puts one of `positions` on one of `lines` or on the grid,
+/// zero if nothing is closer than `tolerance`
+fn snap_axis(positions: &[Fixed], lines: &[Fixed], grid: Option<i32>, tolerance: Fixed) -> Fixed {
+    let mut best: Option<Fixed> = None;
+    for &position in positions {
+        let on_grid = grid.map(|exponent| position.round_to_power_of_two(exponent));
+        for &line in lines.iter().chain(on_grid.iter()) {
+            let by = line - position;
+            let closer = match best {
+                Some(b) => by.abs() < b.abs(),
+                None => true,
+            };
+            if closer && by.abs() <= tolerance {
+                best = Some(by);
+            }
+        }
+    }
+    best.unwrap_or(Fixed::ZERO)
+}
+
+/// Draws the grid over the whole screen, in a shader so it costs the same however many lines
+/// there are
+pub struct Grid {
+    quad: VertexData<P2>,
+}
+
+impl Grid {
+    pub fn new() -> Self {
+        use vertex_attribs::*;
+        Self {
+            quad: VertexData::new(vec![POINT2_F32]),
+        }
+    }
+
+    pub fn draw(
+        &mut self,
+        shaders: &Shaders,
+        projection: &na::Matrix4<f32>,
+        camera: &ZoomTransform,
+        screen_size: V2,
+        style: GridStyle,
+    ) {
+        if style == GridStyle::Hidden {
+            return;
+        }
+        self.quad.clear();
+        self.quad.append(
+            &mut vec![
+                P2::new(0.0, 0.0),
+                P2::new(screen_size.x, 0.0),
+                P2::new(screen_size.x, screen_size.y),
+                P2::new(0.0, screen_size.y),
+            ],
+            &mut vec![0, 1, 2, 0, 2, 3],
+            false,
+        );
+        let spacing = 2f64.powi(spacing_exponent(camera.scale())) / camera.scale();
+        let program = &shaders.grid;
+        program.set_used();
+        program.write_mat4("projection", projection);
+        program.write_point2("phase", &na::convert(phase(camera)));
+        program.write_float("spacing", spacing as f32);
+        program.write_float("minSpacing", MIN_SPACING as f32);
+        program.write_float("levels", (COARSEST_LEVEL + 1) as f32);
+        let (dots, alpha) = match style {
+            GridStyle::Dots => (1.0, DOT_ALPHA),
+            _ => (0.0, LINE_ALPHA),
+        };
+        program.write_float("dots", dots);
+        program.write_vec3("color", &GRID_COLOR.into());
+        program.write_float("alpha", alpha);
+        self.quad.draw();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_lines_are_spread_out_at_any_zoom() {
+        for &scale in [1.0, 0.3, 7.0, 1e-20, 1e12].iter() {
+            let pixels = 2f64.powi(spacing_exponent(scale)) / scale;
+            assert!((MIN_SPACING..MIN_SPACING * 2.0).contains(&pixels));
+        }
+        // every level of the grid lines up with its phase
+        let camera = ZoomTransform::new(0.5, V2f64::new(1e9 + 3.3, -7.0));
+        let phase = camera.transform_point_exact(phase(&camera));
+        let coarsest = spacing_exponent(0.5) + COARSEST_LEVEL;
+        assert_eq!(phase.x.floor_to_power_of_two(coarsest), phase.x);
+        assert!(phase.x <= camera.transform_point_exact(P2f64::origin()).x);
+    }
+
+    #[test]
+    fn snapping_picks_the_closest_line_in_reach() {
+        let f = Fixed::from_f64;
+        let tolerance = f(1.0);
+        let lines = [f(10.0), f(20.5)];
+        assert_eq!(snap_axis(&[f(9.5)], &lines, None, tolerance), f(0.5));
+        assert_eq!(snap_axis(&[f(12.0)], &lines, None, tolerance), Fixed::ZERO);
+        // the far edge is closer to a line than the near one is
+        assert_eq!(
+            snap_axis(&[f(9.0), f(20.0)], &lines, None, tolerance),
+            f(0.5)
+        );
+        // grid lines every 4
+        assert_eq!(snap_axis(&[f(13.0)], &lines, Some(2), tolerance), f(-1.0));
+        assert_eq!(snap_axis(&[f(10.25)], &lines, Some(2), tolerance), f(-0.25));
+    }
+}
@@ -0,0 +1,13 @@
+#version 330 core
+
+layout (location = 0) in vec2 Position; // in screen coordinates
+
+out vec2 screenPosition;
+
+uniform mat4 projection;
+
+void main()
+{
+    gl_Position = projection * vec4(Position, 0.0, 1.0);
+    screenPosition = Position;
+}
@@ -0,0 +1,741 @@
+use crate::gl_vertices::*;
+use crate::grid::{spacing_exponent, COARSEST_LEVEL};
+use crate::text::Text;
+use crate::tools::{Shape, ToolKind};
+use crate::{ExplainObject, Shaders};
+use explain_core::fixed::Fixed;
+use explain_core::line::Stroke;
+use explain_core::recording::Replay;
+use explain_core::util::*;
+use explain_core::world::Layer;
+use explain_core::zooming::*;
+
+/// Colors new lines can be drawn in
+pub const PALETTE: [[f32; 3]; 6] = [
+    [0.0, 0.0, 0.0],
+    [0.85, 0.15, 0.15],
+    [0.95, 0.55, 0.1],
+    [0.15, 0.6, 0.2],
+    [0.15, 0.35, 0.85],
+    [0.55, 0.2, 0.7],
+];
+/// Widths new lines can be drawn with, in pixels
+pub const WIDTHS: [f32; 4] = [1.0, 2.0, 4.0, 8.0];
+const TOOLS: [(ToolKind, &str); 8] = [
+    (ToolKind::Pen, "Pen"),
+    (ToolKind::Text, "Text"),
+    (ToolKind::Eraser, "Erase"),
+    (ToolKind::Select, "Select"),
+    (ToolKind::Pan, "Pan"),
+    (ToolKind::Shape(Shape::StraightLine), "Line"),
+    (ToolKind::Shape(Shape::Rectangle), "Rect"),
+    (ToolKind::Shape(Shape::Ellipse), "Ellipse"),
+];
+
+const BAR_HEIGHT: f32 = 32.0;
+const PADDING: f32 = 4.0;
+const BUTTON_SIZE: f32 = BAR_HEIGHT - 2.0 * PADDING;
+const TOOL_BUTTON_WIDTH: f32 = 56.0;
+/// Space between the tools, colors and widths
+const GROUP_GAP: f32 = 8.0;
+const STATUS_HEIGHT: f32 = 24.0;
+const TIMELINE_HEIGHT: f32 = 8.0;
+const TOP_RULER_HEIGHT: f32 = 18.0;
+/// Wider than the top one, so the numbers on it fit
+const LEFT_RULER_WIDTH: f32 = 44.0;
+/// Length of the marks for the finest grid lines on the rulers, which get longer for coarser ones
+const TICK_LENGTH: f32 = 3.0;
+/// How much smaller than the font texture text is drawn, which has 64 pixel tall letters
+const TEXT_SCALE: f64 = 0.22;
+const RULER_TEXT_SCALE: f64 = 0.16;
+/// Corners of the arrow drawn for other people's cursors, from its tip
+const CURSOR_ARROW: [(f32, f32); 3] = [(0.0, 0.0), (0.0, 18.0), (13.0, 13.0)];
+/// Where the name next to someone's cursor starts, from the tip of the arrow
+const CURSOR_NAME_OFFSET: (f32, f32) = (14.0, 16.0);
+
+const BAR_COLOR: [f32; 4] = [0.92, 0.92, 0.92, 0.95];
+const BUTTON_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const PICKED_COLOR: [f32; 4] = [0.7, 0.82, 1.0, 1.0];
+const ICON_COLOR: [f32; 4] = [0.2, 0.2, 0.2, 1.0];
+/// The part of the timeline that's been replayed already
+const PLAYED_COLOR: [f32; 4] = [0.15, 0.35, 0.85, 1.0];
+
+/// Something on the toolbar that can be clicked
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Button {
+    Tool(ToolKind),
+    Color([f32; 3]),
+    Width(f32),
+}
+
+/// A rectangle on the screen, in pixels
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Area {
+    pub min: P2,
+    pub max: P2,
+}
+
+impl Area {
+    fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            min: P2::new(x, y),
+            max: P2::new(x + width, y + height),
+        }
+    }
+
+    pub fn contains(&self, p: P2) -> bool {
+        self.min.x <= p.x && p.x < self.max.x && self.min.y <= p.y && p.y < self.max.y
+    }
+
+    /// The same area with `by` pixels taken off every side
+    fn shrunk(&self, by: f32) -> Area {
+        Area {
+            min: self.min + V2::new(by, by),
+            max: self.max - V2::new(by, by),
+        }
+    }
+}
+
+/// Every button on the toolbar, left to right along the top of the screen
+pub fn toolbar() -> Vec<(Button, Area)> {
+    let mut to_return = vec![];
+    let mut x = PADDING;
+    for &(tool, _) in TOOLS.iter() {
+        to_return.push((
+            Button::Tool(tool),
+            Area::new(x, PADDING, TOOL_BUTTON_WIDTH, BUTTON_SIZE),
+        ));
+        x += TOOL_BUTTON_WIDTH + PADDING;
+    }
+    x += GROUP_GAP;
+    for &color in PALETTE.iter() {
+        let area = Area::new(x, PADDING, BUTTON_SIZE, BUTTON_SIZE);
+        to_return.push((Button::Color(color), area));
+        x += BUTTON_SIZE + PADDING;
+    }
+    x += GROUP_GAP;
+    for &width in WIDTHS.iter() {
+        let area = Area::new(x, PADDING, BUTTON_SIZE, BUTTON_SIZE);
+        to_return.push((Button::Width(width), area));
+        x += BUTTON_SIZE + PADDING;
+    }
+    to_return
+}
+
+fn toolbar_area() -> Area {
+    let right = toolbar().last().map(|(_, a)| a.max.x).unwrap_or(0.0);
+    Area::new(0.0, 0.0, right + PADDING, BAR_HEIGHT)
+}
+
+fn status_area(screen_size: V2) -> Area {
+    Area::new(
+        0.0,
+        screen_size.y - STATUS_HEIGHT,
+        screen_size.x,
+        STATUS_HEIGHT,
+    )
+}
+
+/// The bar showing how far along a replay is, just above the status bar
+fn timeline_area(screen_size: V2) -> Area {
+    Area::new(
+        PADDING,
+        screen_size.y - STATUS_HEIGHT - TIMELINE_HEIGHT - PADDING,
+        screen_size.x - 2.0 * PADDING,
+        TIMELINE_HEIGHT,
+    )
+}
+
+/// If clicking the screen point `p` during a replay jumps around in it. The space around the
+/// timeline counts too, so it's easy to hit
+pub fn on_timeline(p: P2, screen_size: V2) -> bool {
+    let timeline = timeline_area(screen_size);
+    let around = Area {
+        min: timeline.min - V2::new(0.0, PADDING),
+        max: timeline.max + V2::new(0.0, PADDING),
+    };
+    around.contains(p) || status_area(screen_size).contains(p)
+}
+
+/// How far along the timeline the screen x coordinate `x` is, from 0 at the start to 1 at the end
+pub fn timeline_fraction(x: f32, screen_size: V2) -> f64 {
+    let timeline = timeline_area(screen_size);
+    ((x - timeline.min.x) / (timeline.max.x - timeline.min.x)).clamp(0.0, 1.0) as f64
+}
+
+fn top_ruler_area(screen_size: V2) -> Area {
+    Area::new(0.0, BAR_HEIGHT, screen_size.x, TOP_RULER_HEIGHT)
+}
+
+fn left_ruler_area(screen_size: V2) -> Area {
+    let top = BAR_HEIGHT + TOP_RULER_HEIGHT;
+    Area::new(
+        0.0,
+        top,
+        LEFT_RULER_WIDTH,
+        screen_size.y - STATUS_HEIGHT - top,
+    )
+}
+
+pub fn button_at(p: P2) -> Option<Button> {
+    toolbar()
+        .into_iter()
+        .find(|(_, area)| area.contains(p))
+        .map(|(button, _)| button)
+}
+
+/// If the screen point `p` is on the overlay, so clicking there shouldn't reach the board
+pub fn covers(p: P2, screen_size: V2, rulers: bool) -> bool {
+    let on_rulers = rulers
+        && (top_ruler_area(screen_size).contains(p) || left_ruler_area(screen_size).contains(p));
+    toolbar_area().contains(p) || status_area(screen_size).contains(p) || on_rulers
+}
+
+/// Where a grid line crosses a ruler
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Tick {
+    /// How far along the ruler, in pixels
+    at: f32,
+    /// Which level of the grid the line is on, the coarsest being `COARSEST_LEVEL`
+    level: i32,
+    /// Where the line is in the world
+    value: Fixed,
+}
+
+/// The grid lines crossing the screen from 0 to `length` pixels along one axis. `vertical` picks
+/// the y axis, for the ruler on the left
+fn ticks(camera: &ZoomTransform, vertical: bool, length: f32) -> Vec<Tick> {
+    let exponent = spacing_exponent(camera.scale());
+    let corner = camera.transform_point_exact(P2f64::origin());
+    let start = if vertical { corner.y } else { corner.x };
+    let step = Fixed::from_f64(2f64.powi(exponent));
+    let mut value = start.floor_to_power_of_two(exponent);
+    let mut to_return = vec![];
+    loop {
+        let at = ((value - start).to_f64() / camera.scale()) as f32;
+        if at > length {
+            break;
+        }
+        let level = (1..=COARSEST_LEVEL)
+            .take_while(|l| value.floor_to_power_of_two(exponent + l) == value)
+            .last()
+            .unwrap_or(0);
+        if at >= 0.0 {
+            to_return.push(Tick { at, level, value });
+        }
+        value += step;
+    }
+    to_return
+}
+
+/// Digits after the point needed to write the numbers on the rulers, which are multiples of 2 to
+/// the power of `exponent`. Exact for halves and quarters, close enough after that
+fn ruler_digits(exponent: i32) -> usize {
+    let exact = (-exponent).max(0) as usize;
+    let close = (-exponent as f64 * 2f64.log10()).ceil().max(0.0) as usize + 1;
+    exact.min(close)
+}
+
+/// Someone else's mouse on a shared board, drawn as an arrow with their name next to it
+pub struct RemoteCursor<'a> {
+    /// Where the tip of the arrow is on the screen
+    pub at: P2,
+    pub color: [f32; 3],
+    pub name: &'a str,
+}
+
+/// What the status bar at the bottom of the screen shows
+pub struct Status<'a> {
+    pub tool: ToolKind,
+    pub camera: &'a ZoomTransform,
+    /// Where the mouse is on the screen
+    pub cursor: P2,
+    /// Where new objects go
+    pub layer: &'a Layer,
+    pub object_count: usize,
+    /// If everything on the board has been saved, or if something is still being made
+    pub saved: bool,
+    /// Name of whoever's view is being followed on a shared board
+    pub following: Option<&'a str>,
+    /// If narration is being recorded
+    pub narrating: bool,
+    /// Why the last thing tried didn't happen
+    pub notice: Option<&'a str>,
+}
+
+/// The zoom as a percentage, 100% being one world unit per pixel
+fn format_zoom(scale: f64) -> String {
+    let percent = 100.0 / scale;
+    if (0.01..1e6).contains(&percent) {
+        format!("{:.0}%", percent)
+    } else {
+        format!("{:.1e}%", percent)
+    }
+}
+
+/// Seconds as minutes and seconds, like `1:05`
+fn format_time(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// What the status bar shows during a replay
+fn replay_text(replay: &Replay) -> String {
+    format!(
+        "{}   {} / {}   {}x{}",
+        if replay.is_playing() {
+            "Playing"
+        } else {
+            "Paused"
+        },
+        format_time(replay.time()),
+        format_time(replay.duration()),
+        replay.speed(),
+        replay
+            .problems()
+            .last()
+            .map(|p| format!("   {}", p))
+            .unwrap_or_default(),
+    )
+}
+
+impl<'a> Status<'a> {
+    pub fn text(&self) -> String {
+        // enough digits to tell apart neighbouring pixels
+        let digits = (-self.camera.scale().log10()).ceil().clamp(0.0, 38.0) as usize;
+        let cursor = self.camera.transform_point_exact(na::convert(self.cursor));
+        format!(
+            "{}   Zoom {}   x {}  y {}   {}{}   {} object{}   {}{}{}{}",
+            TOOLS
+                .iter()
+                .find(|(t, _)| *t == self.tool)
+                .map(|(_, name)| *name)
+                .unwrap_or(""),
+            format_zoom(self.camera.scale()),
+            cursor.x.to_decimal(digits),
+            cursor.y.to_decimal(digits),
+            self.layer.name,
+            match (self.layer.visible, self.layer.locked) {
+                (true, false) => "",
+                (true, true) => " (locked)",
+                (false, false) => " (hidden)",
+                (false, true) => " (hidden, locked)",
+            },
+            self.object_count,
+            if self.object_count == 1 { "" } else { "s" },
+            if self.saved {
+                "Saved"
+            } else {
+                "Unsaved changes"
+            },
+            match self.following {
+                Some(name) => format!("   Following {}", name),
+                None => String::new(),
+            },
+            if self.narrating { "   Narrating" } else { "" },
+            match self.notice {
+                Some(notice) => format!("   {}", notice),
+                None => String::new(),
+            },
+        )
+    }
+}
+
+#[repr(C)]
+#[derive(Clone)]
+struct UiVertex {
+    position: P2,
+    color: [f32; 4],
+}
+
+/// Overlay drawn on top of the board in screen space, so the camera doesn't move it
+pub struct Hud {
+    quads: VertexData<UiVertex>,
+    labels: Vec<Text>,
+    status: Text,
+    status_text: String,
+    /// Numbers on the rulers, reused between frames since making text is slow
+    ruler_labels: Vec<(String, Text)>,
+    /// Other people's cursors, drawn separately so the rest of the overlay covers them
+    cursor_quads: VertexData<UiVertex>,
+    /// Names next to other people's cursors, reused the same way
+    cursor_labels: Vec<(String, Text)>,
+}
+
+/// A transform putting text from the font texture at `screen_point`
+fn text_transform(screen_point: P2) -> ZoomTransform {
+    ZoomTransform::new(TEXT_SCALE, na::convert(screen_point.coords))
+}
+
+/// Makes the text in `pool` say `texts`, remaking only the ones that changed and adding more if
+/// there aren't enough
+fn reuse_labels(pool: &mut Vec<(String, Text)>, texts: impl Iterator<Item = String>) {
+    for (i, text) in texts.enumerate() {
+        if i == pool.len() {
+            pool.push((String::new(), Text::new(P2::origin())));
+        }
+        let (shown, label) = &mut pool[i];
+        if text != *shown {
+            label.set_text(&text);
+            *shown = text;
+        }
+    }
+}
+
+impl Hud {
+    pub fn new() -> Self {
+        use vertex_attribs::*;
+        let labels = toolbar()
+            .iter()
+            .zip(TOOLS.iter())
+            .map(|((_, area), (_, name))| {
+                let mut label = Text::new(P2::origin());
+                label.set_text(name);
+                label.set_transform(text_transform(area.min + V2::new(PADDING, 2.0)));
+                label
+            })
+            .collect();
+        Self {
+            quads: VertexData::new(vec![POINT2_F32, COLOR4_F32]),
+            labels,
+            status: Text::new(P2::origin()),
+            status_text: String::new(),
+            ruler_labels: vec![],
+            cursor_quads: VertexData::new(vec![POINT2_F32, COLOR4_F32]),
+            cursor_labels: vec![],
+        }
+    }
+
+    /// Draws where everyone else on a shared board is pointing. Goes before `draw`, which covers
+    /// them up
+    pub fn draw_cursors(
+        &mut self,
+        shaders: &Shaders,
+        projection: &na::Matrix4<f32>,
+        cursors: &[RemoteCursor],
+    ) {
+        let mut vertices = vec![];
+        let mut indices = vec![];
+        reuse_labels(
+            &mut self.cursor_labels,
+            cursors.iter().map(|c| String::from(c.name)),
+        );
+        for (cursor, (_, label)) in cursors.iter().zip(self.cursor_labels.iter_mut()) {
+            let first = vertices.len() as u32;
+            let [r, g, b] = cursor.color;
+            for &(x, y) in CURSOR_ARROW.iter() {
+                vertices.push(UiVertex {
+                    position: cursor.at + V2::new(x, y),
+                    color: [r, g, b, 1.0],
+                });
+            }
+            indices.extend([0, 1, 2].iter().map(|i| first + i));
+
+            let origin = cursor.at + V2::new(CURSOR_NAME_OFFSET.0, CURSOR_NAME_OFFSET.1);
+            label.set_transform(text_transform(origin));
+            if let Some(bounds) = label.bounding_box() {
+                let size = bounds.size();
+                let first = vertices.len() as u32;
+                // a pale version of their color, so the name can be read on top of it
+                let tint = [0.6 + 0.4 * r, 0.6 + 0.4 * g, 0.6 + 0.4 * b, 0.9];
+                let (width, height) = (size.x as f32 + 2.0 * PADDING, size.y as f32);
+                for &(x, y) in [(0.0, 0.0), (width, 0.0), (width, height), (0.0, height)].iter() {
+                    vertices.push(UiVertex {
+                        position: origin + V2::new(x - PADDING, y),
+                        color: tint,
+                    });
+                }
+                indices.extend([0, 1, 2, 0, 2, 3].iter().map(|i| first + i));
+            }
+        }
+        self.cursor_quads.clear();
+        self.cursor_quads.append(&mut vertices, &mut indices, false);
+        shaders.ui.set_used();
+        shaders.ui.write_mat4("projection", projection);
+        self.cursor_quads.draw();
+        let screen = ZoomTransform::does_nothing();
+        for (_, label) in self.cursor_labels.iter().take(cursors.len()) {
+            label.draw(shaders, projection, &screen);
+        }
+    }
+
+    /// Draws the timeline and status of a replay, instead of the usual overlay
+    pub fn draw_replay(
+        &mut self,
+        shaders: &Shaders,
+        projection: &na::Matrix4<f32>,
+        screen_size: V2,
+        replay: &Replay,
+    ) {
+        let timeline = timeline_area(screen_size);
+        let fraction = if replay.duration() > 0.0 {
+            (replay.time() / replay.duration()) as f32
+        } else {
+            1.0
+        };
+        let played = Area {
+            min: timeline.min,
+            max: P2::new(
+                timeline.min.x + (timeline.max.x - timeline.min.x) * fraction,
+                timeline.max.y,
+            ),
+        };
+        let mut vertices = vec![];
+        let mut indices = vec![];
+        for &(area, color) in [
+            (status_area(screen_size), BAR_COLOR),
+            (timeline, BAR_COLOR),
+            (played, PLAYED_COLOR),
+        ]
+        .iter()
+        {
+            let first = vertices.len() as u32;
+            for &(x, y) in [
+                (area.min.x, area.min.y),
+                (area.max.x, area.min.y),
+                (area.max.x, area.max.y),
+                (area.min.x, area.max.y),
+            ]
+            .iter()
+            {
+                vertices.push(UiVertex {
+                    position: P2::new(x, y),
+                    color,
+                });
+            }
+            indices.extend([0, 1, 2, 0, 2, 3].iter().map(|i| first + i));
+        }
+        self.quads.clear();
+        self.quads.append(&mut vertices, &mut indices, false);
+        shaders.ui.set_used();
+        shaders.ui.write_mat4("projection", projection);
+        self.quads.draw();
+        self.draw_status(shaders, projection, screen_size, replay_text(replay));
+    }
+
+    /// Writes `text` on the status bar
+    fn draw_status(
+        &mut self,
+        shaders: &Shaders,
+        projection: &na::Matrix4<f32>,
+        screen_size: V2,
+        text: String,
+    ) {
+        if text != self.status_text {
+            self.status.set_text(&text);
+            self.status_text = text;
+        }
+        let status_origin = status_area(screen_size).min + V2::new(PADDING, 2.0);
+        self.status.set_transform(text_transform(status_origin));
+        self.status
+            .draw(shaders, projection, &ZoomTransform::does_nothing());
+    }
+
+    pub fn draw(
+        &mut self,
+        shaders: &Shaders,
+        projection: &na::Matrix4<f32>,
+        screen_size: V2,
+        stroke: Stroke,
+        rulers: bool,
+        status: &Status,
+    ) {
+        let mut vertices = vec![];
+        let mut indices = vec![];
+        let mut quad = |area: Area, color: [f32; 4]| {
+            let first = vertices.len() as u32;
+            for &(x, y) in [
+                (area.min.x, area.min.y),
+                (area.max.x, area.min.y),
+                (area.max.x, area.max.y),
+                (area.min.x, area.max.y),
+            ]
+            .iter()
+            {
+                vertices.push(UiVertex {
+                    position: P2::new(x, y),
+                    color,
+                });
+            }
+            indices.extend([0, 1, 2, 0, 2, 3].iter().map(|i| first + i));
+        };
+
+        // numbers on the rulers, as where they go and what they say
+        let mut labels: Vec<(P2, String)> = vec![];
+        if rulers {
+            let top = top_ruler_area(screen_size);
+            let left = left_ruler_area(screen_size);
+            quad(top, BAR_COLOR);
+            quad(left, BAR_COLOR);
+            let exponent = spacing_exponent(status.camera.scale()) + COARSEST_LEVEL;
+            let digits = ruler_digits(exponent);
+            for tick in ticks(status.camera, false, screen_size.x) {
+                if tick.at < left.max.x {
+                    continue;
+                }
+                let length = TICK_LENGTH * (tick.level + 1) as f32;
+                quad(
+                    Area::new(tick.at - 0.5, top.max.y - length, 1.0, length),
+                    ICON_COLOR,
+                );
+                if tick.level == COARSEST_LEVEL {
+                    let at = P2::new(tick.at + 2.0, top.min.y);
+                    labels.push((at, tick.value.to_decimal(digits)));
+                }
+            }
+            for tick in ticks(status.camera, true, left.max.y) {
+                if tick.at < left.min.y {
+                    continue;
+                }
+                let length = TICK_LENGTH * (tick.level + 1) as f32;
+                quad(
+                    Area::new(left.max.x - length, tick.at - 0.5, length, 1.0),
+                    ICON_COLOR,
+                );
+                if tick.level == COARSEST_LEVEL {
+                    let at = P2::new(left.min.x + 2.0, tick.at);
+                    labels.push((at, tick.value.to_decimal(digits)));
+                }
+            }
+        }
+        quad(toolbar_area(), BAR_COLOR);
+        quad(status_area(screen_size), BAR_COLOR);
+        for (button, area) in toolbar() {
+            let picked = match button {
+                Button::Tool(tool) => tool == status.tool,
+                Button::Color(color) => color == stroke.color,
+                Button::Width(width) => width == stroke.width,
+            };
+            quad(area, if picked { PICKED_COLOR } else { BUTTON_COLOR });
+            match button {
+                Button::Color(color) => {
+                    quad(area.shrunk(PADDING), [color[0], color[1], color[2], 1.0])
+                }
+                Button::Width(width) => {
+                    let middle = (area.min.y + area.max.y) / 2.0;
+                    let bar = Area {
+                        min: P2::new(area.min.x + PADDING, middle - width / 2.0),
+                        max: P2::new(area.max.x - PADDING, middle + width / 2.0),
+                    };
+                    quad(bar, ICON_COLOR);
+                }
+                Button::Tool(_) => {}
+            }
+        }
+
+        self.quads.clear();
+        self.quads.append(&mut vertices, &mut indices, false);
+        shaders.ui.set_used();
+        shaders.ui.write_mat4("projection", projection);
+        self.quads.draw();
+
+        self.draw_status(shaders, projection, screen_size, status.text());
+        let screen = ZoomTransform::does_nothing();
+        for label in self.labels.iter() {
+            label.draw(shaders, projection, &screen);
+        }
+        reuse_labels(
+            &mut self.ruler_labels,
+            labels.iter().map(|(_, text)| text.clone()),
+        );
+        for ((at, _), (_, label)) in labels.into_iter().zip(self.ruler_labels.iter_mut()) {
+            label.set_transform(ZoomTransform::new(RULER_TEXT_SCALE, na::convert(at.coords)));
+            label.draw(shaders, projection, &screen);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use explain_core::fixed::FixedV2;
+    use explain_core::world::World;
+
+    #[test]
+    fn clicks_find_their_buttons() {
+        for (button, area) in toolbar() {
+            assert_eq!(button_at(na::center(&area.min, &area.max)), Some(button));
+            assert!(covers(area.min, V2::new(800.0, 600.0), false));
+        }
+        assert_eq!(button_at(P2::new(400.0, 300.0)), None);
+        assert!(!covers(P2::new(400.0, 300.0), V2::new(800.0, 600.0), true));
+        assert!(covers(P2::new(400.0, 590.0), V2::new(800.0, 600.0), false));
+        assert!(!covers(P2::new(10.0, 300.0), V2::new(800.0, 600.0), false));
+        assert!(covers(P2::new(10.0, 300.0), V2::new(800.0, 600.0), true));
+    }
+
+    #[test]
+    fn the_timeline_spans_the_screen() {
+        let screen = V2::new(800.0, 600.0);
+        let middle = P2::new(400.0, screen.y - STATUS_HEIGHT - PADDING - 1.0);
+        assert!(on_timeline(middle, screen));
+        assert!(!on_timeline(P2::new(400.0, 300.0), screen));
+        assert_eq!(timeline_fraction(400.0, screen), 0.5);
+        assert_eq!(timeline_fraction(-5.0, screen), 0.0);
+        assert_eq!(format_time(65.9), "1:05");
+    }
+
+    #[test]
+    fn rulers_mark_the_grid() {
+        let camera = ZoomTransform::new(1.0, V2f64::new(-30.0, 5.0));
+        let ticks = ticks(&camera, false, 150.0);
+        let found: Vec<(f32, i32, f64)> = ticks
+            .iter()
+            .map(|t| (t.at, t.level, t.value.to_f64()))
+            .collect();
+        assert_eq!(
+            found[..4],
+            [
+                (14.0, 0, -16.0),
+                (30.0, 3, 0.0),
+                (46.0, 0, 16.0),
+                (62.0, 1, 32.0)
+            ]
+        );
+        assert_eq!(found.last(), Some(&(142.0, 0, 112.0)));
+        assert_eq!(ruler_digits(3), 0);
+        assert_eq!(ruler_digits(-2), 2);
+        assert_eq!(ruler_digits(-3), 2);
+    }
+
+    #[test]
+    fn status_shows_where_the_cursor_is_in_the_world() {
+        let camera = ZoomTransform::new(0.01, V2f64::new(-2.0, 3.0));
+        let mut world = World::new();
+        let status = Status {
+            tool: ToolKind::Pen,
+            camera: &camera,
+            cursor: P2::new(100.0, 50.0),
+            layer: world.current_layer(),
+            object_count: 1,
+            saved: true,
+            following: None,
+            narrating: false,
+            notice: None,
+        };
+        assert_eq!(
+            status.text(),
+            "Pen   Zoom 10000%   x -1.00  y 3.50   Layer 1   1 object   Saved"
+        );
+
+        // far past where f64 could tell the cursor apart from where the camera is
+        world.current_layer_mut().locked = true;
+        let deep = ZoomTransform::new(1e-20, V2f64::zeros())
+            .translated(FixedV2::from_f64(V2f64::new(1.0, 1.0)));
+        let status = Status {
+            camera: &deep,
+            cursor: P2::new(10.0, 0.0),
+            tool: ToolKind::Pen,
+            layer: world.current_layer(),
+            saved: false,
+            object_count: 0,
+            following: Some("Ada"),
+            narrating: true,
+            notice: Some("Layer 1 is locked, unlock it to draw on it"),
+        };
+        assert_eq!(
+            status.text(),
+            "Pen   Zoom 1.0e22%   x 1.00000000000000000010  y 1.00000000000000000000   Layer 1 (locked)   0 objects   Unsaved changes   Following Ada   Narrating   Layer 1 is locked, unlock it to draw on it"
+        );
+    }
+}
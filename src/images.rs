@@ -0,0 +1,120 @@
+extern crate gl;
+extern crate image;
+use crate::gl_vertices::*;
+use crate::{ExplainObject, Shaders};
+use explain_core::geometry::Rect;
+use explain_core::images::SavedImage;
+use explain_core::object_kinds::Attachments;
+use explain_core::util::*;
+use explain_core::zooming::ZoomTransform;
+use sdl2::event::Event;
+
+/// Draws a picture on the board, like a screenshot to draw over. One image pixel is one unit
+/// before the transform
+pub struct Image {
+    gl_vertices: VertexData<(P2, P2)>,
+    texture: gl::types::GLuint,
+    zoom_transform: ZoomTransform,
+    size: V2,
+}
+
+impl Image {
+    pub const KIND: &'static str = SavedImage::KIND;
+
+    /// Decodes a PNG or JPEG file that was already read into memory
+    pub fn from_bytes(file: &[u8]) -> image::ImageResult<Self> {
+        let pixels = image::load_from_memory(file)?.to_rgba8();
+        let size = V2::new(pixels.width() as f32, pixels.height() as f32);
+        let mut texture = 0;
+        unsafe {
+            gl::GenTextures(1, &mut texture);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_MIN_FILTER,
+                gl::LINEAR_MIPMAP_LINEAR as i32,
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA as i32,
+                pixels.width() as i32,
+                pixels.height() as i32,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_ptr() as *const gl::types::GLvoid,
+            );
+            gl::GenerateMipmap(gl::TEXTURE_2D);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        use vertex_attribs::*;
+        let mut gl_vertices = VertexData::new(vec![POINT2_F32, POINT2_F32]);
+        gl_vertices.append(
+            &mut vec![
+                (P2::new(0.0, 0.0), P2::new(0.0, 0.0)),
+                (P2::new(size.x, 0.0), P2::new(1.0, 0.0)),
+                (P2::new(size.x, size.y), P2::new(1.0, 1.0)),
+                (P2::new(0.0, size.y), P2::new(0.0, 1.0)),
+            ],
+            &mut vec![0, 1, 2, 0, 2, 3],
+            true,
+        );
+        Ok(Self {
+            gl_vertices,
+            texture,
+            zoom_transform: ZoomTransform::does_nothing(),
+            size,
+        })
+    }
+
+    pub fn from_saved(saved: SavedImage, attachments: &Attachments) -> Result<Self, String> {
+        let mut to_return =
+            Image::from_bytes(&saved.file(attachments)?).map_err(|e| e.to_string())?;
+        to_return.zoom_transform = saved.transform;
+        Ok(to_return)
+    }
+}
+
+impl Drop for Image {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.texture);
+        }
+    }
+}
+
+impl ExplainObject for Image {
+    fn set_transform(&mut self, z: ZoomTransform) {
+        self.zoom_transform = z;
+    }
+    fn draw(&self, shaders: &Shaders, projection: &na::Matrix4<f32>, camera: &ZoomTransform) {
+        shaders.image.set_used();
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+        }
+        shaders.image.write_mat4("projection", projection);
+        shaders
+            .image
+            .write_transform(&self.zoom_transform.relative_to(camera));
+        self.gl_vertices.draw();
+    }
+    fn process_event(&mut self, _e: &Event) -> bool {
+        false
+    }
+    fn bounding_box(&self) -> Option<Rect> {
+        let corners = vec![
+            P2f64::origin(),
+            P2f64::from(na::convert::<V2, V2f64>(self.size)),
+        ];
+        Rect::around_points(corners, &self.zoom_transform)
+    }
+
+    fn transform(&self) -> &ZoomTransform {
+        &self.zoom_transform
+    }
+}
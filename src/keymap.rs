@@ -0,0 +1,600 @@
+use crate::settings::read_or_write_default;
+use sdl2::event::Event;
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::mouse::MouseButton;
+use serde_json::{Map, Value};
+use std::path::Path;
+
+/// Everything that can be bound to a key or mouse button
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    Quit,
+    /// Hold to use the current tool, like drawing with the pen
+    UseTool,
+    /// Hold and move the mouse to pan around, whatever the current tool is
+    Pan,
+    FinishObject,
+    PenTool,
+    TextTool,
+    EraserTool,
+    SelectTool,
+    PanTool,
+    StraightLineTool,
+    RectangleTool,
+    EllipseTool,
+    DeleteSelection,
+    Copy,
+    /// Copies the selection as a picture, for programs that can't paste svg
+    CopyImage,
+    Cut,
+    Paste,
+    Duplicate,
+    BringToFront,
+    SendToBack,
+    FitBoard,
+    FitSelection,
+    ResetZoom,
+    NextObject,
+    PreviousObject,
+    AddBookmark,
+    RemoveBookmark,
+    NextBookmark,
+    PreviousBookmark,
+    MoveBookmarkEarlier,
+    MoveBookmarkLater,
+    AddLayer,
+    RemoveLayer,
+    RenameLayer,
+    LayerAbove,
+    LayerBelow,
+    MoveLayerUp,
+    MoveLayerDown,
+    ToggleLayerVisible,
+    ToggleLayerLocked,
+    MoveSelectionToLayer,
+    /// Switches between no grid, dots and lines
+    CycleGrid,
+    ToggleSnapping,
+    ToggleRulers,
+    /// Follows the view of the next person on a shared board, stopping after the last one
+    Follow,
+    Present,
+    PresentationNext,
+    PresentationPrevious,
+    StopPresenting,
+    /// Replays how the board was drawn
+    Replay,
+    ReplayPlayPause,
+    /// Skips ahead in the replay
+    ReplayForward,
+    ReplayBack,
+    ReplayFaster,
+    ReplaySlower,
+    StopReplay,
+    /// Starts or stops recording narration along with the drawing
+    Narrate,
+    ToggleWireframe,
+}
+
+/// Which actions can be used at the same time, so only bindings in the same context conflict
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Context {
+    Drawing,
+    Presenting,
+    Replaying,
+}
+
+impl Action {
+    pub const ALL: [Action; 58] = [
+        Action::Quit,
+        Action::UseTool,
+        Action::Pan,
+        Action::FinishObject,
+        Action::PenTool,
+        Action::TextTool,
+        Action::EraserTool,
+        Action::SelectTool,
+        Action::PanTool,
+        Action::StraightLineTool,
+        Action::RectangleTool,
+        Action::EllipseTool,
+        Action::DeleteSelection,
+        Action::Copy,
+        Action::CopyImage,
+        Action::Cut,
+        Action::Paste,
+        Action::Duplicate,
+        Action::BringToFront,
+        Action::SendToBack,
+        Action::FitBoard,
+        Action::FitSelection,
+        Action::ResetZoom,
+        Action::NextObject,
+        Action::PreviousObject,
+        Action::AddBookmark,
+        Action::RemoveBookmark,
+        Action::NextBookmark,
+        Action::PreviousBookmark,
+        Action::MoveBookmarkEarlier,
+        Action::MoveBookmarkLater,
+        Action::AddLayer,
+        Action::RemoveLayer,
+        Action::RenameLayer,
+        Action::LayerAbove,
+        Action::LayerBelow,
+        Action::MoveLayerUp,
+        Action::MoveLayerDown,
+        Action::ToggleLayerVisible,
+        Action::ToggleLayerLocked,
+        Action::MoveSelectionToLayer,
+        Action::CycleGrid,
+        Action::ToggleSnapping,
+        Action::ToggleRulers,
+        Action::Follow,
+        Action::Present,
+        Action::PresentationNext,
+        Action::PresentationPrevious,
+        Action::StopPresenting,
+        Action::Replay,
+        Action::ReplayPlayPause,
+        Action::ReplayForward,
+        Action::ReplayBack,
+        Action::ReplayFaster,
+        Action::ReplaySlower,
+        Action::StopReplay,
+        Action::Narrate,
+        Action::ToggleWireframe,
+    ];
+
+    /// What the action is called in the config file
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::UseTool => "use_tool",
+            Action::Pan => "pan",
+            Action::FinishObject => "finish_object",
+            Action::PenTool => "pen_tool",
+            Action::TextTool => "text_tool",
+            Action::EraserTool => "eraser_tool",
+            Action::SelectTool => "select_tool",
+            Action::PanTool => "pan_tool",
+            Action::StraightLineTool => "straight_line_tool",
+            Action::RectangleTool => "rectangle_tool",
+            Action::EllipseTool => "ellipse_tool",
+            Action::DeleteSelection => "delete_selection",
+            Action::Copy => "copy",
+            Action::CopyImage => "copy_image",
+            Action::Cut => "cut",
+            Action::Paste => "paste",
+            Action::Duplicate => "duplicate",
+            Action::BringToFront => "bring_to_front",
+            Action::SendToBack => "send_to_back",
+            Action::FitBoard => "fit_board",
+            Action::FitSelection => "fit_selection",
+            Action::ResetZoom => "reset_zoom",
+            Action::NextObject => "next_object",
+            Action::PreviousObject => "previous_object",
+            Action::AddBookmark => "add_bookmark",
+            Action::RemoveBookmark => "remove_bookmark",
+            Action::NextBookmark => "next_bookmark",
+            Action::PreviousBookmark => "previous_bookmark",
+            Action::MoveBookmarkEarlier => "move_bookmark_earlier",
+            Action::MoveBookmarkLater => "move_bookmark_later",
+            Action::AddLayer => "add_layer",
+            Action::RemoveLayer => "remove_layer",
+            Action::RenameLayer => "rename_layer",
+            Action::LayerAbove => "layer_above",
+            Action::LayerBelow => "layer_below",
+            Action::MoveLayerUp => "move_layer_up",
+            Action::MoveLayerDown => "move_layer_down",
+            Action::ToggleLayerVisible => "toggle_layer_visible",
+            Action::ToggleLayerLocked => "toggle_layer_locked",
+            Action::MoveSelectionToLayer => "move_selection_to_layer",
+            Action::CycleGrid => "cycle_grid",
+            Action::ToggleSnapping => "toggle_snapping",
+            Action::ToggleRulers => "toggle_rulers",
+            Action::Follow => "follow",
+            Action::Present => "present",
+            Action::PresentationNext => "presentation_next",
+            Action::PresentationPrevious => "presentation_previous",
+            Action::StopPresenting => "stop_presenting",
+            Action::Replay => "replay",
+            Action::ReplayPlayPause => "replay_play_pause",
+            Action::ReplayForward => "replay_forward",
+            Action::ReplayBack => "replay_back",
+            Action::ReplayFaster => "replay_faster",
+            Action::ReplaySlower => "replay_slower",
+            Action::StopReplay => "stop_replay",
+            Action::Narrate => "narrate",
+            Action::ToggleWireframe => "toggle_wireframe",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.iter().cloned().find(|a| a.name() == name)
+    }
+
+    pub fn context(self) -> Context {
+        match self {
+            Action::PresentationNext | Action::PresentationPrevious | Action::StopPresenting => {
+                Context::Presenting
+            }
+            Action::ReplayPlayPause
+            | Action::ReplayForward
+            | Action::ReplayBack
+            | Action::ReplayFaster
+            | Action::ReplaySlower
+            | Action::StopReplay => Context::Replaying,
+            _ => Context::Drawing,
+        }
+    }
+
+    fn default_chords(self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["Escape"],
+            Action::UseTool => &["Mouse Left"],
+            Action::Pan => &["Mouse Middle"],
+            Action::FinishObject => &["Return"],
+            Action::PenTool => &["P"],
+            Action::TextTool => &["T"],
+            Action::EraserTool => &["E"],
+            Action::SelectTool => &["V"],
+            Action::PanTool => &["H"],
+            Action::StraightLineTool => &["L"],
+            Action::RectangleTool => &["R"],
+            Action::EllipseTool => &["O"],
+            Action::DeleteSelection => &["Delete"],
+            Action::Copy => &["Ctrl+C"],
+            Action::CopyImage => &["Ctrl+Shift+C"],
+            Action::Cut => &["Ctrl+X"],
+            Action::Paste => &["Ctrl+V"],
+            Action::Duplicate => &["Ctrl+D"],
+            Action::BringToFront => &["Ctrl+Up"],
+            Action::SendToBack => &["Ctrl+Down"],
+            Action::FitBoard => &["Home"],
+            Action::FitSelection => &["F"],
+            Action::ResetZoom => &["0", "Keypad 0"],
+            Action::NextObject => &["PageDown", "Tab"],
+            Action::PreviousObject => &["PageUp", "Shift+Tab"],
+            Action::AddBookmark => &["Ctrl+B"],
+            Action::RemoveBookmark => &["Ctrl+Backspace"],
+            Action::NextBookmark => &["]"],
+            Action::PreviousBookmark => &["["],
+            Action::MoveBookmarkEarlier => &["Ctrl+["],
+            Action::MoveBookmarkLater => &["Ctrl+]"],
+            Action::AddLayer => &["Ctrl+Shift+N"],
+            Action::RemoveLayer => &["Ctrl+Shift+Backspace"],
+            Action::RenameLayer => &["F2"],
+            Action::LayerAbove => &["Alt+Up"],
+            Action::LayerBelow => &["Alt+Down"],
+            Action::MoveLayerUp => &["Ctrl+Alt+Up"],
+            Action::MoveLayerDown => &["Ctrl+Alt+Down"],
+            Action::ToggleLayerVisible => &["Ctrl+Shift+H"],
+            Action::ToggleLayerLocked => &["Ctrl+Shift+L"],
+            Action::MoveSelectionToLayer => &["Ctrl+Shift+M"],
+            Action::CycleGrid => &["G"],
+            Action::ToggleSnapping => &["Shift+G"],
+            Action::ToggleRulers => &["Ctrl+R"],
+            Action::Follow => &["Shift+F"],
+            Action::Present => &["F5"],
+            Action::PresentationNext => &["Right", "Down", "Space", "PageDown"],
+            Action::PresentationPrevious => &["Left", "Up", "PageUp"],
+            Action::StopPresenting => &["Escape", "F5"],
+            Action::Replay => &["F6"],
+            Action::ReplayPlayPause => &["Space"],
+            Action::ReplayForward => &["Right"],
+            Action::ReplayBack => &["Left"],
+            Action::ReplayFaster => &["Up", "]"],
+            Action::ReplaySlower => &["Down", "["],
+            Action::StopReplay => &["Escape", "F6"],
+            Action::Narrate => &["F7"],
+            Action::ToggleWireframe => &["Z"],
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Input {
+    Key(Keycode),
+    Mouse(MouseButton),
+}
+
+/// A key or mouse button, along with which modifiers have to be held down with it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Chord {
+    pub input: Input,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+fn mouse_button_name(button: MouseButton) -> &'static str {
+    match button {
+        MouseButton::Left => "Left",
+        MouseButton::Middle => "Middle",
+        MouseButton::Right => "Right",
+        MouseButton::X1 => "X1",
+        MouseButton::X2 => "X2",
+        MouseButton::Unknown => "Unknown",
+    }
+}
+
+impl Chord {
+    fn new(input: Input, keymod: Mod) -> Self {
+        Self {
+            input,
+            ctrl: keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD),
+            shift: keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD),
+            alt: keymod.intersects(Mod::LALTMOD | Mod::RALTMOD),
+        }
+    }
+
+    /// Parses chords like `Ctrl+Shift+B`, `Mouse Middle` or `Alt+Mouse Left`. Keys are named like
+    /// SDL names them
+    pub fn parse(s: &str) -> Result<Chord, String> {
+        let mut parts: Vec<&str> = s.split('+').map(|p| p.trim()).collect();
+        // "+" on its own, or "Ctrl++", is binding the plus key
+        if s.ends_with("++") || s == "+" {
+            parts.pop();
+            parts.pop();
+            parts.push("+");
+        }
+        let input_name = parts.pop().unwrap_or("");
+        let mut to_return = Chord {
+            input: Input::Key(Keycode::Escape),
+            ctrl: false,
+            shift: false,
+            alt: false,
+        };
+        for modifier in parts {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => to_return.ctrl = true,
+                "shift" => to_return.shift = true,
+                "alt" => to_return.alt = true,
+                _ => return Err(format!("unknown modifier '{}' in '{}'", modifier, s)),
+            }
+        }
+        to_return.input = if let Some(button) = input_name.strip_prefix("Mouse ") {
+            let button = match button.trim() {
+                "Left" => MouseButton::Left,
+                "Middle" => MouseButton::Middle,
+                "Right" => MouseButton::Right,
+                "X1" => MouseButton::X1,
+                "X2" => MouseButton::X2,
+                _ => return Err(format!("unknown mouse button '{}' in '{}'", button, s)),
+            };
+            Input::Mouse(button)
+        } else {
+            match Keycode::from_name(input_name) {
+                Some(keycode) => Input::Key(keycode),
+                None => return Err(format!("unknown key '{}' in '{}'", input_name, s)),
+            }
+        };
+        Ok(to_return)
+    }
+
+    pub fn name(&self) -> String {
+        let mut to_return = String::new();
+        if self.ctrl {
+            to_return.push_str("Ctrl+");
+        }
+        if self.shift {
+            to_return.push_str("Shift+");
+        }
+        if self.alt {
+            to_return.push_str("Alt+");
+        }
+        match self.input {
+            Input::Key(keycode) => to_return.push_str(&keycode.name()),
+            Input::Mouse(button) => {
+                to_return.push_str("Mouse ");
+                to_return.push_str(mouse_button_name(button));
+            }
+        }
+        to_return
+    }
+}
+
+/// Two actions that can be used at the same time are bound to the same chord
+#[derive(Clone, PartialEq, Debug)]
+pub struct Conflict {
+    pub chord: Chord,
+    pub first: Action,
+    pub second: Action,
+}
+
+/// Maps input to the actions it triggers. Loaded from `keybindings.json` in the config directory,
+/// which maps action names to lists of chords. Actions missing from it keep their default bindings
+pub struct Keymap {
+    bindings: Vec<(Chord, Action)>,
+}
+
+impl Keymap {
+    pub fn defaults() -> Self {
+        let mut bindings = vec![];
+        for &action in Action::ALL.iter() {
+            for chord in action.default_chords() {
+                bindings.push((Chord::parse(chord).unwrap(), action));
+            }
+        }
+        Self { bindings }
+    }
+
+    /// Defaults overridden by the bindings in `json`. Anything that doesn't parse is skipped and
+    /// described in the returned warnings
+    pub fn from_json(json: &str) -> (Self, Vec<String>) {
+        let mut to_return = Self::defaults();
+        let mut warnings = vec![];
+        let overrides: Map<String, Value> = match serde_json::from_str(json) {
+            Ok(m) => m,
+            Err(e) => {
+                warnings.push(format!(
+                    "couldn't read key bindings, using defaults - {}",
+                    e
+                ));
+                return (to_return, warnings);
+            }
+        };
+        for (name, chords) in overrides.iter() {
+            let action = match Action::from_name(name) {
+                Some(a) => a,
+                None => {
+                    warnings.push(format!("no action called '{}'", name));
+                    continue;
+                }
+            };
+            let chords = match chords {
+                Value::Array(chords) => chords.iter().map(|c| c.as_str()).collect(),
+                Value::String(chord) => vec![Some(chord.as_str())],
+                _ => vec![None],
+            };
+            to_return.bindings.retain(|(_, a)| *a != action);
+            for chord in chords {
+                match chord.map(Chord::parse) {
+                    Some(Ok(chord)) => to_return.bindings.push((chord, action)),
+                    Some(Err(e)) => warnings.push(e),
+                    None => warnings.push(format!("bindings for '{}' must be strings", name)),
+                }
+            }
+        }
+        for conflict in to_return.conflicts() {
+            warnings.push(format!(
+                "{} is bound to both {} and {}, only {} will work",
+                conflict.chord.name(),
+                conflict.first.name(),
+                conflict.second.name(),
+                conflict.first.name(),
+            ));
+        }
+        (to_return, warnings)
+    }
+
+    pub fn load(path: &Path) -> (Self, Vec<String>) {
+        match read_or_write_default(path, &Self::defaults().to_json()) {
+            Ok(json) => Self::from_json(&json),
+            Err(e) => (
+                Self::defaults(),
+                vec![format!(
+                    "couldn't read key bindings, using defaults - {}",
+                    e
+                )],
+            ),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut to_write = Map::new();
+        for &action in Action::ALL.iter() {
+            let chords = self
+                .bindings
+                .iter()
+                .filter(|(_, a)| *a == action)
+                .map(|(c, _)| Value::String(c.name()))
+                .collect();
+            to_write.insert(String::from(action.name()), Value::Array(chords));
+        }
+        serde_json::to_string_pretty(&Value::Object(to_write)).unwrap()
+    }
+
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let mut to_return = vec![];
+        for (i, (chord, first)) in self.bindings.iter().enumerate() {
+            for (other_chord, second) in self.bindings[i + 1..].iter() {
+                if chord == other_chord && first != second && first.context() == second.context() {
+                    to_return.push(Conflict {
+                        chord: *chord,
+                        first: *first,
+                        second: *second,
+                    });
+                }
+            }
+        }
+        to_return
+    }
+
+    fn action_for_chord(&self, chord: Chord, context: Context) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(c, a)| *c == chord && a.context() == context)
+            .map(|(_, a)| *a)
+    }
+
+    /// The action a key or mouse button being pressed triggers
+    pub fn pressed(&self, e: &Event, context: Context, keymod: Mod) -> Option<Action> {
+        let chord = match *e {
+            Event::KeyDown {
+                keycode: Some(keycode),
+                keymod,
+                ..
+            } => Chord::new(Input::Key(keycode), keymod),
+            Event::MouseButtonDown { mouse_btn, .. } => Chord::new(Input::Mouse(mouse_btn), keymod),
+            _ => return None,
+        };
+        self.action_for_chord(chord, context)
+    }
+
+    /// If a key or mouse button being let go of ends holding down `action`. Modifiers are
+    /// ignored, so letting go of them first doesn't leave things stuck
+    pub fn releases(&self, e: &Event, action: Action) -> bool {
+        let input = match *e {
+            Event::KeyUp {
+                keycode: Some(keycode),
+                ..
+            } => Input::Key(keycode),
+            Event::MouseButtonUp { mouse_btn, .. } => Input::Mouse(mouse_btn),
+            _ => return false,
+        };
+        self.bindings
+            .iter()
+            .any(|(c, a)| c.input == input && *a == action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chords_round_trip_through_their_names() {
+        for name in [
+            "Ctrl+B",
+            "Shift+Tab",
+            "Ctrl+Shift+Alt+F5",
+            "Mouse Middle",
+            "Ctrl+[",
+        ] {
+            assert_eq!(Chord::parse(name).unwrap().name(), name);
+        }
+        assert!(Chord::parse("Hyper+B").is_err());
+        assert!(Chord::parse("Mouse Sideways").is_err());
+    }
+
+    #[test]
+    fn defaults_have_no_conflicts_and_survive_being_written_out() {
+        let defaults = Keymap::defaults();
+        assert_eq!(defaults.conflicts(), vec![]);
+        let (keymap, warnings) = Keymap::from_json(&defaults.to_json());
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(keymap.bindings.len(), defaults.bindings.len());
+        assert!(defaults
+            .bindings
+            .iter()
+            .all(|b| keymap.bindings.contains(b)));
+    }
+
+    #[test]
+    fn overrides_replace_defaults_and_conflicts_are_reported() {
+        let (keymap, warnings) =
+            Keymap::from_json(r#"{ "text_tool": ["Ctrl+T"], "fit_board": "F", "nope": [] }"#);
+        let text_tool = Chord::parse("Ctrl+T").unwrap();
+        assert_eq!(
+            keymap.action_for_chord(text_tool, Context::Drawing),
+            Some(Action::TextTool)
+        );
+        let t = Chord::parse("T").unwrap();
+        assert_eq!(keymap.action_for_chord(t, Context::Drawing), None);
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+        assert!(warnings.iter().any(|w| w.contains("nope")));
+        assert!(warnings.iter().any(|w| w.contains("fit_board")));
+    }
+}
@@ -1,65 +1,76 @@
 extern crate gl;
 use crate::gl_vertices::*;
-use crate::util::*;
-use crate::{ExplainObject, ZoomTransform, TypedExplainObject, Shaders};
+use crate::{ExplainObject, Shaders};
+use explain_core::geometry::Rect;
+use explain_core::line::{SavedLine, Stroke};
+use explain_core::util::*;
+use explain_core::zooming::ZoomTransform;
 use sdl2::event::Event;
 
+/// Cosine of the angle between two segments past which they're drawn separately instead of
+/// being joined up
+const SHARP_TURN_COS: f32 = 0.5;
+
 #[derive(Clone)]
 pub struct Line {
-
     gl_vertices: VertexData<(P2, V2)>,
     last_point: Option<P2>,
     zoom_transform: ZoomTransform,
+    stroke: Stroke,
 }
 
-use serde::{Deserialize, Serialize};
-#[derive(Serialize, Deserialize)]
-pub struct SavedLine {
-    points: Vec<P2>,
-    transform: ZoomTransform,
-}
+impl Line {
+    pub const KIND: &'static str = SavedLine::KIND;
 
-impl SavedLine {
-    pub fn from_line(l: &Line) -> Self {
+    pub fn new(stroke: Stroke) -> Line {
+        use vertex_attribs::*;
+        Line {
+            last_point: None,
+            zoom_transform: ZoomTransform::does_nothing(),
+            stroke,
+            gl_vertices: VertexData::new(vec![POINT2_F32, VECTOR2_F32]),
+        }
+    }
+    pub fn from_saved(saved: &SavedLine) -> Line {
+        let mut to_return = Line::new(saved.stroke);
+        to_return.set_points(&saved.points);
+        to_return.zoom_transform = saved.transform.clone();
+
+        to_return
+    }
+
+    pub fn to_saved(&self) -> SavedLine {
+        SavedLine {
+            points: self.points(),
+            transform: self.zoom_transform.clone(),
+            stroke: self.stroke,
+        }
+    }
+    /// Every point along the line, in its own space
+    pub fn points(&self) -> Vec<P2> {
+        // each segment is the up and down vertices of its start, then of its end
+        let d = self.gl_vertices.data();
         let mut points = vec![];
+        if let Some(first) = d.first() {
+            points.push(first.0);
+        }
         let mut i = 3;
-        let d = l.gl_vertices.data();
         while i < d.len() {
             points.push(d[i].0);
             i += 4;
         }
-        Self {
-            points,
-            transform: l.zoom_transform.clone(),
-        }
+        points
     }
 
-    pub fn into_line(&self) -> Line {
-        let mut to_return = Line::new();
-
-        let mut i = 0;
-        if self.points.len() > 0 {
-            while i < self.points.len() - 1 {
-                to_return.add_new_segment(self.points[i], self.points[i + 1]);
-                i += 1;
-            }
+    /// Replaces the whole line with one going through `points`
+    pub fn set_points(&mut self, points: &[P2]) {
+        self.gl_vertices.clear();
+        // a segment with no length has no direction to be drawn across
+        for segment in points.windows(2).filter(|s| s[0] != s[1]) {
+            self.add_new_segment(segment[0], segment[1]);
         }
-        to_return.zoom_transform = self.transform.clone();
-
-        to_return
     }
-}
 
-impl Line {
-    pub fn new() -> Line {
-
-        use vertex_attribs::*;
-        Line {
-            last_point: None,
-            zoom_transform: ZoomTransform::does_nothing(),
-            gl_vertices: VertexData::new(vec![POINT2_F32, VECTOR2_F32]),
-        }
-    }
     fn add_new_segment(&mut self, last_point: P2, new_point: P2) {
         use std::f32::consts::PI;
         fn rotate(v: V2, theta: f32) -> V2 {
@@ -75,14 +86,18 @@ impl Line {
         let mut last_up = up;
         let mut last_down = down;
         if self.gl_vertices.data_len() > 0 {
-            last_up = self
+            let previous_up = self
                 .gl_vertices
                 .get_vertex(self.gl_vertices.data_len() - 2)
                 .1;
-            last_down = self
-                .gl_vertices
-                .get_vertex(self.gl_vertices.data_len() - 1)
-                .1;
+            // around sharp corners, like a rectangle's, the segment would get squashed to nothing
+            if previous_up.dot(&up) > SHARP_TURN_COS {
+                last_up = previous_up;
+                last_down = self
+                    .gl_vertices
+                    .get_vertex(self.gl_vertices.data_len() - 1)
+                    .1;
+            }
         }
 
         self.gl_vertices.append(
@@ -108,14 +123,13 @@ impl ExplainObject for Line {
         if self.gl_vertices.data_len() == 0 {
             return; // nothing in the vertices array, nothing to draw
         }
-        let mut transform_to_use = ZoomTransform::does_nothing();
-        self.zoom_transform.transform_other(&mut transform_to_use);
-        camera.transform_other(&mut transform_to_use);
+        let transform_to_use = self.zoom_transform.relative_to(camera);
 
         shaders.line.set_used();
         shaders.line.write_mat4("projection", projection);
-        transform_to_use.write_to_shader(&shaders.line);
-        shaders.line.write_float("width", 2.0);
+        shaders.line.write_transform(&transform_to_use);
+        shaders.line.write_float("width", self.stroke.width);
+        shaders.line.write_vec3("color", &self.stroke.color.into());
         self.gl_vertices.draw();
     }
 
@@ -138,7 +152,12 @@ impl ExplainObject for Line {
         false
     }
 
-    fn get_as_type(&self) -> TypedExplainObject {
-        TypedExplainObject::Line((*self).clone())
+    fn bounding_box(&self) -> Option<Rect> {
+        let points = self.gl_vertices.data().iter().map(|v| na::convert(v.0));
+        Rect::around_points(points, &self.zoom_transform)
+    }
+
+    fn transform(&self) -> &ZoomTransform {
+        &self.zoom_transform
     }
 }
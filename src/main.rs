@@ -3,45 +3,137 @@ extern crate gl;
 extern crate nalgebra as na;
 extern crate sdl2;
 extern crate serde;
+mod animation;
+mod cli;
+mod clipboard;
+mod collaboration;
+mod drawables;
+mod export;
+mod frame;
+mod grid;
 #[macro_use]
 mod gl_shaders;
 mod gl_vertices;
+mod hud;
+mod images;
+mod keymap;
 mod line;
+mod narration;
+mod object_kinds;
 mod saving;
+mod settings;
+mod svg;
 mod text;
-mod util;
-mod world;
-mod zooming;
+mod tools;
 
-use line::Line;
+use animation::CameraAnimator;
+use cli::Task;
+use clipboard::Clipboard;
+use collaboration::Session;
+use drawables::Drawables;
+use export::{export_replay, paint_replay, ExportOptions};
+use frame::FrameScheduler;
+use grid::{Grid, Guides};
+use hud::{Button, Hud, RemoteCursor, Status};
+use keymap::{Action, Context, Keymap};
+use narration::{Microphone, NarrationPlayer};
 use saving::*;
-use text::Text;
-use util::*;
-use world::*;
-use zooming::*;
+use settings::Settings;
+use tools::{ToolContext, ToolKind};
 
+use explain_core::bookmarks::Bookmark;
+use explain_core::geometry::Rect;
+use explain_core::images::Picture;
+use explain_core::line::Stroke;
+use explain_core::narration::{AudioSource, WavFile};
+use explain_core::navigation::{self, NavigationCommand};
+use explain_core::recording::{Recorder, Replay};
+use explain_core::shape::Shape;
+use explain_core::util::*;
+use explain_core::world::World;
+use explain_core::zooming::*;
+use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
-use sdl2::video::GLProfile;
-use sdl2::{event::Event};
-use std::time::Duration;
+use sdl2::messagebox::{show_simple_message_box, MessageBoxFlag};
+use sdl2::video::{FullscreenType, GLProfile, SwapInterval};
+use std::time::{Duration, Instant};
 
-/// Stuff that is on the whiteboard, panned/zoomed around
+/// Draws something on the whiteboard, panned/zoomed around. What it's drawing is kept in the
+/// world as a `Shape`, which this is made from
 pub trait ExplainObject {
     fn set_transform(&mut self, z: ZoomTransform);
     fn draw(&self, shaders: &Shaders, projection: &na::Matrix4<f32>, camera: &ZoomTransform);
     fn process_event(&mut self, e: &Event) -> bool;
-    fn get_as_type(&self) -> TypedExplainObject; // this will copy, don't use it all the time
+    /// Where the object is in the world, `None` if there's nothing to it yet
+    fn bounding_box(&self) -> Option<Rect>;
+    fn transform(&self) -> &ZoomTransform;
 }
 
-pub enum TypedExplainObject {
-    Line(Line),
-    Text(Text),
-}
+/// Sent from the thread watching the settings file when it changes
+struct SettingsChanged;
 
 // Should there be a better scheme for how shaders are stored/managed or is this good enough?
 pub struct Shaders {
     line: gl_shaders::ShaderProgram,
     text: gl_shaders::ShaderProgram,
+    ui: gl_shaders::ShaderProgram,
+    image: gl_shaders::ShaderProgram,
+    grid: gl_shaders::ShaderProgram,
+}
+
+/// Something whose name is being typed in
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Naming {
+    Bookmark,
+    Layer,
+}
+
+fn name_being_typed(world: &mut World, naming: Naming) -> Option<&mut String> {
+    match naming {
+        Naming::Bookmark => world.bookmarks.current_mut().map(|b| &mut b.name),
+        Naming::Layer => Some(&mut world.current_layer_mut().name),
+    }
+}
+
+/// Says how exporting a replay to `path` went
+fn report_export(exported: Result<(usize, Vec<String>), String>, path: &str) {
+    match exported {
+        Ok((frames, problems)) => {
+            println!("exported {} frames to {}", frames, path);
+            for problem in problems {
+                println!("but the replay {}", problem);
+            }
+        }
+        Err(e) => println!("couldn't export the replay to {} - {}", path, e),
+    }
+}
+
+/// Exports the replay of the saved board by painting it without gl, for when there's no display
+/// to get gl from
+fn paint_saved_replay(path: &str, mut options: ExportOptions, settings: &Settings) {
+    options.background = settings.background_color;
+    let world = load_or_new_world(settings.save_format).unwrap_or_else(|e| {
+        println!("{}", e);
+        World::new()
+    });
+    let painted = paint_replay(world.recording, std::path::Path::new(path), &options);
+    report_export(painted, path);
+}
+
+fn screen_size(window: &sdl2::video::Window) -> V2f64 {
+    let (width, height) = window.size();
+    V2f64::new(width as f64, height as f64)
+}
+
+fn navigation_command(action: Action) -> Option<NavigationCommand> {
+    match action {
+        Action::FitBoard => Some(NavigationCommand::FitBoard),
+        Action::FitSelection => Some(NavigationCommand::FitSelection),
+        Action::ResetZoom => Some(NavigationCommand::ResetZoom),
+        Action::NextObject => Some(NavigationCommand::NextObject),
+        Action::PreviousObject => Some(NavigationCommand::PreviousObject),
+        _ => None,
+    }
 }
 
 // https://www.khronos.org/opengl/wiki/OpenGL_Error
@@ -72,26 +164,102 @@ extern "system" fn message_callback(
     }
 }
 
+/// What to do, from the command line
+enum Command<'a> {
+    Draw,
+    /// Shares the board on an address
+    Host(&'a str),
+    /// Joins a board someone else shares
+    Join(&'a str),
+    /// Draws how the board was drawn into a gif or folder of frames, without showing a window
+    ExportReplay(&'a str, ExportOptions),
+    /// Works on board files without opening a window
+    Headless(Task<'a>),
+}
+
+/// Shortest time between saves, so changes made quickly one after another are saved together
+/// instead of writing the whole board out for each
+const SAVE_INTERVAL: Duration = Duration::from_secs(1);
+/// How long something that went wrong stays in the status bar
+const REPORT_TIME: Duration = Duration::from_secs(5);
+
+const USAGE: &str = "usage: explain [--host address | --join address | --export-replay path.gif|folder [--size WxH] [--fps N] [--camera recorded|board]]";
+
 pub fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let command = match args.as_slice() {
+        [] => Command::Draw,
+        ["--host", address] => Command::Host(address),
+        ["--join", address] => Command::Join(address),
+        ["--export-replay", path, options @ ..] => match ExportOptions::parse(options) {
+            Ok(options) => Command::ExportReplay(path, options),
+            Err(e) => return println!("{}\n{}", e, USAGE),
+        },
+        ["--convert", from, to] => Command::Headless(Task::Convert(from, to)),
+        args => match Task::parse(args) {
+            Some(Ok(task)) => Command::Headless(task),
+            Some(Err(e)) => return println!("{}\n{}\n{}", e, USAGE, cli::USAGE),
+            None => return println!("{}\n{}", USAGE, cli::USAGE),
+        },
+    };
+    let settings_path = get_config_directory_path().join("settings.json");
+    let (mut settings, warnings) = Settings::load(&settings_path);
+    for warning in warnings {
+        println!("settings: {}", warning);
+    }
+    if let Command::Headless(task) = command {
+        // scripts need to know when something went wrong
+        match cli::run(task, &settings) {
+            Ok(report) => println!("{}", report),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // without a display there's no gl, so a replay being exported is painted without it instead
+    macro_rules! or_without_display {
+        ($result:expr) => {
+            match $result {
+                Ok(value) => value,
+                Err(e) => {
+                    if let Command::ExportReplay(path, options) = command {
+                        return paint_saved_replay(path, options, &settings);
+                    }
+                    return println!("couldn't open a window - {}", e);
+                }
+            }
+        };
+    }
     let sdl_context = sdl2::init().unwrap();
 
-    let video_subsystem = sdl_context.video().unwrap();
+    let video_subsystem = or_without_display!(sdl_context.video());
+    // only needed for narration, so drawing still works without it
+    let audio_subsystem = sdl_context.audio();
+    let mut clipboard = Clipboard::new(video_subsystem.clipboard());
 
     let gl_attr = video_subsystem.gl_attr();
     gl_attr.set_context_profile(sdl2::video::GLProfile::GLES);
     gl_attr.set_context_major_version(2);
     gl_attr.set_context_minor_version(0);
 
-    let window = video_subsystem
-        .window("explain", 800, 600)
-        .opengl()
-        .position_centered()
-        .resizable()
-        .build()
-        .unwrap();
+    let [width, height] = settings.window_size;
+    let mut window_builder = video_subsystem.window("explain", width, height);
+    window_builder.opengl().position_centered().resizable();
+    if let Command::ExportReplay(..) = command {
+        window_builder.hidden();
+    }
+    let mut window = or_without_display!(window_builder.build());
 
-    let _ctx = window.gl_create_context().unwrap();
+    let _ctx = or_without_display!(window.gl_create_context());
     gl::load_with(|name| video_subsystem.gl_get_proc_address(name) as *const _);
+    // without vsync frames are paced by sleeping instead
+    let vsync = video_subsystem
+        .gl_set_swap_interval(SwapInterval::VSync)
+        .is_ok();
 
     debug_assert_eq!(gl_attr.context_profile(), GLProfile::GLES);
     debug_assert_eq!(gl_attr.context_version(), (2, 0));
@@ -99,188 +267,921 @@ pub fn main() {
     // ui state
     // array of items that dynamically expands as user creates more items with the various tools
     // available
-    let mut world = load_or_new_world();
-    let mut currently_creating: Option<Box<dyn ExplainObject>> = None;
+    let mut world = load_or_new_world(settings.save_format).unwrap_or_else(|e| {
+        println!("{}", e);
+        if !matches!(command, Command::ExportReplay(..)) {
+            show_simple_message_box(MessageBoxFlag::ERROR, "Explain", &e, &window).ok();
+        }
+        World::new()
+    });
+    let mut session = match command {
+        Command::Host(address) => match Session::host(address, &mut world) {
+            Ok(session) => {
+                println!("sharing the board on {}", session.address().unwrap());
+                Some(session)
+            }
+            Err(e) => return println!("couldn't share the board on {} - {}", address, e),
+        },
+        Command::Join(address) => match Session::join(address) {
+            Ok(session) => Some(session),
+            Err(e) => return println!("couldn't join {} - {}", address, e),
+        },
+        Command::Draw | Command::ExportReplay(..) | Command::Headless(..) => None,
+    };
+    let mut recorder = Recorder::new(&mut world, Instant::now());
+    // while replaying how the board was drawn, the board itself is left alone
+    let mut replay: Option<Replay> = None;
+    let mut narration_player: Option<NarrationPlayer> = None;
+    // what draws the board, and the board and what's being drawn in a replay
+    let mut drawables = Drawables::new();
+    let mut replay_drawables = Drawables::new();
+    let mut replay_drawing = Drawables::new();
+    let mut scrubbing = false;
+    let mut current_tool = ToolKind::Pen;
+    let mut tool = current_tool.create();
 
     let mut event_pump = sdl_context.event_pump().unwrap();
+    let event_subsystem = sdl_context.event().unwrap();
+    event_subsystem
+        .register_custom_event::<SettingsChanged>()
+        .unwrap();
+    let event_sender = event_subsystem.event_sender();
+    settings::watch(settings_path.clone(), move || {
+        // if the event can't be queued the settings just aren't reloaded this time
+        event_sender.push_custom_event(SettingsChanged).ok();
+    });
 
     // gl stuff
     let shaders = Shaders {
         line: shader!("line.vert", "line.frag"),
         text: shader!("text.vert", "text.frag"),
+        ui: shader!("ui.vert", "ui.frag"),
+        image: shader!("text.vert", "image.frag"),
+        grid: shader!("grid.vert", "grid.frag"),
     };
-    let mut projection = nalgebra::Orthographic3::new(0.0, 800.0, 600.0, 0.0, -1.0, 1.0);
+    let mut projection =
+        nalgebra::Orthographic3::new(0.0, width as f32, height as f32, 0.0, -1.0, 1.0);
     let mut drawing_wireframe = false;
+    let mut camera_animator = CameraAnimator::new();
+    let mut scheduler = FrameScheduler::new(60, vsync);
+    let mut presenting = false;
+    // while naming a bookmark or layer, typing goes into its name. The old name is replaced on
+    // the first character typed
+    let mut naming: Option<Naming> = None;
+    let mut name_typed = false;
+    let mut panning = false;
+    let mut swallow_text_input = false;
+    let (keymap, warnings) = Keymap::load(&get_config_directory_path().join("keybindings.json"));
+    for warning in warnings {
+        println!("key bindings: {}", warning);
+    }
+    let mut stroke = Stroke {
+        color: hud::PALETTE[0],
+        width: settings.stroke_width,
+    };
+    let mut guides = Guides::from_settings(&settings);
+    let mut hud = Hud::new();
+    // why the last thing tried didn't happen, until it can
+    let mut notice: Option<String> = None;
+    // something that went wrong on its own, like a setting that couldn't be used, and since when
+    let mut report: Option<(String, Instant)> = None;
+    // if the board has changed since it was last saved, which stays true if saving fails
+    let mut unsaved = false;
+    let mut last_save: Option<Instant> = None;
+    macro_rules! save_board {
+        () => {
+            last_save = Some(Instant::now());
+            match save(&world, settings.save_format) {
+                Ok(()) => unsaved = false,
+                Err(e) => {
+                    unsaved = true;
+                    println!("couldn't save the board - {}", e);
+                }
+            }
+        };
+    }
+    // shares the change with anyone else on the board and has it saved soon. Guests don't save,
+    // the board being the host's
+    macro_rules! board_changed {
+        () => {
+            recorder.board_changed(&mut world, Instant::now());
+            match &mut session {
+                Some(session) => {
+                    session.share_changes(&world);
+                    if !session.is_guest() {
+                        unsaved = true;
+                    }
+                }
+                None => unsaved = true,
+            }
+        };
+    }
+    // moving the camera by hand or by a key stops following someone else's
+    macro_rules! stop_following {
+        () => {
+            if let Some(shared) = &mut session {
+                shared.stop_following();
+            }
+        };
+    }
+    let mut grid = Grid::new();
+    // everything the current tool is allowed to change, for handing to it
+    macro_rules! tool_context {
+        ($mouse_pos:expr) => {
+            ToolContext {
+                world: &mut world,
+                camera_animator: &mut camera_animator,
+                keymap: &keymap,
+                stroke,
+                guides,
+                mouse_pos: $mouse_pos,
+                changed: false,
+                notice: None,
+            }
+        };
+    }
     unsafe {
-        gl::Viewport(0, 0, 800, 600);
+        gl::Viewport(0, 0, width as i32, height as i32);
         gl::Enable(gl::DEBUG_OUTPUT);
         gl::DebugMessageCallback(Some(message_callback), std::ptr::null());
         gl::Enable(gl::BLEND);
         gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
     }
+    if let Command::ExportReplay(path, mut options) = command {
+        options.background = settings.background_color;
+        let recording = std::mem::take(&mut world.recording);
+        let exported = export_replay(&shaders, recording, std::path::Path::new(path), &options);
+        return report_export(exported, path);
+    }
     'running: loop {
-        unsafe {
-            gl::ClearColor(1.0, 1.0, 1.0, 1.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT);
+        // sleep until there's either an event or a frame to draw
+        let animating =
+            camera_animator.is_animating() || matches!(&replay, Some(r) if r.is_playing());
+        let wait = scheduler.wait_time(animating, Instant::now());
+        // shared boards are polled for messages, so don't sleep for longer than that
+        let wait = match (wait, &session) {
+            (Some(wait), Some(_)) => Some(wait.min(collaboration::POLL_INTERVAL)),
+            (None, Some(_)) => Some(collaboration::POLL_INTERVAL),
+            (wait, None) => wait,
+        };
+        // and changes are saved once it's been long enough since the last save
+        let wait = match (wait, unsaved) {
+            (wait, true) => {
+                let since = last_save.map_or(SAVE_INTERVAL, |t| t.elapsed());
+                let left = SAVE_INTERVAL.saturating_sub(since);
+                Some(wait.map_or(left, |w| w.min(left)))
+            }
+            (wait, false) => wait,
+        };
+        // and reports are taken down once they've been up long enough
+        let wait = match (wait, &report) {
+            (wait, Some((_, since))) => {
+                let left = REPORT_TIME.saturating_sub(since.elapsed());
+                Some(wait.map_or(left, |w| w.min(left)))
+            }
+            (wait, None) => wait,
+        };
+        let first_event = match wait {
+            None => Some(event_pump.wait_event()),
+            Some(wait) if wait.as_micros() > 0 => {
+                event_pump.wait_event_timeout(frame::timeout_millis(wait))
+            }
+            Some(_) => None,
+        };
+        if unsaved && !matches!(last_save, Some(t) if t.elapsed() < SAVE_INTERVAL) {
+            save_board!();
+        }
+
+        if let Some(shared) = &mut session {
+            let received = shared.receive(&mut world);
+            if received.board_changed {
+                recorder.board_changed(&mut world, Instant::now());
+                if !shared.is_guest() {
+                    unsaved = true;
+                }
+                scheduler.request_redraw();
+            }
+            if let Some(news) = received.news.last() {
+                report = Some((news.clone(), Instant::now()));
+                scheduler.request_redraw();
+            }
+            if received.others_moved {
+                if let Some(followed) = shared.following() {
+                    let screen_size = screen_size(&window);
+                    camera_animator.fly_to(
+                        followed.view.camera_for(screen_size),
+                        P2f64::from(screen_size / 2.0),
+                    );
+                }
+                scheduler.request_redraw();
+            }
+            if shared.is_closed() {
+                let news = String::from("the host stopped sharing the board");
+                report = Some((news, Instant::now()));
+                // the board was the host's, so it's put away instead of being saved over the
+                // guest's own
+                if shared.is_guest() {
+                    world = load_or_new_world(settings.save_format).unwrap_or_else(|e| {
+                        println!("{}", e);
+                        World::new()
+                    });
+                    camera_animator.stop();
+                    recorder = Recorder::new(&mut world, Instant::now());
+                    tool = current_tool.create();
+                }
+                session = None;
+            }
         }
 
         let ms = event_pump.mouse_state();
-        let middle_down = ms.middle();
         let mouse_pos = P2::new(ms.x() as f32, ms.y() as f32);
         drop(ms);
 
-        let mut cur_movement = Movement::new();
-        for event in event_pump.poll_iter() {
-            use sdl2::mouse::MouseButton;
-            let mut consumed_event = false;
-            if let Some(object) = &mut currently_creating {
-                let mut new_transform = world.camera.clone();
-                new_transform.become_inverse();
-                object.set_transform(new_transform);
-                consumed_event = object.process_event(&event);
+        for event in first_event.into_iter().chain(event_pump.poll_iter()) {
+            scheduler.request_redraw();
+            // a key that did something shouldn't also be typed, like the T that starts text
+            match event {
+                Event::TextInput { .. } if swallow_text_input => {
+                    swallow_text_input = false;
+                    continue;
+                }
+                Event::KeyDown { .. } => swallow_text_input = false,
+                _ => {}
             }
-
-            // TODO move to world
-            fn push_object_if_there(
-                window: &sdl2::video::Window,
-                world: &mut World,
-                object: Option<Box<dyn ExplainObject>>,
-            ) {
-                if let Some(object) = object {
-                    let as_type = object.get_as_type();
-                    match as_type {
-                        TypedExplainObject::Line(l) => {
-                            world.lines.push(l);
+            let keymod = sdl_context.keyboard().mod_state();
+            let mut consumed_event = false;
+            let mut stop_replay = false;
+            if presenting {
+                // nothing but stepping through the bookmarks while presenting
+                if let Some(action) = keymap.pressed(&event, Context::Presenting, keymod) {
+                    match action {
+                        Action::StopPresenting => {
+                            presenting = false;
+                            if let Err(e) = window.set_fullscreen(FullscreenType::Off) {
+                                let problem = format!("couldn't leave full screen - {}", e);
+                                report = Some((problem, Instant::now()));
+                            }
                         }
-                        TypedExplainObject::Text(t) => {
-                            world.texts.push(t);
+                        Action::PresentationNext | Action::PresentationPrevious => {
+                            let by = if action == Action::PresentationNext {
+                                1
+                            } else {
+                                -1
+                            };
+                            stop_following!();
+                            if let Some(bookmark) = world.bookmarks.step(by) {
+                                let screen_size = screen_size(&window);
+                                camera_animator.fly_to(
+                                    bookmark.camera_for(screen_size),
+                                    P2f64::from(screen_size / 2.0),
+                                );
+                            }
                         }
+                        _ => {}
                     }
-                    save(window, world);
                 }
-            }
-
-            if !consumed_event {
+                consumed_event = matches!(
+                    event,
+                    Event::KeyDown { .. }
+                        | Event::KeyUp { .. }
+                        | Event::TextInput { .. }
+                        | Event::MouseButtonDown { .. }
+                        | Event::MouseButtonUp { .. }
+                        | Event::MouseMotion { .. }
+                        | Event::MouseWheel { .. }
+                        | Event::DropFile { .. }
+                );
+            } else if let Some(playing) = &mut replay {
+                let screen_size: V2 = na::convert(screen_size(&window));
+                match keymap.pressed(&event, Context::Replaying, keymod) {
+                    Some(Action::StopReplay) => stop_replay = true,
+                    Some(Action::ReplayPlayPause) => playing.toggle_playing(),
+                    Some(Action::ReplayForward) => playing.skip(true),
+                    Some(Action::ReplayBack) => playing.skip(false),
+                    Some(Action::ReplayFaster) => playing.change_speed(true),
+                    Some(Action::ReplaySlower) => playing.change_speed(false),
+                    _ => {}
+                }
+                // clicking and dragging along the timeline jumps around in the replay
                 match event {
-                    // TODO refactor thing creation to put whether element is created or not into element's file
-                    Event::Quit { .. }
-                    | Event::KeyDown {
-                        keycode: Some(Keycode::Escape),
-                        ..
-                    } => break 'running,
-
-                    // creation and deletion
-                    Event::MouseButtonDown {
-                        mouse_btn: MouseButton::Left,
-                        ..
-                    } => {
-                        push_object_if_there(&window, &mut world, currently_creating);
-                        currently_creating = Some(Box::new(Line::new()));
+                    Event::MouseButtonDown { x, y, .. }
+                        if hud::on_timeline(P2::new(x as f32, y as f32), screen_size) =>
+                    {
+                        scrubbing = true;
                     }
-                    Event::MouseButtonUp {
-                        mouse_btn: MouseButton::Left,
-                        ..
-                    } => {
-                        push_object_if_there(&window, &mut world, currently_creating);
-                        currently_creating = None
+                    Event::MouseButtonUp { .. } => scrubbing = false,
+                    _ => {}
+                }
+                match event {
+                    Event::MouseButtonDown { x, .. } | Event::MouseMotion { x, .. }
+                        if scrubbing =>
+                    {
+                        let fraction = hud::timeline_fraction(x as f32, screen_size);
+                        playing.seek(fraction * playing.duration());
+                    }
+                    _ => {}
+                }
+                consumed_event = matches!(
+                    event,
+                    Event::KeyDown { .. }
+                        | Event::KeyUp { .. }
+                        | Event::TextInput { .. }
+                        | Event::MouseButtonDown { .. }
+                        | Event::MouseButtonUp { .. }
+                        | Event::MouseMotion { .. }
+                        | Event::DropFile { .. }
+                );
+            } else if let Some(being_named) = naming {
+                consumed_event = match &event {
+                    Event::TextInput { text, .. } => {
+                        if let Some(name) = name_being_typed(&mut world, being_named) {
+                            if !name_typed {
+                                name.clear();
+                                name_typed = true;
+                            }
+                            name.push_str(text);
+                        }
+                        true
                     }
-
-                    // text
                     Event::KeyDown {
-                        keycode: Some(Keycode::T),
+                        keycode: Some(Keycode::Backspace),
                         ..
                     } => {
-                        push_object_if_there(&window, &mut world, currently_creating);
-                        let global_pos = mouse_pos;
-                        currently_creating =
-                            Some(Box::new(Text::new(P2::new(global_pos.x, global_pos.y))));
+                        if let Some(name) = name_being_typed(&mut world, being_named) {
+                            name.pop();
+                            name_typed = true;
+                        }
+                        true
                     }
                     Event::KeyDown {
                         keycode: Some(Keycode::Return),
                         ..
+                    }
+                    | Event::KeyDown {
+                        keycode: Some(Keycode::Escape),
+                        ..
                     } => {
-                        push_object_if_there(&window, &mut world, currently_creating);
-                        currently_creating = None;
+                        naming = None;
+                        board_changed!();
+                        true
                     }
-
-                    // zooming
-                    Event::MouseWheel { y, .. } => {
-                        let scale_delta = 1.0 + (y as f64) * 0.05;
-                        cur_movement.zoom = scale_delta;
-                        cur_movement.wrt_point = na::convert(mouse_pos);
+                    // the keys being typed shouldn't trigger anything
+                    Event::KeyDown { .. } | Event::KeyUp { .. } => true,
+                    _ => false,
+                };
+            }
+            if stop_replay {
+                replay = None;
+                if let Some(player) = &mut narration_player {
+                    player.stop();
+                }
+            }
+            if consumed_event {
+                continue;
+            }
+            // clicks on the toolbar don't reach the board
+            let hud_click = match event {
+                Event::MouseButtonDown { x, y, .. } => {
+                    let p = P2::new(x as f32, y as f32);
+                    let screen_size = na::convert(screen_size(&window));
+                    if hud::covers(p, screen_size, guides.rulers) {
+                        Some(hud::button_at(p))
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            };
+            let action = match hud_click {
+                Some(Some(Button::Tool(kind))) => Some(kind.action()),
+                Some(button) => {
+                    match button {
+                        Some(Button::Color(color)) => stroke.color = color,
+                        Some(Button::Width(width)) => stroke.width = width,
+                        _ => {}
                     }
+                    continue;
+                }
+                None => keymap.pressed(&event, Context::Drawing, keymod),
+            };
+            if hud_click.is_none() {
+                // the tool gets the first look at everything, e.g. so keys can be typed into text
+                let mut context = tool_context!(mouse_pos);
+                let consumed_event = tool.process_event(&event, action, &mut context);
+                notice = context.notice.or(notice);
+                if context.changed {
+                    board_changed!();
+                }
+                if consumed_event {
+                    continue;
+                }
+            }
+            if let Some(action) = action {
+                if let Event::KeyDown { .. } = event {
+                    swallow_text_input = true;
+                }
+                match action {
+                    Action::Quit => break 'running,
 
-                    Event::KeyDown {
-                        keycode: Some(Keycode::E),
-                        ..
-                    } => {
-                        // debug e key to zoom out really far
-                        let scale_delta = 0.1;
-                        cur_movement.zoom = scale_delta;
-                        cur_movement.wrt_point = P2f64::new(105.0, 73.0);
-                        // cur_movement.wrt_point = mouse_pos;
+                    // tools
+                    Action::PenTool
+                    | Action::TextTool
+                    | Action::EraserTool
+                    | Action::SelectTool
+                    | Action::PanTool
+                    | Action::StraightLineTool
+                    | Action::RectangleTool
+                    | Action::EllipseTool => {
+                        let mut context = tool_context!(mouse_pos);
+                        tool.finish(&mut context);
+                        current_tool = ToolKind::from_action(action).unwrap();
+                        tool = current_tool.create();
+                        tool.activate(&mut context);
+                        notice = context.notice.or(notice);
+                        if context.changed {
+                            board_changed!();
+                        }
                     }
-                    Event::KeyDown {
-                        keycode: Some(Keycode::Q),
-                        ..
-                    } => {
-                        // debug q key to zoom in really far to first object
-                        let scale_delta = 10.0;
-                        cur_movement.zoom = scale_delta;
-                        cur_movement.wrt_point = P2f64::new(105.0, 73.0);
-                        // P2::from(items[0].get_moved_around().get_drawing_transform().offset);
+                    // anything not used up by the tool
+                    Action::UseTool | Action::FinishObject => {}
+                    Action::DeleteSelection => {
+                        for id in std::mem::take(&mut world.selection) {
+                            world.remove(id);
+                        }
+                        board_changed!();
+                    }
+                    Action::Copy | Action::Cut => {
+                        if clipboard.copy(&world) && action == Action::Cut {
+                            for id in std::mem::take(&mut world.selection) {
+                                world.remove(id);
+                            }
+                            board_changed!();
+                        }
+                    }
+                    Action::CopyImage => {
+                        clipboard.copy_image(&world, settings.background_color);
+                    }
+                    Action::Paste if world.cant_add_reason().is_some() => {
+                        notice = world.cant_add_reason();
+                    }
+                    Action::Paste | Action::Duplicate => {
+                        let added = if action == Action::Paste {
+                            clipboard
+                                .paste(&world.camera, na::convert(mouse_pos), screen_size(&window))
+                                .map(|objects| objects.into_iter().map(|o| world.add(o)).collect())
+                        } else {
+                            // copies stay on the layers of what they're copies of
+                            clipboard::duplicate(&mut world)
+                        };
+                        match added {
+                            Ok(ids) if !ids.is_empty() => {
+                                world.selection = ids;
+                                board_changed!();
+                            }
+                            Ok(_) => {}
+                            Err(e) => println!("couldn't add the objects - {}", e),
+                        }
+                    }
+                    Action::BringToFront | Action::SendToBack => {
+                        if action == Action::BringToFront {
+                            world.bring_to_front(&world.selection.clone());
+                        } else {
+                            world.send_to_back(&world.selection.clone());
+                        }
+                        board_changed!();
                     }
 
                     // panning
-                    Event::MouseMotion { xrel, yrel, .. } => {
-                        if middle_down {
-                            cur_movement.pan -= V2f64::new(xrel as f64, yrel as f64);
+                    Action::Pan => {
+                        panning = true;
+                        stop_following!();
+                        // grabbing the board stops it from gliding
+                        camera_animator.stop();
+                    }
+
+                    // navigation
+                    Action::FitBoard
+                    | Action::FitSelection
+                    | Action::ResetZoom
+                    | Action::NextObject
+                    | Action::PreviousObject => {
+                        stop_following!();
+                        let command = navigation_command(action).unwrap();
+                        let screen_size = screen_size(&window);
+                        if let Some(target) =
+                            navigation::camera_target(command, &mut world, screen_size)
+                        {
+                            camera_animator.fly_to(target, P2f64::from(screen_size / 2.0));
+                        }
+                    }
+
+                    // bookmarks
+                    Action::AddBookmark => {
+                        world
+                            .bookmarks
+                            .add(world.camera.clone(), screen_size(&window));
+                        naming = Some(Naming::Bookmark);
+                        name_typed = false;
+                        board_changed!();
+                    }
+                    Action::RemoveBookmark => {
+                        world.bookmarks.remove_current();
+                        board_changed!();
+                    }
+                    Action::MoveBookmarkEarlier | Action::MoveBookmarkLater => {
+                        let by = if action == Action::MoveBookmarkEarlier {
+                            -1
+                        } else {
+                            1
+                        };
+                        world.bookmarks.move_current(by);
+                        board_changed!();
+                    }
+                    Action::PreviousBookmark | Action::NextBookmark => {
+                        let by = if action == Action::PreviousBookmark {
+                            -1
+                        } else {
+                            1
+                        };
+                        stop_following!();
+                        if let Some(bookmark) = world.bookmarks.step(by) {
+                            let screen_size = screen_size(&window);
+                            camera_animator.fly_to(
+                                bookmark.camera_for(screen_size),
+                                P2f64::from(screen_size / 2.0),
+                            );
+                        }
+                    }
+
+                    // layers
+                    Action::AddLayer => {
+                        world.add_layer();
+                        naming = Some(Naming::Layer);
+                        name_typed = false;
+                        board_changed!();
+                    }
+                    Action::RemoveLayer => {
+                        if world.remove_current_layer() {
+                            board_changed!();
+                        } else {
+                            println!("only empty layers can be removed, and not the last one");
+                        }
+                    }
+                    Action::RenameLayer => {
+                        naming = Some(Naming::Layer);
+                        name_typed = false;
+                    }
+                    Action::LayerAbove => world.step_layer(1),
+                    Action::LayerBelow => world.step_layer(-1),
+                    Action::MoveLayerUp | Action::MoveLayerDown => {
+                        let by = if action == Action::MoveLayerUp { 1 } else { -1 };
+                        world.move_current_layer(by);
+                        board_changed!();
+                    }
+                    Action::ToggleLayerVisible | Action::ToggleLayerLocked => {
+                        let layer = world.current_layer_mut();
+                        if action == Action::ToggleLayerVisible {
+                            layer.visible = !layer.visible;
+                        } else {
+                            layer.locked = !layer.locked;
+                        }
+                        world.deselect_unpickable();
+                        board_changed!();
+                    }
+                    Action::MoveSelectionToLayer => {
+                        world.move_to_current_layer(&world.selection.clone());
+                        world.deselect_unpickable();
+                        board_changed!();
+                    }
+
+                    Action::CycleGrid => guides.grid = guides.grid.next(),
+                    Action::ToggleSnapping => guides.snapping = !guides.snapping,
+                    Action::ToggleRulers => guides.rulers = !guides.rulers,
+                    Action::Follow => {
+                        if let Some(shared) = &mut session {
+                            shared.follow_next();
+                            if let Some(followed) = shared.following() {
+                                let screen_size = screen_size(&window);
+                                camera_animator.fly_to(
+                                    followed.view.camera_for(screen_size),
+                                    P2f64::from(screen_size / 2.0),
+                                );
+                            }
+                        }
+                    }
+                    Action::Present => {
+                        let mut context = tool_context!(mouse_pos);
+                        tool.finish(&mut context);
+                        if context.changed {
+                            board_changed!();
+                        }
+                        // presenting is full screen only, so the board stays as it is if it
+                        // can't be
+                        if let Err(e) = window.set_fullscreen(FullscreenType::Desktop) {
+                            let problem = format!("couldn't go full screen to present - {}", e);
+                            report = Some((problem, Instant::now()));
+                        } else {
+                            presenting = true;
+                            world.bookmarks.go_to_start();
+                            stop_following!();
+                            if let Some(bookmark) = world.bookmarks.step(1) {
+                                let screen_size = screen_size(&window);
+                                camera_animator.fly_to(
+                                    bookmark.camera_for(screen_size),
+                                    P2f64::from(screen_size / 2.0),
+                                );
+                            }
+                        }
+                    }
+
+                    Action::Replay => {
+                        let mut context = tool_context!(mouse_pos);
+                        tool.finish(&mut context);
+                        if context.changed || recorder.is_narrating() {
+                            recorder.stop_narrating(&mut world, Instant::now());
+                            board_changed!();
+                        }
+                        if narration_player.is_none() && !world.recording.narration.is_empty() {
+                            match audio_subsystem.as_ref().map_err(Clone::clone) {
+                                Ok(audio) => match NarrationPlayer::open(audio) {
+                                    Ok(player) => narration_player = Some(player),
+                                    Err(e) => println!("couldn't play the narration - {}", e),
+                                },
+                                Err(e) => println!("couldn't play the narration - {}", e),
+                            }
+                        }
+                        replay = Some(Replay::new(world.recording.clone()));
+                    }
+
+                    Action::Narrate => {
+                        let now = Instant::now();
+                        if recorder.is_narrating() {
+                            recorder.stop_narrating(&mut world, now);
+                            // the narration only reaches the disk with the board
+                            board_changed!();
+                        } else {
+                            let source: Result<Box<dyn AudioSource>, String> = match &settings
+                                .fake_microphone
+                            {
+                                Some(path) => WavFile::open(path, now).map(|f| Box::new(f) as _),
+                                None => audio_subsystem
+                                    .as_ref()
+                                    .map_err(Clone::clone)
+                                    .and_then(Microphone::open)
+                                    .map(|m| Box::new(m) as _),
+                            };
+                            match source {
+                                Ok(source) => recorder.start_narrating(&mut world, source, now),
+                                Err(e) => println!("couldn't start narrating - {}", e),
+                            }
                         }
                     }
 
                     // debug wireframe mode
-                    #[cfg(debug_assertions)]
-                    Event::KeyDown {
-                        keycode: Some(Keycode::Z),
-                        ..
-                    } => {
-                        drawing_wireframe = !drawing_wireframe;
-                        unsafe {
-                            if drawing_wireframe {
-                                gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
-                            } else {
-                                gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+                    Action::ToggleWireframe => {
+                        if cfg!(debug_assertions) {
+                            drawing_wireframe = !drawing_wireframe;
+                            unsafe {
+                                if drawing_wireframe {
+                                    gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
+                                } else {
+                                    gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+                                }
                             }
                         }
                     }
 
-                    // resize the gl canvas with the window
-                    Event::Window { win_event, .. } => match win_event {
-                        sdl2::event::WindowEvent::Resized(x, y) => unsafe {
-                            gl::Viewport(0, 0, x, y);
-                            projection.set_right(x as f32);
-                            projection.set_bottom(y as f32);
-                        },
-                        _ => {}
-                    },
-                    _ => {}
+                    // only used while presenting or replaying
+                    Action::PresentationNext
+                    | Action::PresentationPrevious
+                    | Action::StopPresenting
+                    | Action::ReplayPlayPause
+                    | Action::ReplayForward
+                    | Action::ReplayBack
+                    | Action::ReplayFaster
+                    | Action::ReplaySlower
+                    | Action::StopReplay => {}
                 }
+                continue;
+            }
+            match event {
+                Event::Quit { .. } => break 'running,
+
+                _ if keymap.releases(&event, Action::Pan) => {
+                    panning = false;
+                    camera_animator.release();
+                }
+
+                // zooming
+                Event::MouseWheel { y, .. } => {
+                    stop_following!();
+                    let scale_delta = 1.0 + (y as f64) * settings.zoom_step;
+                    camera_animator.zoom_towards(scale_delta, na::convert(mouse_pos));
+                }
+
+                // panning
+                Event::MouseMotion { xrel, yrel, .. } if panning => {
+                    camera_animator.drag(-V2f64::new(xrel as f64, yrel as f64));
+                }
+
+                // images dragged onto the window land under the mouse, and svg drawings in the
+                // middle of the view
+                Event::DropFile { .. } if world.cant_add_reason().is_some() => {
+                    notice = world.cant_add_reason();
+                }
+                Event::DropFile { filename, .. } => {
+                    let path = std::path::Path::new(&filename);
+                    let is_svg = matches!(
+                        path.extension().and_then(|e| e.to_str()),
+                        Some(e) if e.eq_ignore_ascii_case("svg")
+                    );
+                    let dropped: Result<Vec<Box<dyn Shape>>, String> = if is_svg {
+                        std::fs::read_to_string(path)
+                            .map_err(|e| e.to_string())
+                            .and_then(|text| {
+                                let center = P2f64::from(screen_size(&window) / 2.0);
+                                svg::import_objects(&text, &world.camera, center)
+                            })
+                    } else {
+                        Picture::load(path).map(|mut image| {
+                            image.place(
+                                &world.camera,
+                                na::convert(mouse_pos),
+                                screen_size(&window),
+                            );
+                            vec![Box::new(image) as Box<dyn Shape>]
+                        })
+                    };
+                    match dropped {
+                        Ok(objects) => {
+                            world.selection = objects.into_iter().map(|o| world.add(o)).collect();
+                            board_changed!();
+                        }
+                        Err(e) => println!("couldn't add {} - {}", filename, e),
+                    }
+                }
+
+                Event::User { .. } if event.as_user_event_type::<SettingsChanged>().is_some() => {
+                    let (mut new_settings, warnings) = Settings::load(&settings_path);
+                    for warning in warnings {
+                        println!("settings: {}", warning);
+                    }
+                    if new_settings.stroke_width != settings.stroke_width {
+                        stroke.width = new_settings.stroke_width;
+                    }
+                    if Guides::from_settings(&new_settings) != Guides::from_settings(&settings) {
+                        guides = Guides::from_settings(&new_settings);
+                    }
+                    if new_settings.window_size != settings.window_size && !presenting {
+                        let [width, height] = new_settings.window_size;
+                        // the window keeps its size if it can't have the new one
+                        if let Err(e) = window.set_size(width, height) {
+                            let problem = format!("couldn't resize the window - {}", e);
+                            report = Some((problem, Instant::now()));
+                            new_settings.window_size = settings.window_size;
+                        }
+                    }
+                    settings = new_settings;
+                }
+
+                // resize the gl canvas with the window
+                Event::Window {
+                    win_event:
+                        sdl2::event::WindowEvent::Resized(x, y)
+                        | sdl2::event::WindowEvent::SizeChanged(x, y),
+                    ..
+                } => {
+                    unsafe {
+                        gl::Viewport(0, 0, x, y);
+                    }
+                    projection.set_right(x as f32);
+                    projection.set_bottom(y as f32);
+                    if presenting {
+                        // going full screen changes how much of the bookmark fits
+                        if let Some(bookmark) = world.bookmarks.current() {
+                            let screen_size = screen_size(&window);
+                            camera_animator.fly_to(
+                                bookmark.camera_for(screen_size),
+                                P2f64::from(screen_size / 2.0),
+                            );
+                        }
+                    }
+                }
+                _ => {}
             }
         }
 
+        if let Some(shared) = &mut session {
+            let screen_size = screen_size(&window);
+            let over_board = !hud::covers(mouse_pos, na::convert(screen_size), guides.rulers);
+            let cursor = if over_board {
+                Some(world.camera.transform_point_exact(na::convert(mouse_pos)))
+            } else {
+                None
+            };
+            let view = Bookmark::new(String::new(), world.camera.clone(), screen_size);
+            shared.share_presence(&settings.name, cursor, view);
+        }
+
+        let now = Instant::now();
+        recorder.listen(&mut world, now);
+        let animating =
+            camera_animator.is_animating() || matches!(&replay, Some(r) if r.is_playing());
+        if !scheduler.frame_due(animating, now) {
+            continue;
+        }
+        let dt = scheduler.start_frame(now);
+        camera_animator.update(dt, &mut world.camera);
+        match &mut replay {
+            Some(replay) => {
+                replay.advance(dt);
+                if let Some(player) = &mut narration_player {
+                    player.update(replay);
+                }
+            }
+            None => {
+                let drawing = tool.in_progress();
+                recorder.sample(&mut world, drawing.as_deref(), screen_size(&window), now);
+            }
+        }
+
+        unsafe {
+            let [r, g, b] = settings.background_color;
+            gl::ClearColor(r, g, b, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
         let mat = projection.as_matrix();
-        cur_movement.apply_to_transform(&mut world.camera);
-        if let Some(o) = &mut currently_creating {
-            o.draw(&shaders, mat, &world.camera);
+        if let Some(replay) = &replay {
+            let camera = replay
+                .camera_for(screen_size(&window))
+                .unwrap_or_else(|| world.camera.clone());
+            let screen_size = na::convert(screen_size(&window));
+            grid.draw(&shaders, mat, &camera, screen_size, guides.grid);
+            replay_drawables.update(replay.world().visible_revisions());
+            replay_drawables.draw(&shaders, mat, &camera);
+            replay_drawing.update(replay.drawing());
+            replay_drawing.draw(&shaders, mat, &camera);
+            hud.draw_replay(&shaders, mat, screen_size, replay);
+            window.gl_swap_window();
+            continue;
         }
-        for t in world.texts.iter_mut() {
-            t.draw(&shaders, mat, &world.camera);
+        if !presenting {
+            let screen_size = na::convert(screen_size(&window));
+            grid.draw(&shaders, mat, &world.camera, screen_size, guides.grid);
         }
-        for l in world.lines.iter_mut() {
-            l.draw(&shaders, mat, &world.camera);
+        drawables.update(world.visible_revisions());
+        drawables.draw(&shaders, mat, &world.camera);
+        if !presenting {
+            tool.draw_preview(&shaders, mat, &world);
+            if world.cant_add_reason().is_none() {
+                notice = None;
+            }
+            if matches!(&report, Some((_, since)) if since.elapsed() >= REPORT_TIME) {
+                report = None;
+            }
+            let status = Status {
+                tool: current_tool,
+                camera: &world.camera,
+                cursor: mouse_pos,
+                layer: world.current_layer(),
+                object_count: world.object_count(),
+                saved: !unsaved && !tool.has_unfinished_work(),
+                following: session
+                    .as_ref()
+                    .and_then(|s| s.following())
+                    .map(|p| p.name.as_str()),
+                narrating: recorder.is_narrating(),
+                notice: notice
+                    .as_deref()
+                    .or(report.as_ref().map(|(r, _)| r.as_str())),
+            };
+            let cursors: Vec<RemoteCursor> = session
+                .iter()
+                .flat_map(|s| s.others())
+                .filter_map(|p| {
+                    let at = world.camera.inverse_transform_exact(p.cursor?);
+                    Some(RemoteCursor {
+                        at: na::convert(at),
+                        color: p.color,
+                        name: &p.name,
+                    })
+                })
+                .collect();
+            let screen_size = na::convert(screen_size(&window));
+            hud.draw_cursors(&shaders, mat, &cursors);
+            hud.draw(&shaders, mat, screen_size, stroke, guides.rulers, &status);
         }
 
         window.gl_swap_window();
-        ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 60)); // TODO take exactly 1/60s every time by accounting for how long computation above takes
+    }
+    // keep what was said so far
+    if recorder.is_narrating() {
+        recorder.stop_narrating(&mut world, Instant::now());
+        board_changed!();
+    }
+    if unsaved {
+        if let Err(e) = save(&world, settings.save_format) {
+            println!("the last changes to the board couldn't be saved - {}", e);
+        }
     }
 }
@@ -0,0 +1,108 @@
+use explain_core::narration::{samples_between, AudioSource, SAMPLE_RATE};
+use explain_core::recording::Replay;
+use sdl2::audio::{AudioCallback, AudioDevice, AudioQueue, AudioSpecDesired};
+use sdl2::AudioSubsystem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Instant;
+
+/// How far ahead of the replay narration is handed to the speakers, in seconds of real time. Less
+/// than this and it stutters, more and it lags behind pausing
+const LOOKAHEAD: f64 = 0.1;
+
+/// Hands samples over from the audio thread
+struct Forward(Sender<Vec<i16>>);
+
+impl AudioCallback for Forward {
+    type Channel = i16;
+
+    fn callback(&mut self, samples: &mut [i16]) {
+        // nothing to do if nobody's listening any more
+        let _ = self.0.send(samples.to_vec());
+    }
+}
+
+pub struct Microphone {
+    // stops recording when dropped
+    _device: AudioDevice<Forward>,
+    heard: Receiver<Vec<i16>>,
+}
+
+impl Microphone {
+    /// Starts listening to the default microphone
+    pub fn open(audio: &AudioSubsystem) -> Result<Self, String> {
+        let (sender, heard) = channel();
+        let spec = AudioSpecDesired {
+            freq: Some(SAMPLE_RATE),
+            channels: Some(1),
+            samples: None,
+        };
+        let device = audio.open_capture(None, &spec, |_| Forward(sender))?;
+        device.resume();
+        Ok(Self {
+            _device: device,
+            heard,
+        })
+    }
+}
+
+impl AudioSource for Microphone {
+    fn take(&mut self, _now: Instant) -> Vec<i16> {
+        self.heard.try_iter().flatten().collect()
+    }
+}
+
+/// Plays the narration of a replay in time with it
+pub struct NarrationPlayer {
+    queue: AudioQueue<i16>,
+    /// Time in the recording and speed of the narration handed over so far, if it carries on
+    /// from where the replay is
+    queued: Option<(f64, f64)>,
+}
+
+impl NarrationPlayer {
+    pub fn open(audio: &AudioSubsystem) -> Result<Self, String> {
+        let spec = AudioSpecDesired {
+            freq: Some(SAMPLE_RATE),
+            channels: Some(1),
+            samples: None,
+        };
+        Ok(Self {
+            queue: audio.open_queue(None, &spec)?,
+            queued: None,
+        })
+    }
+
+    /// Keeps the speakers a little ahead of `replay`. Called every frame
+    pub fn update(&mut self, replay: &Replay) {
+        if !replay.is_playing() {
+            self.stop();
+            return;
+        }
+        let (time, speed) = (replay.time(), replay.speed());
+        let until = match self.queued {
+            // carrying on from what's already playing
+            Some((until, s))
+                if s == speed && (time..=time + 2.0 * LOOKAHEAD * speed).contains(&until) =>
+            {
+                until
+            }
+            // skipped around or changed speed
+            _ => {
+                self.queue.clear();
+                time
+            }
+        };
+        let samples = samples_between(replay.narration(), until, time + LOOKAHEAD * speed, speed);
+        self.queue.queue(&samples);
+        let until = until + samples.len() as f64 * speed / SAMPLE_RATE as f64;
+        self.queued = Some((until, speed));
+        self.queue.resume();
+    }
+
+    /// Goes quiet, for pausing or leaving the replay
+    pub fn stop(&mut self) {
+        self.queue.pause();
+        self.queue.clear();
+        self.queued = None;
+    }
+}
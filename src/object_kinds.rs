@@ -0,0 +1,90 @@
+use crate::images::Image;
+use crate::line::Line;
+use crate::text::Text;
+use crate::ExplainObject;
+use explain_core::images::SavedImage;
+use explain_core::line::SavedLine;
+use explain_core::object_kinds::Attachments;
+use explain_core::shape::Shape;
+use explain_core::text::SavedText;
+use serde_json::Value;
+
+/// Makes what draws an object from what it saved and the attachments of the board it was saved
+/// with
+pub type Loader = fn(Value, &Attachments) -> Result<Box<dyn ExplainObject>, String>;
+
+/// How to make what draws one kind of object from what its `Shape::save` made
+pub struct ObjectKind {
+    /// The name of its kind in `explain_core::object_kinds::KINDS`
+    pub name: &'static str,
+    pub load: Loader,
+}
+
+/// Every kind of object that can be drawn on the screen. A new kind needs a `Shape` in
+/// `explain_core::object_kinds::KINDS`, and an `ExplainObject` listed here to draw it
+pub const KINDS: &[ObjectKind] = &[
+    ObjectKind {
+        name: Line::KIND,
+        load: |data, _| {
+            let saved: SavedLine = serde_json::from_value(data).map_err(|e| e.to_string())?;
+            Ok(Box::new(Line::from_saved(&saved)))
+        },
+    },
+    ObjectKind {
+        name: Text::KIND,
+        load: |data, _| {
+            let saved: SavedText = serde_json::from_value(data).map_err(|e| e.to_string())?;
+            Ok(Box::new(Text::from_saved(&saved)))
+        },
+    },
+    ObjectKind {
+        name: Image::KIND,
+        load: |data, attachments| {
+            let saved: SavedImage = serde_json::from_value(data).map_err(|e| e.to_string())?;
+            Ok(Box::new(Image::from_saved(saved, attachments)?))
+        },
+    },
+];
+
+/// Makes what draws the object of kind `kind` that was saved as `data`
+pub fn load(
+    kind: &str,
+    data: Value,
+    attachments: &Attachments,
+) -> Result<Box<dyn ExplainObject>, String> {
+    let found = KINDS
+        .iter()
+        .find(|k| k.name == kind)
+        .ok_or_else(|| format!("unknown kind of object \"{}\"", kind))?;
+    (found.load)(data, attachments).map_err(|e| format!("couldn't read a {} - {}", kind, e))
+}
+
+/// Makes what draws `shape`
+pub fn drawable(shape: &dyn Shape) -> Result<Box<dyn ExplainObject>, String> {
+    let attachments: Attachments = shape.attachments().into_iter().collect();
+    load(shape.kind(), shape.save(), &attachments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kind_names_are_unique() {
+        for (i, kind) in KINDS.iter().enumerate() {
+            assert!(KINDS[i + 1..].iter().all(|k| k.name != kind.name));
+        }
+    }
+
+    #[test]
+    fn every_kind_can_be_read_without_a_window() {
+        let readable = explain_core::object_kinds::KINDS;
+        for kind in KINDS {
+            assert!(
+                readable.iter().any(|k| k.name == kind.name),
+                "{}",
+                kind.name
+            );
+        }
+    }
+}
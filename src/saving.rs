@@ -1,25 +1,8 @@
-use std::path::{PathBuf};
-use directories::{ProjectDirs};
-use crate::world::*;
-
-fn fatal_msgbox(window: &sdl2::video::Window, msg: &str) {
-    use sdl2::messagebox::{show_simple_message_box, MessageBoxFlag};
-    show_simple_message_box(MessageBoxFlag::ERROR, "Fatal Error", msg, window).unwrap();
-    panic!();
-}
-
-fn expect_msgbox<T: std::fmt::Debug, E: std::fmt::Debug>(
-    window: &sdl2::video::Window,
-    r: Result<T, E>,
-    msg: &str,
-) -> T {
-    if r.is_ok() {
-        r.unwrap()
-    } else {
-        fatal_msgbox(window, format!("{} - {:?}", msg, r.unwrap_err()).as_str());
-        panic!();
-    }
-}
+use directories::ProjectDirs;
+use explain_core::saving::{decode, encode, SaveFormat};
+use explain_core::world::World;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
 
 fn get_save_directory_path() -> PathBuf {
     // TODO msgbox the unwrap
@@ -30,52 +13,147 @@ fn get_save_directory_path() -> PathBuf {
     )
 }
 
-fn get_save_file_path() -> PathBuf {
-    get_save_directory_path().join("save.explain")
+/// Where settings like key bindings live, as opposed to the boards themselves
+pub fn get_config_directory_path() -> PathBuf {
+    // TODO msgbox the unwrap
+    PathBuf::from(
+        ProjectDirs::from("com", "creikey", "Explain")
+            .unwrap()
+            .config_dir(),
+    )
+}
+
+fn get_save_file_path(format: SaveFormat) -> PathBuf {
+    get_save_directory_path().join(format.file_name())
 }
 
-pub fn save(window: &sdl2::video::Window, world: &World) {
+/// Writes `world` to the save file for `format`. It's written next to it first and then moved
+/// over it, so the old save is kept whole if writing is cut short
+pub fn save(world: &World, format: SaveFormat) -> std::io::Result<()> {
     let save_directory = get_save_directory_path();
-    expect_msgbox(
-        &window,
-        std::fs::create_dir_all(&save_directory),
-        format!(
-            "failed to create save directory in {}",
-            save_directory.to_str().unwrap()
-        )
-        .as_str(),
-    );
-    use std::fs::File;
-    use std::io::prelude::*;
-    let saved_world = SavedWorld::from_world(world);
-    let encoded = bincode::serialize(&saved_world).unwrap();
-    let save_file_path = get_save_file_path();
-    println!(
-        "{} | {}",
-        save_directory.to_str().unwrap(),
-        save_file_path.to_str().unwrap()
-    );
+    std::fs::create_dir_all(&save_directory)?;
+    let encoded = encode(&world.to_saved(), format);
+    let save_file_path = get_save_file_path(format);
+    let mut writing = save_file_path.clone().into_os_string();
+    writing.push(".writing");
+    std::fs::write(&writing, encoded)?;
+    std::fs::rename(writing, save_file_path)
+}
+
+/// The saved board, from whichever format's file was saved last so changing the format keeps
+/// the board, `format` winning a tie. A board that can't be read is moved out of the way instead of
+/// being saved over, and what went wrong is returned
+pub fn load_or_new_world(format: SaveFormat) -> Result<World, String> {
+    load_or_new_world_in(&get_save_directory_path(), format)
+}
 
-    use std::fs::OpenOptions;
-    let mut save_file = if save_file_path.exists() {
-        OpenOptions::new().write(true).open(save_file_path).unwrap()
-    } else {
-        File::create(save_file_path).unwrap()
+fn load_or_new_world_in(directory: &Path, format: SaveFormat) -> Result<World, String> {
+    let other = match format {
+        SaveFormat::Bincode => SaveFormat::Json,
+        SaveFormat::Json => SaveFormat::Bincode,
     };
-    save_file.write_all(encoded.as_slice()).unwrap();
+    // the last of the newest is taken, so `format` goes last
+    let newest = [other, format]
+        .iter()
+        .map(|f| directory.join(f.file_name()))
+        .filter_map(|p| {
+            let modified = p.metadata().ok()?.modified().unwrap_or(UNIX_EPOCH);
+            Some((modified, p))
+        })
+        .max_by_key(|(modified, _)| *modified);
+    let save_path = match newest {
+        Some((_, save_path)) => save_path,
+        None => return Ok(World::new()),
+    };
+    let loaded = std::fs::read(&save_path)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| decode(&bytes))
+        .and_then(World::from_saved);
+    loaded.map_err(|e| {
+        let unreadable = format!("couldn't read the board in {}", save_path.display());
+        match keep_aside(&save_path) {
+            Ok(kept) => format!("{} - {}. It was kept as {}", unreadable, e, kept.display()),
+            Err(moving) => format!(
+                "{} - {}, and couldn't move it out of the way - {}",
+                unreadable, e, moving
+            ),
+        }
+    })
+}
+
+/// Renames the file at `path` to a name that isn't taken, returning the new name
+fn keep_aside(path: &Path) -> std::io::Result<PathBuf> {
+    let kept = (1..)
+        .map(|n| {
+            let mut name = path.as_os_str().to_owned();
+            name.push(if n == 1 {
+                String::from(".unreadable")
+            } else {
+                format!(".unreadable{}", n)
+            });
+            PathBuf::from(name)
+        })
+        .find(|p| !p.exists())
+        .unwrap();
+    std::fs::rename(path, &kept)?;
+    Ok(kept)
 }
 
-pub fn load_or_new_world() -> World {
-    // TODO this stuff should definitely expect_msgbox to show that the save file is corrupt
-    let save_path = get_save_file_path();
-    let to_return: World;
-    if save_path.exists() {
-        let bytes = std::fs::read(save_path).unwrap();
-        let saved_world: SavedWorld = bincode::deserialize(bytes.as_slice()).unwrap();
-        to_return = saved_world.into_world();
-    } else {
-        to_return = World::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boards_are_loaded_back_in_either_format() {
+        let mut world = World::new();
+        world.current_layer_mut().name = String::from("Notes");
+        let saved_world = world.to_saved();
+        for &format in [SaveFormat::Bincode, SaveFormat::Json].iter() {
+            let bytes = encode(&saved_world, format);
+            let world = World::from_saved(decode(&bytes).unwrap()).unwrap();
+            assert_eq!(world.current_layer().name, "Notes");
+        }
+    }
+
+    #[test]
+    fn unreadable_boards_are_kept_instead_of_saved_over() {
+        let directory = std::env::temp_dir().join(format!("explain-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let save_path = directory.join(SaveFormat::Bincode.file_name());
+        for kept in &["save.explain.unreadable", "save.explain.unreadable2"] {
+            std::fs::write(&save_path, b"not a board").unwrap();
+            let error = load_or_new_world_in(&directory, SaveFormat::Bincode)
+                .err()
+                .unwrap();
+            assert!(error.contains(kept), "{}", error);
+            assert!(!save_path.exists());
+            assert_eq!(std::fs::read(directory.join(kept)).unwrap(), b"not a board");
+        }
+        let world = load_or_new_world_in(&directory, SaveFormat::Bincode).unwrap();
+        assert_eq!(world.object_count(), 0);
+        std::fs::remove_dir_all(directory).unwrap();
     }
 
-    to_return
-}
\ No newline at end of file
+    #[test]
+    fn the_format_saved_last_is_loaded() {
+        let directory = std::env::temp_dir().join(format!("explain-newest-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let write = |format: SaveFormat, name: &str, seconds_ago: u64| {
+            let mut world = World::new();
+            world.current_layer_mut().name = String::from(name);
+            let path = directory.join(format.file_name());
+            std::fs::write(&path, encode(&world.to_saved(), format)).unwrap();
+            let modified =
+                std::time::SystemTime::now() - std::time::Duration::from_secs(seconds_ago);
+            let file = std::fs::OpenOptions::new().write(true).open(path).unwrap();
+            file.set_modified(modified).unwrap();
+        };
+        write(SaveFormat::Bincode, "Old", 60);
+        write(SaveFormat::Json, "New", 0);
+        for &format in [SaveFormat::Bincode, SaveFormat::Json].iter() {
+            let world = load_or_new_world_in(&directory, format).unwrap();
+            assert_eq!(world.current_layer().name, "New");
+        }
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
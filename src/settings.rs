@@ -0,0 +1,176 @@
+use crate::grid::GridStyle;
+use explain_core::saving::SaveFormat;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// How often the settings file is checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+/// Widest or tallest window SDL can be asked for, since it keeps sizes as `i32`
+const MAX_WINDOW_SIZE: u32 = i32::MAX as u32;
+
+/// Preferences from `settings.json` in the config directory. Anything missing from the file keeps
+/// its default, so new settings can be added without breaking old files
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Red, green and blue from 0 to 1
+    pub background_color: [f32; 3],
+    /// Width and height the window opens with
+    pub window_size: [u32; 2],
+    /// How thick new lines are, in pixels at the zoom they're drawn at
+    pub stroke_width: f32,
+    /// How much one notch of the mouse wheel zooms by
+    pub zoom_step: f64,
+    /// `hidden`, `dots` or `lines`
+    pub grid: GridStyle,
+    /// If shapes, text and moved objects snap to the grid and to other objects
+    pub snapping: bool,
+    /// If rulers with world coordinates are shown along the top and left of the screen
+    pub rulers: bool,
+    /// What others see next to this person's cursor on a shared board
+    pub name: String,
+    /// A 16 bit wav file narration is read from instead of the microphone, for trying it out
+    /// without one
+    pub fake_microphone: Option<PathBuf>,
+    /// `bincode`, or `json` to save the board as `save.explain.json` where it can be read
+    pub save_format: SaveFormat,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            background_color: [1.0, 1.0, 1.0],
+            window_size: [800, 600],
+            stroke_width: 2.0,
+            zoom_step: 0.05,
+            grid: GridStyle::Hidden,
+            snapping: false,
+            rulers: false,
+            name: std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_else(|_| String::from("Someone")),
+            fake_microphone: None,
+            save_format: SaveFormat::Bincode,
+        }
+    }
+}
+
+/// Reads the config file at `path`, first writing `defaults` there if there's nothing there yet
+/// so it's easy to find and change
+pub fn read_or_write_default(path: &Path, defaults: &str) -> std::io::Result<String> {
+    if !path.exists() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, defaults)?;
+    }
+    std::fs::read_to_string(path)
+}
+
+impl Settings {
+    /// Settings from `json`, with anything that doesn't make sense replaced by its default and
+    /// described in the returned warnings
+    pub fn from_json(json: &str) -> (Self, Vec<String>) {
+        let mut to_return: Settings = match serde_json::from_str(json) {
+            Ok(s) => s,
+            Err(e) => {
+                let warning = format!("couldn't read settings, using defaults - {}", e);
+                return (Self::default(), vec![warning]);
+            }
+        };
+        let defaults = Self::default();
+        let mut warnings = vec![];
+        if to_return
+            .background_color
+            .iter()
+            .any(|c| !(0.0..=1.0).contains(c))
+        {
+            warnings.push(String::from("background_color values must be from 0 to 1"));
+            to_return.background_color = defaults.background_color;
+        }
+        if to_return.window_size.contains(&0) {
+            warnings.push(String::from("window_size can't be zero"));
+            to_return.window_size = defaults.window_size;
+        }
+        if to_return.window_size.iter().any(|&s| s > MAX_WINDOW_SIZE) {
+            let warning = format!("window_size can't be more than {}", MAX_WINDOW_SIZE);
+            warnings.push(warning);
+            to_return.window_size = defaults.window_size;
+        }
+        if to_return.stroke_width.is_nan() || to_return.stroke_width <= 0.0 {
+            warnings.push(String::from("stroke_width must be more than zero"));
+            to_return.stroke_width = defaults.stroke_width;
+        }
+        if !(to_return.zoom_step > 0.0 && to_return.zoom_step < 1.0) {
+            warnings.push(String::from("zoom_step must be between 0 and 1"));
+            to_return.zoom_step = defaults.zoom_step;
+        }
+        (to_return, warnings)
+    }
+
+    pub fn load(path: &Path) -> (Self, Vec<String>) {
+        let defaults = serde_json::to_string_pretty(&Self::default()).unwrap();
+        match read_or_write_default(path, &defaults) {
+            Ok(json) => Self::from_json(&json),
+            Err(e) => (
+                Self::default(),
+                vec![format!("couldn't read settings, using defaults - {}", e)],
+            ),
+        }
+    }
+}
+
+fn last_modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Calls `on_change` from another thread whenever the file at `path` is changed, so settings can
+/// be reloaded while the program is running
+pub fn watch<F: Fn() + Send + 'static>(path: PathBuf, on_change: F) {
+    std::thread::spawn(move || {
+        let mut seen = last_modified(&path);
+        loop {
+            std::thread::sleep(WATCH_INTERVAL);
+            let modified = last_modified(&path);
+            if modified != seen {
+                seen = modified;
+                on_change();
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_settings_keep_their_defaults() {
+        let (settings, warnings) = Settings::from_json(r#"{ "stroke_width": 5.0 }"#);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(
+            settings,
+            Settings {
+                stroke_width: 5.0,
+                ..Settings::default()
+            }
+        );
+    }
+
+    #[test]
+    fn nonsense_settings_are_replaced_with_defaults() {
+        let (settings, warnings) =
+            Settings::from_json(r#"{ "zoom_step": 3.0, "background_color": [0.5, 2.0, 0.0] }"#);
+        assert_eq!(settings, Settings::default());
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+
+        let (settings, warnings) = Settings::from_json(r#"{ "window_size": [4294967295, 600] }"#);
+        assert_eq!(settings.window_size, Settings::default().window_size);
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+
+        let (settings, warnings) = Settings::from_json("not json");
+        assert_eq!(settings, Settings::default());
+        assert_eq!(warnings.len(), 1);
+    }
+}
@@ -0,0 +1,61 @@
+use crate::clipboard::center_on;
+use explain_core::line::SavedLine;
+use explain_core::shape::{bounds, Shape};
+use explain_core::svg::{document, import, page, Imported, BASELINE};
+use explain_core::text::{font_size, SavedText};
+use explain_core::util::*;
+use explain_core::zooming::ZoomTransform;
+
+/// An svg document drawing `objects` at the size they look through `camera`, with `metadata`
+/// tucked in like `document` does
+pub fn render<'a, I>(objects: I, camera: &ZoomTransform, metadata: Option<(&str, &str)>) -> String
+where
+    I: Iterator<Item = &'a dyn Shape> + Clone,
+{
+    let (view, size) = page(
+        bounds(objects.clone().filter_map(|o| o.bounding_box())),
+        camera,
+    );
+    document(size, metadata, objects.map(|o| o.to_svg(&view)))
+}
+
+/// Objects drawing what's in an svg document, one svg unit to a pixel on the screen `camera`
+/// looks through, centered on `screen_point`
+pub fn import_objects(
+    svg: &str,
+    camera: &ZoomTransform,
+    screen_point: P2f64,
+) -> Result<Vec<Box<dyn Shape>>, String> {
+    let mut objects: Vec<Box<dyn Shape>> = import(svg)
+        .into_iter()
+        .map(|imported| -> Box<dyn Shape> {
+            match imported {
+                Imported::Stroke { points, stroke } => {
+                    let mut points: Vec<P2> = points.iter().map(|&p| na::convert(p)).collect();
+                    // a segment with no length has no direction to be drawn across
+                    points.dedup();
+                    Box::new(SavedLine {
+                        points,
+                        transform: camera.clone(),
+                        stroke,
+                    })
+                }
+                Imported::Label { at, text, size } => {
+                    let scale = size / font_size() as f64;
+                    let top = at - V2f64::new(0.0, size * BASELINE);
+                    let on_screen = ZoomTransform::new(scale, top.coords);
+                    Box::new(SavedText {
+                        text,
+                        transform: on_screen.inverse_relative_to(camera),
+                        origin: P2::origin(),
+                    })
+                }
+            }
+        })
+        .collect();
+    if objects.is_empty() {
+        return Err(String::from("there's nothing in it that can be drawn"));
+    }
+    center_on(&mut objects, camera.transform_point_exact(screen_point));
+    Ok(objects)
+}
@@ -1,62 +1,35 @@
 extern crate gl;
 use crate::gl_vertices::*;
-use crate::{ExplainObject, ZoomTransform, TypedExplainObject, Shaders};
-use crate::util::*;
+use crate::{ExplainObject, Shaders};
+use explain_core::geometry::Rect;
+use explain_core::text::{font, glyph, SavedText, FONT_IMAGE};
+use explain_core::util::*;
+use explain_core::zooming::ZoomTransform;
 use sdl2::event::Event;
-use serde_json::{from_str, Map, Value};
+use serde_json::{Map, Value};
 
 extern crate image;
 
-use serde::{Deserialize, Serialize};
-#[derive(Serialize, Deserialize)]
-pub struct SavedText {
-    text: String,
-    transform: ZoomTransform,
-    origin: P2,
-}
-
-impl SavedText {
-    pub fn from_text(t: &Text) -> Self {
-        Self {
-            transform: t.zoom_transform.clone(),
-            text: t.text.clone(),
-            origin: t.origin.clone(),
-        }
-    }
-
-    pub fn into_text(&self) -> Text {
-        let mut to_return = Text::new(self.origin);
-        to_return.zoom_transform = self.transform.clone();
-
-        for c in self.text.chars() {
-            to_return.add_character(&c.to_string());
-        }
-
-        to_return
-    }
-}
-
 #[derive(Clone)]
 pub struct Text {
     gl_vertices: VertexData<(P2, P2)>,
     texture: gl::types::GLuint,
     character_map: Map<String, Value>,
     zoom_transform: ZoomTransform,
-    size: (u64, u64),
     origin: P2,
     width_offset: f32,
     text: String,
 }
 
 impl Text {
-    pub fn new(origin: P2) -> Self {
+    pub const KIND: &'static str = SavedText::KIND;
 
+    pub fn new(origin: P2) -> Self {
         use vertex_attribs::*;
         let gl_vertices = VertexData::new(vec![POINT2_F32, POINT2_F32]);
         use image::DynamicImage;
-        let img = image::load_from_memory(include_bytes!("arial-font.png")).unwrap();
+        let img = image::load_from_memory(FONT_IMAGE).unwrap();
         let mut texture = 0;
-        let mut size = (0, 0);
         unsafe {
             gl::GenTextures(1, &mut texture);
             gl::BindTexture(gl::TEXTURE_2D, texture);
@@ -68,8 +41,6 @@ impl Text {
 
             match img {
                 DynamicImage::ImageRgba8(buf) => {
-                    size.0 = buf.width() as u64;
-                    size.1 = buf.height() as u64;
                     gl::TexImage2D(
                         gl::TEXTURE_2D,
                         0,
@@ -91,21 +62,11 @@ impl Text {
             gl::BindTexture(gl::TEXTURE_2D, 0);
         }
 
-        let maybe_character_map: Value = from_str(include_str!("arial-font.json")).unwrap();
-        let character_map;
-        match maybe_character_map {
-            Value::Object(m) => {
-                character_map = m;
-            }
-            _ => {
-                panic!("Unexpected json type from sdf character location map");
-            }
-        }
+        let character_map = font();
 
         Text {
             texture,
             gl_vertices,
-            size,
             character_map,
             origin,
             zoom_transform: ZoomTransform::does_nothing(),
@@ -113,64 +74,67 @@ impl Text {
             text: String::from(""),
         }
     }
-    fn add_character(&mut self, key_string: &String) {
-        let character_to_rect = self
+    pub fn from_saved(saved: &SavedText) -> Self {
+        let mut to_return = Text::new(saved.origin);
+        to_return.zoom_transform = saved.transform.clone();
+        // boards made by other programs can have characters the font doesn't
+        to_return.set_text(&saved.text);
+
+        to_return
+    }
+
+    pub fn to_saved(&self) -> SavedText {
+        SavedText {
+            transform: self.zoom_transform.clone(),
+            text: self.text.clone(),
+            origin: self.origin,
+        }
+    }
+    /// Replaces everything written so far with `text`, leaving out characters the font doesn't
+    /// have
+    pub fn set_text(&mut self, text: &str) {
+        self.gl_vertices.clear();
+        self.width_offset = 0.0;
+        self.text.clear();
+        let characters = self
             .character_map
             .get("characters")
             .unwrap()
             .as_object()
             .unwrap();
-        let rect = character_to_rect
-            .get(key_string)
-            .unwrap()
-            .as_object()
-            .unwrap();
+        let known: Vec<String> = text
+            .chars()
+            .map(|c| c.to_string())
+            .filter(|c| characters.contains_key(c))
+            .collect();
+        for c in known.iter() {
+            self.add_character(c);
+        }
+    }
+    fn add_character(&mut self, key_string: &str) {
+        let glyph = match glyph(&self.character_map, key_string) {
+            Some(glyph) => glyph,
+            None => return,
+        };
         self.text.push_str(key_string);
-        // TODO use strong typed version of this json stuff (should probably do this when font logic is abstracted to multiple fonts)
-        let width_in_px = rect.get("width").unwrap().as_i64().unwrap();
-        let height_in_px = rect.get("height").unwrap().as_i64().unwrap();
-        let width = width_in_px as f32 / self.size.0 as f32;
-        let height = height_in_px as f32 / self.size.1 as f32;
-        let x = rect.get("x").unwrap().as_i64().unwrap() as f32 / self.size.0 as f32;
-        let y = rect.get("y").unwrap().as_i64().unwrap() as f32 / self.size.1 as f32;
-        let origin_y = rect.get("originY").unwrap().as_i64().unwrap();
-        let origin_x = -rect.get("originX").unwrap().as_i64().unwrap() as f32;
-
-        let vertical_offset = self.character_map.get("size").unwrap().as_i64().unwrap() - origin_y;
+        let pen = self.origin + V2::new(self.width_offset, 0.0);
+        let (upper, lower) = (glyph.corners.0 + pen.coords, glyph.corners.1 + pen.coords);
+        let (atlas_upper, atlas_lower) = glyph.atlas;
         let mut new_vertices = vec![
+            (upper, atlas_upper), // upper left
             (
-                P2::new(self.width_offset + origin_x, vertical_offset as f32),
-                P2::new(x, y),
-            ), // upper left
-            (
-                P2::new(
-                    self.width_offset + origin_x + width_in_px as f32,
-                    vertical_offset as f32,
-                ),
-                P2::new(x + width, y),
+                P2::new(lower.x, upper.y),
+                P2::new(atlas_lower.x, atlas_upper.y),
             ), // upper right
+            (lower, atlas_lower), // lower right
             (
-                P2::new(
-                    self.width_offset + origin_x + width_in_px as f32,
-                    (vertical_offset + height_in_px) as f32,
-                ),
-                P2::new(x + width, y + height),
-            ), // lower right
-            (
-                P2::new(
-                    self.width_offset + origin_x,
-                    (vertical_offset + height_in_px) as f32,
-                ),
-                P2::new(x, y + height),
+                P2::new(upper.x, lower.y),
+                P2::new(atlas_upper.x, atlas_lower.y),
             ), // lower left
         ];
-        for p in new_vertices.iter_mut() {
-            p.0.x += self.origin.x;
-            p.0.y += self.origin.y;
-        }
         self.gl_vertices
             .append(&mut new_vertices, &mut vec![0, 1, 2, 0, 3, 2], false);
-        self.width_offset += rect.get("advance").unwrap().as_i64().unwrap() as f32;
+        self.width_offset += glyph.advance;
     }
 }
 
@@ -186,12 +150,10 @@ impl ExplainObject for Text {
             gl::BindTexture(gl::TEXTURE_2D, self.texture);
         }
 
-        let mut transform_to_use = ZoomTransform::does_nothing();
-        self.zoom_transform.transform_other(&mut transform_to_use);
-        camera.transform_other(&mut transform_to_use);
+        let transform_to_use = self.zoom_transform.relative_to(camera);
 
         shaders.text.write_mat4("projection", projection);
-        transform_to_use.write_to_shader(&shaders.text);
+        shaders.text.write_transform(&transform_to_use);
         self.gl_vertices.draw();
     }
 
@@ -218,7 +180,12 @@ impl ExplainObject for Text {
         }
         false
     }
-    fn get_as_type(&self) -> TypedExplainObject {
-        TypedExplainObject::Text((*self).clone())
+    fn bounding_box(&self) -> Option<Rect> {
+        let points = self.gl_vertices.data().iter().map(|v| na::convert(v.0));
+        Rect::around_points(points, &self.zoom_transform)
+    }
+
+    fn transform(&self) -> &ZoomTransform {
+        &self.zoom_transform
     }
 }
@@ -0,0 +1,724 @@
+use crate::animation::CameraAnimator;
+use crate::grid::{Guides, Snapper};
+use crate::keymap::{Action, Keymap};
+use crate::line::Line;
+use crate::text::Text;
+use crate::{ExplainObject, Shaders};
+use explain_core::fixed::FixedV2;
+use explain_core::geometry::Rect;
+use explain_core::line::Stroke;
+use explain_core::shape;
+use explain_core::util::*;
+use explain_core::world::{ObjectId, World};
+use explain_core::zooming::ZoomTransform;
+use sdl2::event::Event;
+use std::cell::RefCell;
+
+/// How close the cursor has to be to something to pick it, in pixels
+const PICK_DISTANCE: f64 = 4.0;
+/// How many straight segments an ellipse is made of
+const ELLIPSE_SEGMENTS: usize = 64;
+/// How far from the corner of the selection its resize handle can be grabbed, in pixels
+const HANDLE_SIZE: f32 = 6.0;
+/// Smallest a resize can make the selection in one go, so it can't flip over or vanish
+const MIN_SCALE_FACTOR: f64 = 0.01;
+/// The outline drawn around selected objects
+const SELECTION_OUTLINE: Stroke = Stroke {
+    color: [0.2, 0.5, 1.0],
+    width: 1.0,
+};
+
+/// Everything a tool is allowed to change, handed to it along with each event
+pub struct ToolContext<'a> {
+    pub world: &'a mut World,
+    pub camera_animator: &'a mut CameraAnimator,
+    pub keymap: &'a Keymap,
+    /// What new lines look like
+    pub stroke: Stroke,
+    pub guides: Guides,
+    /// Where the mouse was on the screen before the event
+    pub mouse_pos: P2,
+    /// Set by tools when they changed the board, so it gets saved
+    pub changed: bool,
+    /// Set by tools to tell why they didn't do anything, shown in the status bar
+    pub notice: Option<String>,
+}
+
+impl<'a> ToolContext<'a> {
+    /// Where on the screen `e` happened, or the mouse if it isn't a mouse event
+    fn position_of(&self, e: &Event) -> P2 {
+        match *e {
+            Event::MouseButtonDown { x, y, .. }
+            | Event::MouseButtonUp { x, y, .. }
+            | Event::MouseMotion { x, y, .. } => P2::new(x as f32, y as f32),
+            _ => self.mouse_pos,
+        }
+    }
+
+    fn in_world(&self, screen_point: P2) -> FixedV2 {
+        self.world
+            .camera
+            .transform_point_exact(na::convert(screen_point))
+    }
+
+    /// Snaps to what the guides say to, other than the objects in `ignoring`
+    fn snapper(&self, ignoring: &[ObjectId]) -> Snapper {
+        Snapper::new(self.world, &self.guides, ignoring)
+    }
+
+    /// `screen_point` moved onto whatever close by it snaps to
+    fn snapped(&self, screen_point: P2) -> P2 {
+        let point = self.snapper(&[]).point(self.in_world(screen_point));
+        na::convert(self.world.camera.inverse_transform_exact(point))
+    }
+
+    /// If new objects can go on the current layer, noting why not if they can't
+    fn can_add(&mut self) -> bool {
+        self.notice = self.world.cant_add_reason();
+        self.notice.is_none()
+    }
+
+    /// How far from the cursor something can be and still get picked, in world units
+    fn pick_tolerance(&self) -> f64 {
+        PICK_DISTANCE * self.world.camera.scale()
+    }
+}
+
+/// A way of using the mouse and keyboard on the board, like drawing or erasing. Anything made
+/// with a tool is kept by it until it's finished, then added to the world
+pub trait Tool {
+    /// Called when the tool is picked
+    fn activate(&mut self, _context: &mut ToolContext) {}
+    /// Gets the first look at `e`, which triggers `action` in the key bindings if anything.
+    /// Returns if the event was used up, otherwise it goes on to the global key bindings
+    fn process_event(
+        &mut self,
+        e: &Event,
+        action: Option<Action>,
+        context: &mut ToolContext,
+    ) -> bool;
+    /// Wraps up anything in progress, e.g. before switching to another tool
+    fn finish(&mut self, context: &mut ToolContext);
+    /// If something is in progress that isn't on the board or saved yet
+    fn has_unfinished_work(&self) -> bool;
+    /// The object being made as it would be on the board, if it's still in progress
+    fn in_progress(&self) -> Option<Box<dyn shape::Shape>> {
+        None
+    }
+    /// Draws whatever is in progress on top of the board
+    fn draw_preview(&self, shaders: &Shaders, projection: &na::Matrix4<f32>, world: &World);
+}
+
+/// All of the tools, so they can be switched between
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ToolKind {
+    Pen,
+    Text,
+    Eraser,
+    Select,
+    Pan,
+    Shape(Shape),
+}
+
+impl ToolKind {
+    pub fn from_action(action: Action) -> Option<ToolKind> {
+        match action {
+            Action::PenTool => Some(ToolKind::Pen),
+            Action::TextTool => Some(ToolKind::Text),
+            Action::EraserTool => Some(ToolKind::Eraser),
+            Action::SelectTool => Some(ToolKind::Select),
+            Action::PanTool => Some(ToolKind::Pan),
+            Action::StraightLineTool => Some(ToolKind::Shape(Shape::StraightLine)),
+            Action::RectangleTool => Some(ToolKind::Shape(Shape::Rectangle)),
+            Action::EllipseTool => Some(ToolKind::Shape(Shape::Ellipse)),
+            _ => None,
+        }
+    }
+
+    /// The action that switches to this tool
+    pub fn action(self) -> Action {
+        match self {
+            ToolKind::Pen => Action::PenTool,
+            ToolKind::Text => Action::TextTool,
+            ToolKind::Eraser => Action::EraserTool,
+            ToolKind::Select => Action::SelectTool,
+            ToolKind::Pan => Action::PanTool,
+            ToolKind::Shape(Shape::StraightLine) => Action::StraightLineTool,
+            ToolKind::Shape(Shape::Rectangle) => Action::RectangleTool,
+            ToolKind::Shape(Shape::Ellipse) => Action::EllipseTool,
+        }
+    }
+
+    pub fn create(self) -> Box<dyn Tool> {
+        match self {
+            ToolKind::Pen => Box::new(Pen { line: None }),
+            ToolKind::Text => Box::new(TextTool { text: None }),
+            ToolKind::Eraser => Box::new(Eraser { erasing: false }),
+            ToolKind::Select => Box::new(Select {
+                dragging: None,
+                scaling: None,
+                moved: false,
+                outlines: RefCell::new(None),
+            }),
+            ToolKind::Pan => Box::new(Pan { panning: false }),
+            ToolKind::Shape(shape) => Box::new(ShapeTool {
+                shape,
+                start: None,
+                preview: None,
+            }),
+        }
+    }
+}
+
+/// Draws lines following the mouse while it's held down
+pub struct Pen {
+    line: Option<Line>,
+}
+
+impl Tool for Pen {
+    fn process_event(
+        &mut self,
+        e: &Event,
+        action: Option<Action>,
+        context: &mut ToolContext,
+    ) -> bool {
+        if action == Some(Action::UseTool) {
+            self.finish(context);
+            if !context.can_add() {
+                return true;
+            }
+            let mut line = Line::new(context.stroke);
+            line.set_transform(context.world.camera.clone());
+            self.line = Some(line);
+            return true;
+        }
+        if context.keymap.releases(e, Action::UseTool) {
+            self.finish(context);
+            return true;
+        }
+        match &mut self.line {
+            Some(line) => {
+                // lines are drawn in screen space, which the camera maps into the world
+                line.set_transform(context.world.camera.clone());
+                line.process_event(e)
+            }
+            None => false,
+        }
+    }
+
+    fn finish(&mut self, context: &mut ToolContext) {
+        if let Some(line) = self.line.take() {
+            // a click without moving doesn't draw anything
+            if line.bounding_box().is_some() {
+                context.world.add(Box::new(line.to_saved()));
+                context.changed = true;
+            }
+        }
+    }
+
+    fn has_unfinished_work(&self) -> bool {
+        self.line.is_some()
+    }
+
+    fn in_progress(&self) -> Option<Box<dyn shape::Shape>> {
+        let line = self.line.as_ref()?;
+        Some(Box::new(line.to_saved()))
+    }
+
+    fn draw_preview(&self, shaders: &Shaders, projection: &na::Matrix4<f32>, world: &World) {
+        if let Some(line) = &self.line {
+            line.draw(shaders, projection, &world.camera);
+        }
+    }
+}
+
+/// Types text wherever it's clicked, or under the mouse when the tool is picked
+pub struct TextTool {
+    text: Option<Text>,
+}
+
+impl TextTool {
+    fn start_at(&mut self, screen_point: P2, context: &mut ToolContext) {
+        self.finish(context);
+        if !context.can_add() {
+            return;
+        }
+        let mut text = Text::new(context.snapped(screen_point));
+        text.set_transform(context.world.camera.clone());
+        self.text = Some(text);
+    }
+}
+
+impl Tool for TextTool {
+    fn activate(&mut self, context: &mut ToolContext) {
+        self.start_at(context.mouse_pos, context);
+    }
+
+    fn process_event(
+        &mut self,
+        e: &Event,
+        action: Option<Action>,
+        context: &mut ToolContext,
+    ) -> bool {
+        match action {
+            Some(Action::UseTool) => {
+                self.start_at(context.position_of(e), context);
+                return true;
+            }
+            Some(Action::FinishObject) if self.text.is_some() => {
+                self.finish(context);
+                return true;
+            }
+            _ => {}
+        }
+        match &mut self.text {
+            Some(text) => {
+                text.set_transform(context.world.camera.clone());
+                text.process_event(e)
+            }
+            None => false,
+        }
+    }
+
+    fn finish(&mut self, context: &mut ToolContext) {
+        if let Some(text) = self.text.take() {
+            if text.bounding_box().is_some() {
+                context.world.add(Box::new(text.to_saved()));
+                context.changed = true;
+            }
+        }
+    }
+
+    fn has_unfinished_work(&self) -> bool {
+        self.text.is_some()
+    }
+
+    fn in_progress(&self) -> Option<Box<dyn shape::Shape>> {
+        let text = self.text.as_ref()?;
+        Some(Box::new(text.to_saved()))
+    }
+
+    fn draw_preview(&self, shaders: &Shaders, projection: &na::Matrix4<f32>, world: &World) {
+        if let Some(text) = &self.text {
+            text.draw(shaders, projection, &world.camera);
+        }
+    }
+}
+
+/// Removes whatever the mouse touches while it's held down
+pub struct Eraser {
+    erasing: bool,
+}
+
+impl Eraser {
+    fn erase_at(&self, screen_point: P2, context: &mut ToolContext) {
+        let point = context.in_world(screen_point);
+        let tolerance = context.pick_tolerance();
+        while let Some(id) = context.world.object_at(point, tolerance) {
+            context.world.remove(id);
+            context.changed = true;
+        }
+    }
+}
+
+impl Tool for Eraser {
+    fn process_event(
+        &mut self,
+        e: &Event,
+        action: Option<Action>,
+        context: &mut ToolContext,
+    ) -> bool {
+        if action == Some(Action::UseTool) {
+            self.erasing = true;
+            self.erase_at(context.position_of(e), context);
+            return true;
+        }
+        if context.keymap.releases(e, Action::UseTool) {
+            self.erasing = false;
+            return true;
+        }
+        match *e {
+            Event::MouseMotion { .. } if self.erasing => {
+                self.erase_at(context.position_of(e), context);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn finish(&mut self, _context: &mut ToolContext) {
+        self.erasing = false;
+    }
+
+    fn has_unfinished_work(&self) -> bool {
+        false
+    }
+
+    fn draw_preview(&self, _shaders: &Shaders, _projection: &na::Matrix4<f32>, _world: &World) {}
+}
+
+/// Selects objects by clicking on them, moves the selection by dragging it and resizes it by
+/// dragging the handle on its bottom right corner
+pub struct Select {
+    dragging: Option<Dragging>,
+    scaling: Option<Scaling>,
+    moved: bool,
+    /// Kept from the last time they were drawn, so they're only made again when the selection
+    /// changes
+    outlines: RefCell<Option<Outlines>>,
+}
+
+/// The outlines around the selected objects and the resize handle, for the boxes they were made
+/// from
+struct Outlines {
+    boxes: Vec<Rect>,
+    /// The handle is the same size on the screen at any zoom, so it's made again when zooming
+    scale: f64,
+    lines: Vec<Line>,
+}
+
+impl Outlines {
+    fn new(boxes: Vec<Rect>, camera: &ZoomTransform) -> Self {
+        let outline = |min: P2, max: P2| {
+            let mut line = Line::new(SELECTION_OUTLINE);
+            line.set_points(&Shape::Rectangle.outline(min, max));
+            line.set_transform(camera.clone());
+            line
+        };
+        let mut lines: Vec<Line> = boxes
+            .iter()
+            .map(|rect| {
+                outline(
+                    na::convert(camera.inverse_transform_exact(rect.min)),
+                    na::convert(camera.inverse_transform_exact(rect.max)),
+                )
+            })
+            .collect();
+        if let Some(selection) = boxes.iter().copied().reduce(|a, b| a.union(&b)) {
+            let handle = handle_position(&selection, camera);
+            let corner = V2::new(HANDLE_SIZE, HANDLE_SIZE) / 2.0;
+            lines.push(outline(handle - corner, handle + corner));
+        }
+        Self {
+            boxes,
+            scale: camera.scale(),
+            lines,
+        }
+    }
+}
+
+/// A move of the selection in progress
+struct Dragging {
+    /// Where the selection was grabbed
+    grabbed: FixedV2,
+    /// Where the selection was before moving, which is what gets snapped
+    bounds: Rect,
+    /// What the selected objects' transforms were before moving
+    originals: Vec<(ObjectId, ZoomTransform)>,
+}
+
+/// A resize of the selection in progress
+struct Scaling {
+    /// The corner of the selection that stays put
+    anchor: FixedV2,
+    /// Where the handle was grabbed
+    grabbed: FixedV2,
+    /// What the selected objects' transforms were before resizing
+    originals: Vec<(ObjectId, ZoomTransform)>,
+}
+
+/// Everything selected in `world`, `None` if that's nothing
+fn selection_box(world: &World) -> Option<Rect> {
+    world
+        .selected()
+        .filter_map(|o| o.bounding_box())
+        .fold(None, |acc: Option<Rect>, r| match acc {
+            Some(acc) => Some(acc.union(&r)),
+            None => Some(r),
+        })
+}
+
+/// The selected objects along with where they are, so they can be put back there
+fn selected_transforms(world: &World) -> Vec<(ObjectId, ZoomTransform)> {
+    world
+        .selection
+        .iter()
+        .filter_map(|&id| Some((id, world.object(id)?.transform().clone())))
+        .collect()
+}
+
+/// Where the resize handle of `selection` is on the screen
+fn handle_position(selection: &Rect, camera: &ZoomTransform) -> P2 {
+    na::convert(camera.inverse_transform_exact(selection.max))
+}
+
+impl Tool for Select {
+    fn process_event(
+        &mut self,
+        e: &Event,
+        action: Option<Action>,
+        context: &mut ToolContext,
+    ) -> bool {
+        if action == Some(Action::UseTool) {
+            let screen_point = context.position_of(e);
+            let point = context.in_world(screen_point);
+            let tolerance = context.pick_tolerance();
+            let world = &mut *context.world;
+            if let Some(selection) = selection_box(world) {
+                let handle = handle_position(&selection, &world.camera);
+                if (handle - screen_point).amax() <= HANDLE_SIZE {
+                    self.scaling = Some(Scaling {
+                        anchor: selection.min,
+                        grabbed: point,
+                        originals: selected_transforms(world),
+                    });
+                    return true;
+                }
+            }
+            match world.object_at(point, tolerance) {
+                Some(id) => {
+                    // grabbing something already selected drags the whole selection
+                    if !world.selection.contains(&id) {
+                        world.selection = vec![id];
+                    }
+                    self.dragging = selection_box(world).map(|bounds| Dragging {
+                        grabbed: point,
+                        bounds,
+                        originals: selected_transforms(world),
+                    });
+                }
+                None => world.selection.clear(),
+            }
+            return true;
+        }
+        if context.keymap.releases(e, Action::UseTool) {
+            self.finish(context);
+            return true;
+        }
+        match *e {
+            Event::MouseMotion { .. } if self.scaling.is_some() => {
+                let point = context.in_world(context.position_of(e));
+                let point = context.snapper(&context.world.selection).point(point);
+                let scaling = self.scaling.as_ref().unwrap();
+                let grabbed = (scaling.grabbed - scaling.anchor).to_f64();
+                let now = (point - scaling.anchor).to_f64();
+                // how far the handle moved along the diagonal it was grabbed on
+                let factor = (now.dot(&grabbed) / grabbed.norm_squared()).max(MIN_SCALE_FACTOR);
+                if factor.is_finite() {
+                    for (id, original) in scaling.originals.iter() {
+                        if let Some(object) = context.world.object_mut(*id) {
+                            object.set_transform(original.scaled_about(scaling.anchor, factor));
+                        }
+                    }
+                    self.moved = true;
+                }
+                true
+            }
+            Event::MouseMotion { .. } if self.dragging.is_some() => {
+                let point = context.in_world(context.position_of(e));
+                let dragging = self.dragging.as_ref().unwrap();
+                let by = point - dragging.grabbed;
+                let snapper = context.snapper(&context.world.selection);
+                let by = by + snapper.offset(&dragging.bounds.translated(by));
+                for (id, original) in dragging.originals.iter() {
+                    if let Some(object) = context.world.object_mut(*id) {
+                        object.set_transform(original.translated(by));
+                    }
+                }
+                self.moved = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn finish(&mut self, context: &mut ToolContext) {
+        self.dragging = None;
+        self.scaling = None;
+        if self.moved {
+            self.moved = false;
+            context.changed = true;
+        }
+    }
+
+    fn has_unfinished_work(&self) -> bool {
+        self.moved
+    }
+
+    fn draw_preview(&self, shaders: &Shaders, projection: &na::Matrix4<f32>, world: &World) {
+        let boxes: Vec<Rect> = world.selected().filter_map(|o| o.bounding_box()).collect();
+        let mut outlines = self.outlines.borrow_mut();
+        let outdated = match &*outlines {
+            Some(o) => o.boxes != boxes || o.scale != world.camera.scale(),
+            None => true,
+        };
+        if outdated {
+            *outlines = Some(Outlines::new(boxes, &world.camera));
+        }
+        for line in outlines.iter().flat_map(|o| o.lines.iter()) {
+            line.draw(shaders, projection, &world.camera);
+        }
+    }
+}
+
+/// Pans the board by dragging it around
+pub struct Pan {
+    panning: bool,
+}
+
+impl Tool for Pan {
+    fn process_event(
+        &mut self,
+        e: &Event,
+        action: Option<Action>,
+        context: &mut ToolContext,
+    ) -> bool {
+        if action == Some(Action::UseTool) {
+            self.panning = true;
+            // grabbing the board stops it from gliding
+            context.camera_animator.stop();
+            return true;
+        }
+        if context.keymap.releases(e, Action::UseTool) {
+            self.finish(context);
+            return true;
+        }
+        match *e {
+            Event::MouseMotion { xrel, yrel, .. } if self.panning => {
+                context
+                    .camera_animator
+                    .drag(-V2f64::new(xrel as f64, yrel as f64));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn finish(&mut self, context: &mut ToolContext) {
+        if self.panning {
+            self.panning = false;
+            context.camera_animator.release();
+        }
+    }
+
+    fn has_unfinished_work(&self) -> bool {
+        false
+    }
+
+    fn draw_preview(&self, _shaders: &Shaders, _projection: &na::Matrix4<f32>, _world: &World) {}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Shape {
+    StraightLine,
+    Rectangle,
+    Ellipse,
+}
+
+impl Shape {
+    /// Points along the shape dragged out from `from` to `to`. Closed shapes end where they start
+    pub fn outline(self, from: P2, to: P2) -> Vec<P2> {
+        match self {
+            Shape::StraightLine => vec![from, to],
+            Shape::Rectangle => vec![from, P2::new(to.x, from.y), to, P2::new(from.x, to.y), from],
+            Shape::Ellipse => {
+                let center = na::center(&from, &to);
+                let radii = (to - from) / 2.0;
+                (0..=ELLIPSE_SEGMENTS)
+                    .map(|i| {
+                        let angle = i as f32 / ELLIPSE_SEGMENTS as f32 * std::f32::consts::TAU;
+                        center + V2::new(radii.x * angle.cos(), radii.y * angle.sin())
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Drags out straight lines, rectangles and ellipses
+pub struct ShapeTool {
+    shape: Shape,
+    start: Option<P2>,
+    preview: Option<Line>,
+}
+
+impl Tool for ShapeTool {
+    fn process_event(
+        &mut self,
+        e: &Event,
+        action: Option<Action>,
+        context: &mut ToolContext,
+    ) -> bool {
+        if action == Some(Action::UseTool) {
+            self.finish(context);
+            if !context.can_add() {
+                return true;
+            }
+            self.start = Some(context.snapped(context.position_of(e)));
+            self.preview = Some(Line::new(context.stroke));
+            return true;
+        }
+        if context.keymap.releases(e, Action::UseTool) {
+            self.finish(context);
+            return true;
+        }
+        match (e, self.start, &mut self.preview) {
+            (&Event::MouseMotion { x, y, .. }, Some(start), Some(preview)) => {
+                let end = context.snapped(P2::new(x as f32, y as f32));
+                preview.set_points(&self.shape.outline(start, end));
+                preview.set_transform(context.world.camera.clone());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn finish(&mut self, context: &mut ToolContext) {
+        self.start = None;
+        if let Some(line) = self.preview.take() {
+            if line.bounding_box().is_some() {
+                context.world.add(Box::new(line.to_saved()));
+                context.changed = true;
+            }
+        }
+    }
+
+    fn has_unfinished_work(&self) -> bool {
+        self.preview.is_some()
+    }
+
+    fn in_progress(&self) -> Option<Box<dyn shape::Shape>> {
+        let line = self.preview.as_ref()?;
+        Some(Box::new(line.to_saved()))
+    }
+
+    fn draw_preview(&self, shaders: &Shaders, projection: &na::Matrix4<f32>, world: &World) {
+        if let Some(line) = &self.preview {
+            line.draw(shaders, projection, &world.camera);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closed_shapes_end_where_they_start() {
+        let (from, to) = (P2::new(10.0, 20.0), P2::new(50.0, -4.0));
+        for &shape in [Shape::Rectangle, Shape::Ellipse].iter() {
+            let outline = shape.outline(from, to);
+            assert!((outline[0] - outline[outline.len() - 1]).norm() < 1e-4);
+        }
+        assert_eq!(Shape::StraightLine.outline(from, to), vec![from, to]);
+    }
+
+    #[test]
+    fn ellipses_fit_in_the_dragged_out_box() {
+        let (from, to) = (P2::new(0.0, 0.0), P2::new(40.0, 10.0));
+        for p in Shape::Ellipse.outline(from, to) {
+            let on_unit_circle = V2::new((p.x - 20.0) / 20.0, (p.y - 5.0) / 5.0);
+            assert!((on_unit_circle.norm() - 1.0).abs() < 1e-4);
+        }
+    }
+}
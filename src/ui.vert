@@ -0,0 +1,14 @@
+#version 330 core
+
+layout (location = 0) in vec2 Position; // in screen coordinates
+layout (location = 1) in vec4 aColor;
+
+out vec4 vertexColor;
+
+uniform mat4 projection;
+
+void main()
+{
+    gl_Position = projection * vec4(Position, 0.0, 1.0);
+    vertexColor = aColor;
+}